@@ -17,14 +17,18 @@ fn main() {
 
     let mut canvas = match bly::create_canvas(&window) {
         Ok(c) => c,
-        Err(_) => {
-            panic!("Can't initialize Bly!");
+        Err(e) => {
+            panic!("Can't initialize Bly! {}", e);
         }
     };
+    let size = window.inner_size();
+    canvas.resize(size.width, size.height);
 
-    canvas.draw(|painter| {
-        painter.clear(bly::Color::WhiteGray);
-    });
+    canvas
+        .draw(|painter| {
+            painter.clear(bly::Color::WHITE_GRAY);
+        })
+        .unwrap();
 
     event_loop.run(move |event, _, control_flow| {
         control_flow.set_wait();
@@ -34,6 +38,10 @@ fn main() {
                 event: WindowEvent::CloseRequested,
                 window_id,
             } if window_id == window.id() => control_flow.set_exit(),
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                window_id,
+            } if window_id == window.id() => canvas.resize(size.width, size.height),
             Event::MainEventsCleared => {
                 window.request_redraw();
             }
@@ -2,7 +2,7 @@
 
 extern crate env_logger as logger;
 
-use bly::{Color, Point2};
+use bly::{Color, Point, Size};
 
 use std::env;
 
@@ -26,14 +26,18 @@ fn main() {
 
     let mut canvas = match bly::create_canvas(&window) {
         Ok(b) => b,
-        Err(_) => {
-            panic!("Can't initialize Bly!");
+        Err(e) => {
+            panic!("Can't initialize Bly! {}", e);
         }
     };
+    let size = window.inner_size();
+    canvas.resize(size.width, size.height);
 
-    canvas.draw(|painter| {
-        painter.clear(Color::WhiteGray);
-    });
+    canvas
+        .draw(|painter| {
+            painter.clear(Color::WHITE_GRAY);
+        })
+        .unwrap();
 
     event_loop.run(move |event, _, control_flow| {
         control_flow.set_wait();
@@ -42,58 +46,66 @@ fn main() {
                 event: WindowEvent::CloseRequested,
                 window_id,
             } if window_id == window.id() => control_flow.set_exit(),
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                window_id,
+            } if window_id == window.id() => canvas.resize(size.width, size.height),
 
-            Event::MainEventsCleared => canvas.draw(|painter| {
-                painter.clear(Color::WhiteGray);
+            Event::MainEventsCleared => {
+                if let Err(e) = canvas.draw(|painter| {
+                    painter.clear(Color::WHITE_GRAY);
 
-                painter.rectangle(
-                    Point2::new(20.0, 20.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(1.0, 1.0, 1.0, 1.0),
-                );
-                painter.rectangle(
-                    Point2::new(180.0, 20.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(0.5, 0.5, 0.5, 1.0),
-                );
-                painter.rectangle(
-                    Point2::new(340.0, 20.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(0.0, 0.0, 0.0, 1.0),
-                );
+                    painter.rectangle(
+                        Point::new(20.0, 20.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 1.0, 1.0, 1.0),
+                    );
+                    painter.rectangle(
+                        Point::new(180.0, 20.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.5, 0.5, 0.5, 1.0),
+                    );
+                    painter.rectangle(
+                        Point::new(340.0, 20.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 0.0, 0.0, 1.0),
+                    );
 
-                painter.rectangle(
-                    Point2::new(20.0, 180.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(1.0, 0.0, 0.0, 1.0),
-                );
-                painter.rectangle(
-                    Point2::new(180.0, 180.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(0.0, 1.0, 0.0, 1.0),
-                );
-                painter.rectangle(
-                    Point2::new(340.0, 180.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(0.0, 0.0, 1.0, 1.0),
-                );
+                    painter.rectangle(
+                        Point::new(20.0, 180.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 0.0, 0.0, 1.0),
+                    );
+                    painter.rectangle(
+                        Point::new(180.0, 180.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 1.0, 0.0, 1.0),
+                    );
+                    painter.rectangle(
+                        Point::new(340.0, 180.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 0.0, 1.0, 1.0),
+                    );
 
-                painter.rectangle(
-                    Point2::new(20.0, 340.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(1.0, 1.0, 0.0, 1.0),
-                );
-                painter.rectangle(
-                    Point2::new(180.0, 340.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(0.0, 1.0, 1.0, 1.0),
-                );
-                painter.rectangle(
-                    Point2::new(340.0, 340.0),
-                    Point2::new(150.0, 150.0),
-                    Color::Rgba(1.0, 0.0, 1.0, 1.0),
-                );
-            }),
+                    painter.rectangle(
+                        Point::new(20.0, 340.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 1.0, 0.0, 1.0),
+                    );
+                    painter.rectangle(
+                        Point::new(180.0, 340.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 1.0, 1.0, 1.0),
+                    );
+                    painter.rectangle(
+                        Point::new(340.0, 340.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 0.0, 1.0, 1.0),
+                    );
+                }) {
+                    eprintln!("Failed to draw: {}", e);
+                }
+            }
             _ => (),
         }
     });
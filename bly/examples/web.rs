@@ -1,6 +1,6 @@
 #![allow(clippy::single_match)]
 
-use bly::{Color, Point2};
+use bly::{Color, Point, Size};
 use winit::{
     event::{Event, WindowEvent},
     event_loop::EventLoop,
@@ -23,8 +23,8 @@ pub fn main() {
 
     let mut canvas = match bly::create_canvas(&window) {
         Ok(c) => c,
-        Err(_) => {
-            panic!("")
+        Err(e) => {
+            panic!("Can't initialize Bly! {}", e)
         }
     };
 
@@ -37,64 +37,67 @@ pub fn main() {
                 window_id,
             } if window_id == window.id() => control_flow.set_exit(),
             Event::MainEventsCleared => {
-                canvas.draw(|painter| {
-                    painter.clear(Color::WhiteGray);
+                let result = canvas.draw(|painter| {
+                    painter.clear(Color::WHITE_GRAY);
 
                     painter.rectangle(
-                        Point2::new(20.0, 20.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(1.0, 1.0, 1.0, 1.0),
+                        Point::new(20.0, 20.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 1.0, 1.0, 1.0),
                     );
                     painter.rectangle(
-                        Point2::new(180.0, 20.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(0.5, 0.5, 0.5, 1.0),
+                        Point::new(180.0, 20.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.5, 0.5, 0.5, 1.0),
                     );
                     painter.rectangle(
-                        Point2::new(340.0, 20.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(0.0, 0.0, 0.0, 1.0),
+                        Point::new(340.0, 20.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 0.0, 0.0, 1.0),
                     );
 
                     painter.rectangle(
-                        Point2::new(20.0, 180.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(1.0, 0.0, 0.0, 1.0),
+                        Point::new(20.0, 180.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 0.0, 0.0, 1.0),
                     );
                     painter.rectangle(
-                        Point2::new(180.0, 180.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(0.0, 1.0, 0.0, 1.0),
+                        Point::new(180.0, 180.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 1.0, 0.0, 1.0),
                     );
                     painter.rectangle(
-                        Point2::new(340.0, 180.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(0.0, 0.0, 1.0, 1.0),
+                        Point::new(340.0, 180.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 0.0, 1.0, 1.0),
                     );
 
                     painter.rectangle(
-                        Point2::new(20.0, 340.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(1.0, 1.0, 0.0, 1.0),
+                        Point::new(20.0, 340.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 1.0, 0.0, 1.0),
                     );
                     painter.rectangle(
-                        Point2::new(180.0, 340.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(0.0, 1.0, 1.0, 1.0),
+                        Point::new(180.0, 340.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(0.0, 1.0, 1.0, 1.0),
                     );
                     painter.rectangle(
-                        Point2::new(340.0, 340.0),
-                        Point2::new(150.0, 150.0),
-                        Color::Rgba(1.0, 0.0, 1.0, 1.0),
+                        Point::new(340.0, 340.0),
+                        Size::new(150.0, 150.0),
+                        Color::rgba(1.0, 0.0, 1.0, 1.0),
                     );
 
                     painter.line(
-                        Point2::new(0.0, 0.0),
-                        Point2::new(500.0, 50.0),
+                        Point::new(0.0, 0.0),
+                        Point::new(500.0, 50.0),
                         1.0,
-                        Color::Red,
+                        Color::RED,
                     );
                 });
+                if let Err(e) = result {
+                    error!("Failed to draw: {}", e);
+                }
 
                 window.request_redraw();
             }
@@ -0,0 +1,71 @@
+//! How drawn colors are combined with the colors already on the canvas
+
+use crate::Error;
+
+/// # Blend mode
+/// Combines the color being drawn (source) with the color on the canvas (backdrop).
+/// The modes follow the separable blend modes of the W3C Compositing and Blending specification,
+/// the result is then composited over the backdrop like Normal
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// The source is drawn over the backdrop
+    #[default]
+    Normal,
+    /// Multiplies the colors, the result is never lighter
+    Multiply,
+    /// Inverse of multiplying the inverted colors, the result is never darker
+    Screen,
+    /// Multiply or Screen depending on the backdrop, keeping its highlights and shadows
+    Overlay,
+    /// Keeps the darker of both colors
+    Darken,
+    /// Keeps the lighter of both colors
+    Lighten,
+    /// Brightens the backdrop to reflect the source
+    ColorDodge,
+    /// Darkens the backdrop to reflect the source
+    ColorBurn,
+    /// Multiply or Screen depending on the source
+    HardLight,
+    /// A softer version of HardLight
+    SoftLight,
+    /// Subtracts the darker color from the lighter one
+    Difference,
+    /// Like Difference with less contrast
+    Exclusion,
+    /// Adds the colors (and alphas), saturating at white
+    Add,
+}
+
+/// # Blend space
+/// Color space in which drawn colors are combined with the canvas,
+/// and in which gradients and scaled images are interpolated.
+/// Colors, images and the pixels read back from a canvas are sRGB in both spaces
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum BlendSpace {
+    /// The sRGB encoded values are blended, like CSS and most 2D libraries do
+    #[default]
+    Srgb,
+    /// Colors are converted to linear light to be blended.
+    /// Mixed colors keep their brightness, e.g. anti-aliased edges and gradients
+    /// between saturated colors do not look darker in the middle
+    Linear,
+}
+
+/// Result of set_blend_space() for the backends that only blend in sRGB
+#[cfg_attr(
+    not(any(
+        feature = "xlib",
+        feature = "xcb",
+        feature = "wayland",
+        target_os = "windows",
+        target_arch = "wasm32"
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn srgb_only(space: BlendSpace) -> Result<(), Error> {
+    match space {
+        BlendSpace::Srgb => Ok(()),
+        BlendSpace::Linear => Err(Error::Unsupported("blending in linear light")),
+    }
+}
@@ -0,0 +1,729 @@
+//! Drawing with a Cairo context, shared by the Xlib and XCB backends
+
+use crate::blend::srgb_only;
+use crate::cairo::util;
+use crate::cairo::util::ImageSurface;
+use crate::image::ImageData;
+use crate::paint::{color_rgba, premultiplied_stops};
+use crate::{
+    Backend, BlendMode, BlendSpace, Error, ExtendMode, GlyphRun, GradientStop, Image, ImageFilter,
+    LineCap, LineJoin, Paint, Path, PathElement, Point, Rect, StrokeStyle, Transform2D,
+};
+use cairo_sys::{
+    cairo_arc, cairo_clip, cairo_close_path, cairo_create, cairo_curve_to, cairo_destroy,
+    cairo_fill, cairo_get_matrix, cairo_get_operator, cairo_get_source, cairo_identity_matrix,
+    cairo_line_to, cairo_matrix_t, cairo_move_to, cairo_new_path, cairo_new_sub_path, cairo_paint,
+    cairo_paint_with_alpha, cairo_pattern_add_color_stop_rgba, cairo_pattern_create_linear,
+    cairo_pattern_create_radial, cairo_pattern_destroy, cairo_pattern_set_extend,
+    cairo_pattern_set_filter, cairo_pattern_t, cairo_pop_group_to_source, cairo_push_group,
+    cairo_rectangle, cairo_restore, cairo_save, cairo_scale, cairo_set_dash, cairo_set_line_cap,
+    cairo_set_line_join, cairo_set_line_width, cairo_set_matrix, cairo_set_miter_limit,
+    cairo_set_operator, cairo_set_source, cairo_set_source_rgba, cairo_set_source_surface,
+    cairo_status, cairo_stroke, cairo_surface_create_for_rectangle, cairo_surface_create_similar,
+    cairo_surface_destroy, cairo_surface_flush, cairo_surface_get_content, cairo_surface_status,
+    cairo_surface_t, cairo_t, cairo_translate, EXTEND_PAD, EXTEND_REFLECT, EXTEND_REPEAT,
+    FILTER_BILINEAR, FILTER_NEAREST, LINE_CAP_BUTT, LINE_CAP_ROUND, LINE_CAP_SQUARE,
+    LINE_JOIN_BEVEL, LINE_JOIN_MITER, LINE_JOIN_ROUND, OPERATOR_ADD, OPERATOR_COLOR_BURN,
+    OPERATOR_COLOR_DODGE, OPERATOR_DARKEN, OPERATOR_DIFFERENCE, OPERATOR_EXCLUSION,
+    OPERATOR_HARD_LIGHT, OPERATOR_LIGHTEN, OPERATOR_MULTIPLY, OPERATOR_OVER, OPERATOR_OVERLAY,
+    OPERATOR_SCREEN, OPERATOR_SOFT_LIGHT, OPERATOR_SOURCE, STATUS_SUCCESS,
+};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::ffi::{c_double, c_int};
+use std::sync::{Arc, Weak};
+
+/// A window of a display server that Cairo can draw on
+pub(crate) trait Target {
+    /// Current size of the window in pixels, asked to the server
+    unsafe fn size(&self) -> (u32, u32);
+
+    /// Creates a Cairo surface drawing on the window, which is width x height pixels
+    unsafe fn create_surface(&self, width: u32, height: u32) -> *mut cairo_surface_t;
+
+    /// Tells a surface made by create_surface() that the window is now width x height pixels
+    unsafe fn resize_surface(&self, surface: *mut cairo_surface_t, width: u32, height: u32);
+
+    /// Sends the drawing requests queued by Cairo to the server
+    unsafe fn flush(&self);
+}
+
+/// Implements every primitive of Backend with a Cairo context drawing on a Target.  
+/// Frames are drawn into a back buffer and copied to the window in one step by flush(),
+/// so that unfinished frames are never visible
+pub(crate) struct Context<T: Target> {
+    target: T,
+
+    width: u32,
+    height: u32,
+
+    /// Surface of the window
+    surface: *mut cairo_surface_t,
+    /// Surface the frames are drawn into, which keeps the last frame between draws
+    back: *mut cairo_surface_t,
+    /// Context drawing into back
+    cairo: *mut cairo_t,
+
+    /// Images uploaded by draw_image(), until the last clone of the image is dropped
+    images: HashMap<u64, (Weak<ImageData>, ImageSurface)>,
+    /// Opacity and blend mode of the pushed layers
+    layers: Vec<(f32, BlendMode)>,
+}
+
+impl<T: Target> Context<T> {
+    pub(crate) unsafe fn new(target: T) -> Result<Self, Error> {
+        let (width, height) = target.size();
+        let surface = target.create_surface(width, height);
+
+        let status = cairo_surface_status(surface);
+        if status != STATUS_SUCCESS {
+            cairo_surface_destroy(surface);
+            return Err(Error::SurfaceCreationFailed(util::status_to_string(status)));
+        }
+
+        info!("A Cairo surface has been created.");
+
+        let back = create_back_buffer(surface, width, height);
+        let status = cairo_surface_status(back);
+        if status != STATUS_SUCCESS {
+            cairo_surface_destroy(back);
+            cairo_surface_destroy(surface);
+            return Err(Error::SurfaceCreationFailed(util::status_to_string(status)));
+        }
+
+        let cairo = cairo_create(back);
+        let status = cairo_status(cairo);
+        if status != STATUS_SUCCESS {
+            cairo_destroy(cairo);
+            cairo_surface_destroy(back);
+            cairo_surface_destroy(surface);
+            return Err(Error::ContextCreationFailed(util::status_to_string(status)));
+        }
+
+        Ok(Self {
+            target,
+            width,
+            height,
+            surface,
+            back,
+            cairo,
+            images: HashMap::new(),
+            layers: Vec::new(),
+        })
+    }
+}
+
+impl<T: Target> Backend for Context<T> {
+    #[inline]
+    unsafe fn begin_draw(&mut self) -> Result<(), Error> {
+        let (width, height) = self.target.size();
+        self.scale(width, height);
+        self.images.retain(|_, (owner, _)| owner.strong_count() > 0);
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn flush(&mut self) -> Result<(), Error> {
+        copy_surface(self.back, self.surface);
+        cairo_surface_flush(self.surface);
+        self.target.flush();
+        let status = cairo_status(self.cairo);
+        if status != STATUS_SUCCESS {
+            return Err(Error::DrawingFailed(util::status_to_string(status)));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn get_display_size(&mut self) -> (u32, u32) {
+        self.target.size()
+    }
+
+    #[inline]
+    unsafe fn resize(&mut self, _width: u32, _height: u32) {
+        // The size of the window is queried when it is needed
+    }
+
+    #[inline]
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error> {
+        // Cairo composites the encoded values of its surfaces
+        srgb_only(space)
+    }
+
+    #[inline]
+    unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        let (width, height) = (self.width, self.height);
+
+        // The whole window is filled whatever the transform is.
+        // The color replaces the pixels like on the other backends,
+        // so that a window with an alpha channel can be cleared to transparent
+        cairo_save(self.cairo);
+        cairo_identity_matrix(self.cairo);
+        cairo_set_operator(self.cairo, OPERATOR_SOURCE);
+        cairo_set_source_rgba(
+            self.cairo,
+            r as c_double,
+            g as c_double,
+            b as c_double,
+            a as c_double,
+        );
+        cairo_rectangle(
+            self.cairo,
+            0 as c_double,
+            0 as c_double,
+            width as c_double,
+            height as c_double,
+        );
+        cairo_fill(self.cairo);
+        cairo_restore(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn set_transform(&mut self, transform: &Transform2D) {
+        // Cairo stops drawing for good on a matrix that cannot be inverted,
+        // so such a transform shrinks everything into an invisible point instead
+        let transform = match transform.inverse() {
+            Some(_) => *transform,
+            None => Transform2D::new(1e-9, 0.0, 0.0, 1e-9, transform.m31, transform.m32),
+        };
+        let matrix = cairo_matrix_t {
+            xx: transform.m11 as c_double,
+            yx: transform.m12 as c_double,
+            xy: transform.m21 as c_double,
+            yy: transform.m22 as c_double,
+            x0: transform.m31 as c_double,
+            y0: transform.m32 as c_double,
+        };
+        cairo_set_matrix(self.cairo, &matrix);
+    }
+
+    #[inline]
+    unsafe fn push_clip_rect(&mut self, rect: Rect) {
+        cairo_save(self.cairo);
+        cairo_new_path(self.cairo);
+        cairo_rectangle(
+            self.cairo,
+            rect.x as c_double,
+            rect.y as c_double,
+            rect.width as c_double,
+            rect.height as c_double,
+        );
+        cairo_clip(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        cairo_save(self.cairo);
+        self.rounded_rectangle_path(rect, radius);
+        cairo_clip(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn push_clip_path(&mut self, path: &Path) {
+        cairo_save(self.cairo);
+        self.set_path(path);
+        cairo_clip(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn pop_clip(&mut self) {
+        // Restoring the state also brings back the matrix and the operator,
+        // which may have changed since the push
+        let mut matrix: cairo_matrix_t = std::mem::zeroed();
+        cairo_get_matrix(self.cairo, &mut matrix);
+        let operator = cairo_get_operator(self.cairo);
+        cairo_restore(self.cairo);
+        cairo_set_matrix(self.cairo, &matrix);
+        cairo_set_operator(self.cairo, operator);
+    }
+
+    #[inline]
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode) {
+        cairo_set_operator(self.cairo, operator(mode));
+    }
+
+    #[inline]
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        self.layers.push((opacity, mode));
+        cairo_push_group(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn pop_layer(&mut self) {
+        let (opacity, mode) = match self.layers.pop() {
+            Some(layer) => layer,
+            None => return,
+        };
+        // Popping the group also brings back the matrix and the operator (see pop_clip())
+        let mut matrix: cairo_matrix_t = std::mem::zeroed();
+        cairo_get_matrix(self.cairo, &mut matrix);
+        let current = cairo_get_operator(self.cairo);
+        cairo_pop_group_to_source(self.cairo);
+        cairo_set_operator(self.cairo, operator(mode));
+        cairo_paint_with_alpha(self.cairo, opacity as c_double);
+        cairo_set_matrix(self.cairo, &matrix);
+        cairo_set_operator(self.cairo, current);
+    }
+
+    #[inline]
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint) {
+        self.set_source(paint);
+
+        cairo_arc(
+            self.cairo,
+            (point.x + radius) as c_double,
+            (point.y + radius) as c_double,
+            radius as c_double,
+            0.0,
+            2.0 * PI,
+        );
+
+        cairo_fill(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint) {
+        self.set_source(paint);
+        cairo_rectangle(
+            self.cairo,
+            rect.x as c_double,
+            rect.y as c_double,
+            rect.width as c_double,
+            rect.height as c_double,
+        );
+        cairo_fill(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint) {
+        self.set_source(paint);
+        self.rounded_rectangle_path(rect, radius);
+        cairo_fill(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint) {
+        self.set_stroke_style(style);
+
+        self.set_source(paint);
+        cairo_new_path(self.cairo);
+        cairo_move_to(self.cairo, point1.x as c_double, point1.y as c_double);
+        cairo_line_to(self.cairo, point2.x as c_double, point2.y as c_double);
+
+        cairo_stroke(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn stroke_ellipse(
+        &mut self,
+        point: Point,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
+    ) {
+        self.set_stroke_style(style);
+        self.set_source(paint);
+
+        cairo_new_path(self.cairo);
+        cairo_arc(
+            self.cairo,
+            (point.x + radius) as c_double,
+            (point.y + radius) as c_double,
+            radius as c_double,
+            0.0,
+            2.0 * PI,
+        );
+        cairo_close_path(self.cairo);
+
+        cairo_stroke(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint) {
+        self.set_stroke_style(style);
+        self.set_source(paint);
+        cairo_new_path(self.cairo);
+        cairo_rectangle(
+            self.cairo,
+            rect.x as c_double,
+            rect.y as c_double,
+            rect.width as c_double,
+            rect.height as c_double,
+        );
+        cairo_stroke(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn stroke_rounded_rectangle(
+        &mut self,
+        rect: Rect,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
+    ) {
+        self.set_stroke_style(style);
+        self.set_source(paint);
+        self.rounded_rectangle_path(rect, radius);
+        cairo_stroke(self.cairo);
+    }
+    #[inline]
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint) {
+        self.set_source(paint);
+        self.set_path(path);
+        cairo_fill(self.cairo);
+    }
+
+    #[inline]
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint) {
+        self.set_stroke_style(style);
+        self.set_source(paint);
+        self.set_path(path);
+        cairo_stroke(self.cairo);
+    }
+
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        if dest.width == 0.0 || dest.height == 0.0 {
+            return;
+        }
+        let surface = match self.images.get(&image.id()) {
+            Some((_, uploaded)) => uploaded.surface,
+            None => match ImageSurface::new(image) {
+                Some(uploaded) => {
+                    let surface = uploaded.surface;
+                    self.images
+                        .insert(image.id(), (Arc::downgrade(image.data()), uploaded));
+                    surface
+                }
+                None => {
+                    error!(
+                        "Failed to upload an image of {}x{}",
+                        image.width(),
+                        image.height()
+                    );
+                    return;
+                }
+            },
+        };
+
+        // The sub-surface makes the edge pixels of src repeat instead of its neighbours
+        let source = cairo_surface_create_for_rectangle(
+            surface,
+            src.x as c_double,
+            src.y as c_double,
+            src.width as c_double,
+            src.height as c_double,
+        );
+        cairo_save(self.cairo);
+        cairo_new_path(self.cairo);
+        cairo_rectangle(
+            self.cairo,
+            dest.x as c_double,
+            dest.y as c_double,
+            dest.width as c_double,
+            dest.height as c_double,
+        );
+        cairo_clip(self.cairo);
+        cairo_translate(self.cairo, dest.x as c_double, dest.y as c_double);
+        cairo_scale(
+            self.cairo,
+            (dest.width / src.width) as c_double,
+            (dest.height / src.height) as c_double,
+        );
+        cairo_set_source_surface(self.cairo, source, 0.0, 0.0);
+        let pattern = cairo_get_source(self.cairo);
+        cairo_pattern_set_filter(
+            pattern,
+            match image.filter() {
+                ImageFilter::Nearest => FILTER_NEAREST,
+                ImageFilter::Bilinear => FILTER_BILINEAR,
+            },
+        );
+        cairo_pattern_set_extend(pattern, EXTEND_PAD);
+        cairo_paint_with_alpha(self.cairo, opacity as c_double);
+        cairo_restore(self.cairo);
+        cairo_surface_destroy(source);
+    }
+
+    #[inline]
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint) {
+        self.fill_path(&run.to_path(origin), paint);
+    }
+
+    #[inline]
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        let pixels =
+            util::read_surface_pixels(self.back, self.width as c_int, self.height as c_int);
+        Some((self.width, self.height, pixels))
+    }
+}
+
+impl<T: Target> Context<T> {
+    /// Makes the paint the source of the next fill or stroke
+    unsafe fn set_source(&mut self, paint: &Paint) {
+        match paint {
+            Paint::Solid(color) => {
+                let (r, g, b, a) = color_rgba(*color);
+                cairo_set_source_rgba(
+                    self.cairo,
+                    r as c_double,
+                    g as c_double,
+                    b as c_double,
+                    a as c_double,
+                );
+            }
+            Paint::LinearGradient(gradient) => {
+                let pattern = cairo_pattern_create_linear(
+                    gradient.start.x as c_double,
+                    gradient.start.y as c_double,
+                    gradient.end.x as c_double,
+                    gradient.end.y as c_double,
+                );
+                self.set_gradient_source(pattern, &gradient.stops, gradient.extend);
+            }
+            Paint::RadialGradient(gradient) => {
+                let pattern = cairo_pattern_create_radial(
+                    gradient.center.x as c_double,
+                    gradient.center.y as c_double,
+                    0.0,
+                    gradient.center.x as c_double,
+                    gradient.center.y as c_double,
+                    gradient.radius as c_double,
+                );
+                self.set_gradient_source(pattern, &gradient.stops, gradient.extend);
+            }
+        }
+    }
+
+    /// Adds the stops to a gradient pattern and makes it the source.
+    /// The context keeps its own reference, so the pattern is released here
+    unsafe fn set_gradient_source(
+        &mut self,
+        pattern: *mut cairo_pattern_t,
+        stops: &[GradientStop],
+        extend: ExtendMode,
+    ) {
+        // Pixman interpolates straight colors
+        for stop in premultiplied_stops(stops) {
+            let (r, g, b, a) = color_rgba(stop.color);
+            cairo_pattern_add_color_stop_rgba(
+                pattern,
+                stop.offset as c_double,
+                r as c_double,
+                g as c_double,
+                b as c_double,
+                a as c_double,
+            );
+        }
+        cairo_pattern_set_extend(
+            pattern,
+            match extend {
+                ExtendMode::Pad => EXTEND_PAD,
+                ExtendMode::Repeat => EXTEND_REPEAT,
+                ExtendMode::Reflect => EXTEND_REFLECT,
+            },
+        );
+        cairo_set_source(self.cairo, pattern);
+        cairo_pattern_destroy(pattern);
+    }
+
+    /// Applies the width, joins, caps and dashes used by the next cairo_stroke()
+    unsafe fn set_stroke_style(&mut self, style: &StrokeStyle) {
+        cairo_set_line_width(self.cairo, style.width as c_double);
+        cairo_set_line_join(
+            self.cairo,
+            match style.line_join {
+                LineJoin::Miter => LINE_JOIN_MITER,
+                LineJoin::Round => LINE_JOIN_ROUND,
+                LineJoin::Bevel => LINE_JOIN_BEVEL,
+            },
+        );
+        cairo_set_line_cap(
+            self.cairo,
+            match style.line_cap {
+                LineCap::Butt => LINE_CAP_BUTT,
+                LineCap::Round => LINE_CAP_ROUND,
+                LineCap::Square => LINE_CAP_SQUARE,
+            },
+        );
+        cairo_set_miter_limit(self.cairo, style.miter_limit as c_double);
+        if style.is_dashed() {
+            let dash: Vec<c_double> = style.dash.iter().map(|d| *d as c_double).collect();
+            cairo_set_dash(
+                self.cairo,
+                dash.as_ptr(),
+                dash.len() as c_int,
+                style.dash_offset as c_double,
+            );
+        } else {
+            // No dashes means a solid outline
+            cairo_set_dash(self.cairo, std::ptr::null(), 0, 0.0);
+        }
+    }
+
+    /// Replaces the current cairo path with a rounded rectangle
+    unsafe fn rounded_rectangle_path(&mut self, rect: Rect, radius: f32) {
+        let degrees: f32 = (PI / 180.0) as f32;
+
+        cairo_new_path(self.cairo);
+        cairo_new_sub_path(self.cairo);
+        cairo_arc(
+            self.cairo,
+            (rect.right() - radius) as c_double,
+            (rect.y + radius) as c_double,
+            radius as c_double,
+            (-90.0 * degrees) as c_double,
+            (0.0 * degrees) as c_double,
+        );
+        cairo_arc(
+            self.cairo,
+            (rect.right() - radius) as c_double,
+            (rect.bottom() - radius) as c_double,
+            radius as c_double,
+            (0.0 * degrees) as c_double,
+            (90.0 * degrees) as c_double,
+        );
+        cairo_arc(
+            self.cairo,
+            (rect.x + radius) as c_double,
+            (rect.bottom() - radius) as c_double,
+            radius as c_double,
+            (90.0 * degrees) as c_double,
+            (180.0 * degrees) as c_double,
+        );
+        cairo_arc(
+            self.cairo,
+            (rect.x + radius) as c_double,
+            (rect.y + radius) as c_double,
+            radius as c_double,
+            (180.0 * degrees) as c_double,
+            (270.0 * degrees) as c_double,
+        );
+        cairo_close_path(self.cairo);
+    }
+
+    /// Replaces the current cairo path with the given path
+    unsafe fn set_path(&mut self, path: &Path) {
+        cairo_new_path(self.cairo);
+        let mut start = (0.0, 0.0);
+        let mut current = (0.0, 0.0);
+        for element in path.elements() {
+            match *element {
+                PathElement::MoveTo(x, y) => {
+                    cairo_move_to(self.cairo, x as c_double, y as c_double);
+                    start = (x, y);
+                    current = (x, y);
+                }
+                PathElement::LineTo(x, y) => {
+                    cairo_line_to(self.cairo, x as c_double, y as c_double);
+                    current = (x, y);
+                }
+                PathElement::QuadTo(x1, y1, x, y) => {
+                    // cairo has no quadratic curves, so the curve is elevated to a cubic one
+                    let c1 = (
+                        current.0 + 2.0 / 3.0 * (x1 - current.0),
+                        current.1 + 2.0 / 3.0 * (y1 - current.1),
+                    );
+                    let c2 = (x + 2.0 / 3.0 * (x1 - x), y + 2.0 / 3.0 * (y1 - y));
+                    cairo_curve_to(
+                        self.cairo,
+                        c1.0 as c_double,
+                        c1.1 as c_double,
+                        c2.0 as c_double,
+                        c2.1 as c_double,
+                        x as c_double,
+                        y as c_double,
+                    );
+                    current = (x, y);
+                }
+                PathElement::CubicTo(x1, y1, x2, y2, x, y) => {
+                    cairo_curve_to(
+                        self.cairo,
+                        x1 as c_double,
+                        y1 as c_double,
+                        x2 as c_double,
+                        y2 as c_double,
+                        x as c_double,
+                        y as c_double,
+                    );
+                    current = (x, y);
+                }
+                PathElement::Close => {
+                    cairo_close_path(self.cairo);
+                    current = start;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    unsafe fn scale(&mut self, width: u32, height: u32) {
+        if width != self.width || height != self.height {
+            self.target.resize_surface(self.surface, width, height);
+
+            // The last frame stays in the top left corner, like on a resized window
+            let back = create_back_buffer(self.surface, width, height);
+            copy_surface(self.back, back);
+            cairo_destroy(self.cairo);
+            cairo_surface_destroy(self.back);
+            self.back = back;
+            self.cairo = cairo_create(back);
+
+            self.width = width;
+            self.height = height;
+        }
+    }
+}
+
+/// Creates a back buffer for a window surface.
+/// For Xlib and XCB windows, it is a pixmap of the same visual on the server
+unsafe fn create_back_buffer(
+    window: *mut cairo_surface_t,
+    width: u32,
+    height: u32,
+) -> *mut cairo_surface_t {
+    // The X server refuses to create empty pixmaps
+    cairo_surface_create_similar(
+        window,
+        cairo_surface_get_content(window),
+        width.max(1) as c_int,
+        height.max(1) as c_int,
+    )
+}
+
+/// Replaces the pixels of destination with the ones of source
+unsafe fn copy_surface(source: *mut cairo_surface_t, destination: *mut cairo_surface_t) {
+    let cairo = cairo_create(destination);
+    cairo_set_source_surface(cairo, source, 0.0, 0.0);
+    cairo_set_operator(cairo, OPERATOR_SOURCE);
+    cairo_paint(cairo);
+    cairo_destroy(cairo);
+}
+
+impl<T: Target> Drop for Context<T> {
+    fn drop(&mut self) {
+        unsafe {
+            cairo_destroy(self.cairo);
+            cairo_surface_destroy(self.back);
+            cairo_surface_destroy(self.surface);
+        }
+    }
+}
+
+/// Cairo operator of a blend mode
+fn operator(mode: BlendMode) -> i32 {
+    match mode {
+        BlendMode::Normal => OPERATOR_OVER,
+        BlendMode::Multiply => OPERATOR_MULTIPLY,
+        BlendMode::Screen => OPERATOR_SCREEN,
+        BlendMode::Overlay => OPERATOR_OVERLAY,
+        BlendMode::Darken => OPERATOR_DARKEN,
+        BlendMode::Lighten => OPERATOR_LIGHTEN,
+        BlendMode::ColorDodge => OPERATOR_COLOR_DODGE,
+        BlendMode::ColorBurn => OPERATOR_COLOR_BURN,
+        BlendMode::HardLight => OPERATOR_HARD_LIGHT,
+        BlendMode::SoftLight => OPERATOR_SOFT_LIGHT,
+        BlendMode::Difference => OPERATOR_DIFFERENCE,
+        BlendMode::Exclusion => OPERATOR_EXCLUSION,
+        BlendMode::Add => OPERATOR_ADD,
+    }
+}
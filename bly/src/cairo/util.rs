@@ -1,27 +1,114 @@
-use std::ffi::c_ulong;
-use x11::xlib::{Display, XGetGeometry};
+use crate::Image;
+use cairo_sys::{
+    cairo_create, cairo_destroy, cairo_format_stride_for_width, cairo_image_surface_create,
+    cairo_image_surface_create_for_data, cairo_image_surface_get_data,
+    cairo_image_surface_get_stride, cairo_paint, cairo_set_source_surface, cairo_status_t,
+    cairo_status_to_string, cairo_surface_destroy, cairo_surface_flush, cairo_surface_status,
+    cairo_surface_t, FORMAT_A_RGB32, STATUS_SUCCESS,
+};
+use std::ffi::{c_int, CStr};
 
-#[inline]
-pub(crate) unsafe fn get_xlib_window_size(
-    display: *mut Display,
-    window: c_ulong,
-) -> (c_ulong, c_ulong) {
-    let mut width = 0;
-    let mut height = 0;
-    let mut dummy = 0;
-    let mut c_int_dummy = 0;
-    let mut c_uint_dummy = 0;
-    XGetGeometry(
-        display,
-        window,
-        &mut dummy,
-        &mut c_int_dummy,
-        &mut c_int_dummy,
-        &mut width,
-        &mut height,
-        &mut c_uint_dummy,
-        &mut c_uint_dummy,
-    );
+/// Copies the contents of a cairo surface into straight (non-premultiplied) RGBA8 pixels
+pub(crate) unsafe fn read_surface_pixels(
+    surface: *mut cairo_surface_t,
+    width: c_int,
+    height: c_int,
+) -> Vec<u8> {
+    let image = cairo_image_surface_create(FORMAT_A_RGB32, width, height);
+    let cairo = cairo_create(image);
+    cairo_set_source_surface(cairo, surface, 0.0, 0.0);
+    cairo_paint(cairo);
+    cairo_destroy(cairo);
+    cairo_surface_flush(image);
 
-    (width.into(), height.into())
+    let data = cairo_image_surface_get_data(image);
+    let stride = cairo_image_surface_get_stride(image) as usize;
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    if !data.is_null() {
+        for y in 0..height as usize {
+            let row = std::slice::from_raw_parts(data.add(y * stride), width as usize * 4);
+            for px in row.chunks_exact(4) {
+                // CAIRO_FORMAT_ARGB32 is premultiplied and stored as native-endian u32
+                let argb = u32::from_ne_bytes([px[0], px[1], px[2], px[3]]);
+                let a = (argb >> 24) & 0xff;
+                let unpremultiply = |c: u32| {
+                    (c * 255 + a / 2)
+                        .checked_div(a)
+                        .map_or(0, |v| v.min(255) as u8)
+                };
+                pixels.push(unpremultiply((argb >> 16) & 0xff));
+                pixels.push(unpremultiply((argb >> 8) & 0xff));
+                pixels.push(unpremultiply(argb & 0xff));
+                pixels.push(a as u8);
+            }
+        }
+    }
+    cairo_surface_destroy(image);
+    pixels
+}
+
+/// An Image uploaded to a cairo image surface.
+/// The surface draws directly from data, so both are dropped together
+pub(crate) struct ImageSurface {
+    pub surface: *mut cairo_surface_t,
+    _data: Vec<u8>,
+}
+
+impl ImageSurface {
+    /// Converts the pixels of the image to premultiplied CAIRO_FORMAT_ARGB32
+    pub unsafe fn new(image: &Image) -> Option<Self> {
+        let (width, height) = (image.width() as c_int, image.height() as c_int);
+        let stride = cairo_format_stride_for_width(FORMAT_A_RGB32, width);
+        if stride < 0 {
+            return None;
+        }
+        let mut data = vec![0u8; stride as usize * height as usize];
+        for (row, pixels) in data
+            .chunks_exact_mut(stride as usize)
+            .zip(image.pixels().chunks_exact(width as usize * 4))
+        {
+            for (dst, px) in row.chunks_exact_mut(4).zip(pixels.chunks_exact(4)) {
+                let a = px[3] as u32;
+                let premultiply = |c: u8| (c as u32 * a + 127) / 255;
+                let argb = a << 24
+                    | premultiply(px[0]) << 16
+                    | premultiply(px[1]) << 8
+                    | premultiply(px[2]);
+                dst.copy_from_slice(&argb.to_ne_bytes());
+            }
+        }
+
+        let surface = cairo_image_surface_create_for_data(
+            data.as_mut_ptr(),
+            FORMAT_A_RGB32,
+            width,
+            height,
+            stride,
+        );
+        if cairo_surface_status(surface) != STATUS_SUCCESS {
+            cairo_surface_destroy(surface);
+            return None;
+        }
+        Some(Self {
+            surface,
+            _data: data,
+        })
+    }
+}
+
+impl Drop for ImageSurface {
+    fn drop(&mut self) {
+        unsafe {
+            cairo_surface_destroy(self.surface);
+        }
+    }
+}
+
+/// Returns the human-readable description of a cairo status
+pub(crate) fn status_to_string(status: cairo_status_t) -> String {
+    unsafe {
+        CStr::from_ptr(cairo_status_to_string(status))
+            .to_string_lossy()
+            .into_owned()
+    }
 }
@@ -0,0 +1,214 @@
+use crate::cairo::context::{Context, Target};
+use crate::cairo::CairoBackend;
+use crate::Error;
+use cairo_sys::{
+    cairo_surface_t, cairo_xcb_surface_create, cairo_xcb_surface_set_size, xcb_connection_t,
+    xcb_visualtype_t,
+};
+use std::ffi::{c_int, c_uint, c_void};
+
+// libxcb has no bindings among our dependencies, so the few requests used here are declared by hand.
+// Replies are allocated by libxcb and released with free()
+
+#[repr(C)]
+struct XcbIterator<T> {
+    data: *mut T,
+    rem: c_int,
+    index: c_int,
+}
+
+#[repr(C)]
+struct XcbCookie {
+    sequence: c_uint,
+}
+
+/// xcb_visualtype_t
+#[repr(C)]
+struct VisualType {
+    visual_id: u32,
+    class: u8,
+    bits_per_rgb_value: u8,
+    colormap_entries: u16,
+    red_mask: u32,
+    green_mask: u32,
+    blue_mask: u32,
+    pad: [u8; 4],
+}
+
+/// xcb_get_geometry_reply_t
+#[repr(C)]
+struct GeometryReply {
+    response_type: u8,
+    depth: u8,
+    sequence: u16,
+    length: u32,
+    root: u32,
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+    border_width: u16,
+    pad: [u8; 2],
+}
+
+/// The leading fields of xcb_get_window_attributes_reply_t
+#[repr(C)]
+struct WindowAttributesReply {
+    response_type: u8,
+    backing_store: u8,
+    sequence: u16,
+    length: u32,
+    visual: u32,
+}
+
+#[link(name = "xcb")]
+extern "C" {
+    fn xcb_connection_has_error(c: *mut xcb_connection_t) -> c_int;
+    fn xcb_flush(c: *mut xcb_connection_t) -> c_int;
+    fn xcb_get_setup(c: *mut xcb_connection_t) -> *const c_void;
+    fn xcb_setup_roots_iterator(setup: *const c_void) -> XcbIterator<c_void>;
+    fn xcb_screen_next(i: *mut XcbIterator<c_void>);
+    fn xcb_screen_allowed_depths_iterator(screen: *const c_void) -> XcbIterator<c_void>;
+    fn xcb_depth_next(i: *mut XcbIterator<c_void>);
+    fn xcb_depth_visuals_iterator(depth: *const c_void) -> XcbIterator<VisualType>;
+    fn xcb_visualtype_next(i: *mut XcbIterator<VisualType>);
+    fn xcb_get_geometry(c: *mut xcb_connection_t, drawable: u32) -> XcbCookie;
+    fn xcb_get_geometry_reply(
+        c: *mut xcb_connection_t,
+        cookie: XcbCookie,
+        e: *mut *mut c_void,
+    ) -> *mut GeometryReply;
+    fn xcb_get_window_attributes(c: *mut xcb_connection_t, window: u32) -> XcbCookie;
+    fn xcb_get_window_attributes_reply(
+        c: *mut xcb_connection_t,
+        cookie: XcbCookie,
+        e: *mut *mut c_void,
+    ) -> *mut WindowAttributesReply;
+}
+
+extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
+/// Creates a backend drawing on an XCB window of the application's connection.
+/// visual_id may be 0, in which case the visual of the window is asked to the server
+#[doc(hidden)]
+pub(crate) fn create_backend(
+    connection: *mut c_void,
+    window: u32,
+    visual_id: u32,
+) -> Result<CairoBackend, Error> {
+    let connection = connection as *mut xcb_connection_t;
+    unsafe {
+        if connection.is_null() || xcb_connection_has_error(connection) != 0 {
+            error!("The XCB connection is not usable");
+            return Err(Error::DisplayConnectionFailed);
+        }
+
+        let visual_id = match visual_id {
+            0 => window_visual(connection, window).ok_or_else(|| {
+                Error::SurfaceCreationFailed(format!(
+                    "failed to get the attributes of the window {}",
+                    window
+                ))
+            })?,
+            id => id,
+        };
+        let visual = find_visual(connection, visual_id).ok_or_else(|| {
+            Error::SurfaceCreationFailed(format!("unknown visual {:#x}", visual_id))
+        })?;
+
+        let target = XcbWindow {
+            connection,
+            window,
+            visual,
+        };
+        Ok(CairoBackend {
+            backend: Box::new(Context::new(target)?),
+        })
+    }
+}
+
+/// A window of an XCB connection owned by the application
+pub(crate) struct XcbWindow {
+    connection: *mut xcb_connection_t,
+    window: u32,
+    /// Points into the setup data of the connection, which lives as long as the connection
+    visual: *mut VisualType,
+}
+
+impl Target for XcbWindow {
+    unsafe fn size(&self) -> (u32, u32) {
+        let cookie = xcb_get_geometry(self.connection, self.window);
+        let mut error = std::ptr::null_mut();
+        let reply = xcb_get_geometry_reply(self.connection, cookie, &mut error);
+        if !error.is_null() {
+            free(error);
+        }
+        if reply.is_null() {
+            return (0, 0);
+        }
+        let size = ((*reply).width as u32, (*reply).height as u32);
+        free(reply as *mut c_void);
+        size
+    }
+
+    unsafe fn create_surface(&self, width: u32, height: u32) -> *mut cairo_surface_t {
+        cairo_xcb_surface_create(
+            self.connection,
+            self.window,
+            self.visual as *mut xcb_visualtype_t,
+            width as c_int,
+            height as c_int,
+        )
+    }
+
+    #[inline]
+    unsafe fn resize_surface(&self, surface: *mut cairo_surface_t, width: u32, height: u32) {
+        cairo_xcb_surface_set_size(surface, width as c_int, height as c_int);
+    }
+
+    #[inline]
+    unsafe fn flush(&self) {
+        xcb_flush(self.connection);
+    }
+}
+
+/// Visual of a window, or None if the window does not exist
+unsafe fn window_visual(connection: *mut xcb_connection_t, window: u32) -> Option<u32> {
+    let cookie = xcb_get_window_attributes(connection, window);
+    let mut error = std::ptr::null_mut();
+    let reply = xcb_get_window_attributes_reply(connection, cookie, &mut error);
+    if !error.is_null() {
+        free(error);
+    }
+    if reply.is_null() {
+        return None;
+    }
+    let visual = (*reply).visual;
+    free(reply as *mut c_void);
+    Some(visual)
+}
+
+/// Looks for the description of a visual in every screen of the connection
+unsafe fn find_visual(
+    connection: *mut xcb_connection_t,
+    visual_id: u32,
+) -> Option<*mut VisualType> {
+    let mut screens = xcb_setup_roots_iterator(xcb_get_setup(connection));
+    while screens.rem > 0 {
+        let mut depths = xcb_screen_allowed_depths_iterator(screens.data);
+        while depths.rem > 0 {
+            let mut visuals = xcb_depth_visuals_iterator(depths.data);
+            while visuals.rem > 0 {
+                if (*visuals.data).visual_id == visual_id {
+                    return Some(visuals.data);
+                }
+                xcb_visualtype_next(&mut visuals);
+            }
+            xcb_depth_next(&mut depths);
+        }
+        xcb_screen_next(&mut screens);
+    }
+    None
+}
@@ -1,255 +1,127 @@
-use crate::cairo::{util, CairoBackend};
-use crate::{Backend, Point2};
-use cairo_sys::{
-    cairo_arc, cairo_close_path, cairo_create, cairo_destroy, cairo_fill, cairo_fill_preserve,
-    cairo_line_to, cairo_move_to, cairo_new_sub_path, cairo_rectangle, cairo_set_line_width,
-    cairo_set_source_rgb, cairo_set_source_rgba, cairo_stroke, cairo_surface_t, cairo_t,
-    cairo_xlib_surface_create,
+use crate::cairo::context::{Context, Target};
+use crate::cairo::CairoBackend;
+use crate::unix::Shared;
+use crate::Error;
+use cairo_sys::{cairo_surface_t, cairo_xlib_surface_create, cairo_xlib_surface_set_size};
+use std::ffi::{c_int, c_ulong, c_void};
+use std::sync::Arc;
+use x11::xlib::{
+    Display, Visual, XCloseDisplay, XFlush, XGetGeometry, XGetWindowAttributes, XOpenDisplay,
+    XVisualIDFromVisual, XWindowAttributes,
 };
-use std::f64::consts::PI;
-use std::ffi::{c_double, c_int, c_ulong};
-use x11::xlib::{Display, XDefaultVisual, XFlush, XGetGeometry, XOpenDisplay};
 
-#[doc(hidden)]
-pub(crate) fn create_backend(window: c_ulong) -> CairoBackend {
-    unsafe {
-        let display = XOpenDisplay(std::ptr::null_mut());
-        info!("Display acquired. {:?}", display);
-        let (width, height) = util::get_xlib_window_size(display, window);
-
-        let surface = cairo_xlib_surface_create(
-            display,
-            window,
-            XDefaultVisual(display, 0),
-            width as c_int,
-            height as c_int,
-        );
-
-        info!("A Cairo surface has been created.");
-
-        let cairo = cairo_create(surface);
+/// Connection to the X server opened by Bly when the window handle comes without a display,
+/// shared by every such Xlib canvas of the thread.  
+/// Canvases cannot be sent to other threads, so each connection is only used by the thread
+/// that opened it, and Xlib does not need XInitThreads(), which would have to be its first call
+pub(crate) struct XDisplay(*mut Display);
 
-        CairoBackend {
-            backend: Box::new(XLibBackend {
-                handle: window,
-                display,
-                width,
-                height,
-                surface,
-                cairo,
-            }),
+impl Drop for XDisplay {
+    fn drop(&mut self) {
+        info!("Closing display. {:?}", self.0);
+        unsafe {
+            XCloseDisplay(self.0);
         }
     }
 }
 
-#[doc(hidden)]
-pub(crate) struct XLibBackend {
-    handle: c_ulong,
-    display: *mut Display,
-
-    width: c_ulong,
-    height: c_ulong,
-
-    surface: *mut cairo_surface_t,
-    cairo: *mut cairo_t,
+thread_local! {
+    static DISPLAY: Shared<XDisplay> = const { Shared::new() };
 }
 
-impl Backend for XLibBackend {
-    #[inline]
-    unsafe fn begin_draw(&mut self) {
-        let (width, height) = get_xlib_window_size(self.display, self.handle);
-        self.scale(width, height);
-    }
-
-    #[inline]
-    unsafe fn flush(&mut self) {
-        XFlush(self.display);
-    }
-
-    #[inline]
-    unsafe fn get_display_size(&mut self) -> (u32, u32) {
-        let (width, height) = get_xlib_window_size(self.display, self.handle);
-        (width as u32, height as u32)
-    }
-
-    #[inline]
-    unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
-        let (width, height) = get_xlib_window_size(self.display, self.handle);
-
-        cairo_set_source_rgb(self.cairo, r as c_double, g as c_double, b as c_double);
-        cairo_rectangle(
-            self.cairo,
-            0 as c_double,
-            0 as c_double,
-            width as c_double,
-            height as c_double,
-        );
-        cairo_fill(self.cairo);
-    }
+fn open_display() -> Result<Arc<XDisplay>, Error> {
+    DISPLAY.with(|shared| {
+        shared.get_or_try_init(|| unsafe {
+            let display = XOpenDisplay(std::ptr::null_mut());
+            if display.is_null() {
+                error!("Failed to open the X display");
+                return Err(Error::DisplayConnectionFailed);
+            }
+            info!("Display acquired. {:?}", display);
+            Ok(XDisplay(display))
+        })
+    })
+}
 
-    #[inline]
-    unsafe fn ellipse(&mut self, point: Point2<f32>, radius: f32, r: f32, g: f32, b: f32, a: f32) {
-        cairo_set_source_rgba(
-            self.cairo,
-            r as c_double,
-            g as c_double,
-            b as c_double,
-            a as c_double,
-        );
+/// Creates a backend drawing on a window of display, the connection of the application.
+/// If display is null, a connection is opened to the default X server
+#[doc(hidden)]
+pub(crate) fn create_backend(display: *mut c_void, window: c_ulong) -> Result<CairoBackend, Error> {
+    let (connection, display) = if display.is_null() {
+        let connection = open_display()?;
+        let display = connection.0;
+        (Some(connection), display)
+    } else {
+        (None, display as *mut Display)
+    };
 
-        cairo_arc(
-            self.cairo,
-            (point.0 + radius) as c_double,
-            (point.1 + radius) as c_double,
-            radius as c_double,
-            0.0,
-            2.0 * PI,
+    unsafe {
+        // The window may use another visual than the default one of its screen,
+        // e.g. a 32 bits ARGB visual for transparency
+        let mut attributes: XWindowAttributes = std::mem::zeroed();
+        if XGetWindowAttributes(display, window, &mut attributes) == 0 {
+            return Err(Error::SurfaceCreationFailed(format!(
+                "failed to get the attributes of the window {}",
+                window
+            )));
+        }
+        info!(
+            "Window visual: {:#x}, depth: {}",
+            XVisualIDFromVisual(attributes.visual),
+            attributes.depth
         );
 
-        cairo_fill(self.cairo);
+        let target = XlibWindow {
+            handle: window,
+            _connection: connection,
+            display,
+            visual: attributes.visual,
+        };
+        Ok(CairoBackend {
+            backend: Box::new(Context::new(target)?),
+        })
     }
+}
 
-    #[inline]
-    unsafe fn rectangle(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    ) {
-        cairo_set_source_rgba(
-            self.cairo,
-            r as c_double,
-            g as c_double,
-            b as c_double,
-            a as c_double,
-        );
-        cairo_rectangle(
-            self.cairo,
-            point1.0 as c_double,
-            point1.1 as c_double,
-            point2.0 as c_double,
-            point2.1 as c_double,
-        );
-        cairo_fill(self.cairo);
-    }
+/// An Xlib window
+pub(crate) struct XlibWindow {
+    handle: c_ulong,
+    /// Keeps the display connection open while this canvas exists,
+    /// if it was opened by Bly rather than by the application
+    _connection: Option<Arc<XDisplay>>,
+    display: *mut Display,
+    visual: *mut Visual,
+}
 
+impl Target for XlibWindow {
     #[inline]
-    unsafe fn rounded_rectangle(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        radius: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    ) {
-        cairo_set_source_rgba(
-            self.cairo,
-            r as c_double,
-            g as c_double,
-            b as c_double,
-            a as c_double,
-        );
-
-        let degrees: f32 = (PI / 180.0) as f32;
-
-        cairo_new_sub_path(self.cairo);
-        cairo_arc(
-            self.cairo,
-            (point1.0 + point2.0 - radius) as c_double,
-            (point1.1 + radius) as c_double,
-            radius as c_double,
-            (-90.0 * degrees) as c_double,
-            (0.0 * degrees) as c_double,
-        );
-        cairo_arc(
-            self.cairo,
-            (point1.0 + point2.0 - radius) as c_double,
-            (point1.1 + point2.1 - radius) as c_double,
-            radius as c_double,
-            (0.0 * degrees) as c_double,
-            (90.0 * degrees) as c_double,
-        );
-        cairo_arc(
-            self.cairo,
-            (point1.0 + radius) as c_double,
-            (point1.1 + point2.1 - radius) as c_double,
-            radius as c_double,
-            (90.0 * degrees) as c_double,
-            (180.0 * degrees) as c_double,
-        );
-        cairo_arc(
-            self.cairo,
-            (point1.0 + radius) as c_double,
-            (point1.1 + radius) as c_double,
-            radius as c_double,
-            (180.0 * degrees) as c_double,
-            (270.0 * degrees) as c_double,
-        );
-        cairo_close_path(self.cairo);
-        cairo_fill_preserve(self.cairo);
+    unsafe fn size(&self) -> (u32, u32) {
+        get_xlib_window_size(self.display, self.handle)
     }
 
-    #[inline]
-    unsafe fn line(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        stroke: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    ) {
-        cairo_set_line_width(self.cairo, stroke as c_double);
-
-        cairo_set_source_rgba(
-            self.cairo,
-            r as c_double,
-            g as c_double,
-            b as c_double,
-            a as c_double,
-        );
-        cairo_move_to(self.cairo, point1.0 as c_double, point1.1 as c_double);
-        cairo_line_to(self.cairo, point2.0 as c_double, point2.1 as c_double);
-
-        cairo_stroke(self.cairo);
+    unsafe fn create_surface(&self, width: u32, height: u32) -> *mut cairo_surface_t {
+        cairo_xlib_surface_create(
+            self.display,
+            self.handle,
+            self.visual,
+            width as c_int,
+            height as c_int,
+        )
     }
-}
 
-impl XLibBackend {
     #[inline]
-    unsafe fn scale(&mut self, width: c_ulong, height: c_ulong) {
-        if width != self.width || height != self.height {
-            self.surface = cairo_xlib_surface_create(
-                self.display,
-                self.handle,
-                XDefaultVisual(self.display, 0),
-                width as c_int,
-                height as c_int,
-            );
-            self.cairo = cairo_create(self.surface);
-        }
+    unsafe fn resize_surface(&self, surface: *mut cairo_surface_t, width: u32, height: u32) {
+        cairo_xlib_surface_set_size(surface, width as c_int, height as c_int);
     }
-}
 
-impl Drop for XLibBackend {
-    fn drop(&mut self) {
-        unsafe {
-            cairo_destroy(self.cairo);
-        }
+    #[inline]
+    unsafe fn flush(&self) {
+        XFlush(self.display);
     }
 }
 
 #[inline]
-pub(crate) unsafe fn get_xlib_window_size(
-    display: *mut Display,
-    window: c_ulong,
-) -> (c_ulong, c_ulong) {
+pub(crate) unsafe fn get_xlib_window_size(display: *mut Display, window: c_ulong) -> (u32, u32) {
     let mut width = 0;
     let mut height = 0;
     let mut dummy = 0;
@@ -267,5 +139,5 @@ pub(crate) unsafe fn get_xlib_window_size(
         &mut c_uint_dummy,
     );
 
-    (width.into(), height.into())
+    (width, height)
 }
@@ -0,0 +1,543 @@
+//! Colors, their CSS notations and conversions between color models
+
+use crate::Error;
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+/// # Color
+/// A color of the sRGB color space with straight (non-premultiplied) alpha.
+/// Every component goes from 0.0 to 1.0.
+///
+/// Colors can be written like in CSS, or built from other color models.
+/// ```
+/// use bly::Color;
+///
+/// let orange: Color = "#ff8000".parse().unwrap();
+/// assert_eq!(orange, Color::rgb8(255, 128, 0));
+/// assert_eq!(Color::parse("rgb(255 128 0 / 50%)").unwrap(), orange.with_alpha(0.5));
+/// assert_eq!(Color::parse("RebeccaPurple").unwrap(), Color::from(0x663399ff));
+///
+/// assert_eq!(Color::RED.to_hsl(), (0.0, 1.0, 0.5));
+/// assert_eq!(Color::from_hsl(120.0, 1.0, 0.5), Color::GREEN);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    /// Opacity, from 0.0 (invisible) to 1.0 (opaque)
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    /// A light gray, e.g. for the background of a window
+    pub const WHITE_GRAY: Color = Color::rgb(0.9, 0.9, 0.9);
+    /// Gray of CSS, halfway between black and white
+    pub const GRAY: Color = Color::rgb8(128, 128, 128);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    /// Pure green, which CSS calls lime (green is darker in CSS)
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+
+    /// Creates a color from its components between 0.0 and 1.0
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Creates an opaque color from its components between 0.0 and 1.0
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    /// Creates a color from its components between 0 and 255
+    pub const fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::rgba(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    /// Creates an opaque color from its components between 0 and 255
+    pub const fn rgb8(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba8(r, g, b, 255)
+    }
+
+    /// Parses a hexadecimal color: #rgb, #rgba, #rrggbb or #rrggbbaa.
+    /// The # may be left out
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let digits = hex.trim().trim_start_matches('#');
+        if !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid_color(hex));
+        }
+        let value = u32::from_str_radix(digits, 16).map_err(|_| invalid_color(hex))?;
+        let nibble = |shift: u32| ((value >> shift) & 0xf) as u8 * 17;
+        let byte = |shift: u32| ((value >> shift) & 0xff) as u8;
+        match digits.len() {
+            3 => Ok(Self::rgb8(nibble(8), nibble(4), nibble(0))),
+            4 => Ok(Self::rgba8(nibble(12), nibble(8), nibble(4), nibble(0))),
+            6 => Ok(Self::rgb8(byte(16), byte(8), byte(0))),
+            8 => Ok(Self::from(value)),
+            _ => Err(invalid_color(hex)),
+        }
+    }
+
+    /// Returns the CSS named color (e.g. "rebeccapurple"), ignoring the case
+    pub fn named(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        NAMED_COLORS
+            .binary_search_by(|(named, _)| named.cmp(&name.as_str()))
+            .ok()
+            .map(|i| Self::from(NAMED_COLORS[i].1 << 8 | 0xff))
+    }
+
+    /// Parses a color written in CSS:
+    /// a hexadecimal color, a named color, transparent, rgb(), rgba(), hsl(), hsla() or oklch().
+    /// Both the comma separated and the space separated syntax are accepted
+    pub fn parse(css: &str) -> Result<Self, Error> {
+        let color = css.trim().to_ascii_lowercase();
+        if color.starts_with('#') {
+            return Self::from_hex(&color);
+        }
+        if color == "transparent" {
+            return Ok(Self::TRANSPARENT);
+        }
+        Self::named(&color)
+            .or_else(|| parse_function(&color))
+            .ok_or_else(|| invalid_color(css))
+    }
+
+    /// Creates an opaque color from a hue in degrees, a saturation and a lightness between 0.0 and 1.0
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let (s, l) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        from_hue(hue, chroma, l - chroma / 2.0)
+    }
+
+    /// Hue in degrees, saturation and lightness of the color
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue();
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+        (hue, saturation, lightness)
+    }
+
+    /// Creates an opaque color from a hue in degrees, a saturation and a value between 0.0 and 1.0
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let (s, v) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+        let chroma = v * s;
+        from_hue(hue, chroma, v - chroma)
+    }
+
+    /// Hue in degrees, saturation and value of the color
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue();
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (hue, saturation, max)
+    }
+
+    /// Creates an opaque color from the OKLCH color model:
+    /// a perceived lightness between 0.0 and 1.0, a chroma (up to about 0.4) and a hue in degrees.
+    /// Colors outside of sRGB are clamped
+    pub fn from_oklch(lightness: f32, chroma: f32, hue: f32) -> Self {
+        let hue = hue.to_radians();
+        let (a, b) = (chroma.max(0.0) * hue.cos(), chroma.max(0.0) * hue.sin());
+        let l = lightness + 0.396_337_78 * a + 0.215_803_76 * b;
+        let m = lightness - 0.105_561_346 * a - 0.063_854_17 * b;
+        let s = lightness - 0.089_484_18 * a - 1.291_485_5 * b;
+        let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+        Self::rgb(
+            linear_to_srgb(4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s),
+            linear_to_srgb(-1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s),
+            linear_to_srgb(-0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s),
+        )
+    }
+
+    /// Perceived lightness, chroma and hue in degrees of the color in the OKLCH color model
+    pub fn to_oklch(self) -> (f32, f32, f32) {
+        let (r, g, b) = (
+            srgb_to_linear(self.r),
+            srgb_to_linear(self.g),
+            srgb_to_linear(self.b),
+        );
+        let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+        let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+        let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+        let lightness = 0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s;
+        let a = 1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s;
+        let b = 0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s;
+        let hue = b.atan2(a).to_degrees().rem_euclid(360.0);
+        (lightness, a.hypot(b), hue)
+    }
+
+    /// The same color with another opacity
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Self { a: alpha, ..self }
+    }
+
+    /// Increases the HSL lightness of the color by amount (e.g. 0.1 for 10%)
+    pub fn lighten(self, amount: f32) -> Self {
+        let (hue, saturation, lightness) = self.to_hsl();
+        Self::from_hsl(hue, saturation, lightness + amount).with_alpha(self.a)
+    }
+
+    /// Decreases the HSL lightness of the color by amount (e.g. 0.1 for 10%)
+    pub fn darken(self, amount: f32) -> Self {
+        self.lighten(-amount)
+    }
+
+    /// Mixes the color with other, from 0.0 (only this color) to 1.0 (only other).
+    /// Like gradients, the colors are mixed with premultiplied alpha,
+    /// so that a transparent color does not darken the mix
+    pub fn mix(self, other: Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let (a0, a1) = (self.a.clamp(0.0, 1.0), other.a.clamp(0.0, 1.0));
+        let a = a0 + (a1 - a0) * t;
+        if a <= 0.0 {
+            return Self::TRANSPARENT;
+        }
+        let channel = |c0: f32, c1: f32| (c0 * a0 + (c1 * a1 - c0 * a0) * t) / a;
+        Self::rgba(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+            a,
+        )
+    }
+
+    /// Components of the color between 0 and 255
+    pub fn to_rgba8(self) -> [u8; 4] {
+        let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [byte(self.r), byte(self.g), byte(self.b), byte(self.a)]
+    }
+
+    /// Hue in degrees, largest and smallest component of the color
+    fn hue(self) -> (f32, f32, f32) {
+        let (r, g, b) = (
+            self.r.clamp(0.0, 1.0),
+            self.g.clamp(0.0, 1.0),
+            self.b.clamp(0.0, 1.0),
+        );
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let delta = max - min;
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (hue, max, min)
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(css: &str) -> Result<Self, Error> {
+        Self::parse(css)
+    }
+}
+
+impl From<[u8; 4]> for Color {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self::rgba8(r, g, b, a)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::rgba(r, g, b, a)
+    }
+}
+
+/// 0xRRGGBBAA, like the #rrggbbaa notation
+impl From<u32> for Color {
+    fn from(rgba: u32) -> Self {
+        let [r, g, b, a] = rgba.to_be_bytes();
+        Self::rgba8(r, g, b, a)
+    }
+}
+
+fn invalid_color(css: &str) -> Error {
+    Error::ColorParsingFailed(format!("{:?} is not a valid color", css))
+}
+
+/// Opaque color of a hue in degrees, with the given chroma and the smallest component m
+fn from_hue(hue: f32, chroma: f32, m: f32) -> Color {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    Color::rgb(r + m, g + m, b + m)
+}
+
+/// Converts an sRGB encoded component to linear light
+#[inline]
+pub(crate) fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light component to sRGB encoding, clamped between 0.0 and 1.0
+#[inline]
+pub(crate) fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Color of a CSS function like rgb(255 0 0 / 50%)
+fn parse_function(css: &str) -> Option<Color> {
+    let (name, arguments) = css.strip_suffix(')')?.split_once('(')?;
+    let (components, alpha) = match arguments.contains(',') {
+        // Legacy syntax: rgba(255, 0, 0, 0.5)
+        true => {
+            let arguments: Vec<&str> = arguments.split(',').map(str::trim).collect();
+            match arguments[..] {
+                [x, y, z] => ([x, y, z], None),
+                [x, y, z, alpha] => ([x, y, z], Some(alpha)),
+                _ => return None,
+            }
+        }
+        // Modern syntax: rgb(255 0 0 / 50%)
+        false => {
+            let (components, alpha) = match arguments.split_once('/') {
+                Some((components, alpha)) => (components, Some(alpha.trim())),
+                None => (arguments, None),
+            };
+            let components: Vec<&str> = components.split_whitespace().collect();
+            match components[..] {
+                [x, y, z] => ([x, y, z], alpha),
+                _ => return None,
+            }
+        }
+    };
+    let [x, y, z] = components;
+    let color = match name.trim() {
+        "rgb" | "rgba" => Color::rgb(rgb_component(x)?, rgb_component(y)?, rgb_component(z)?),
+        "hsl" | "hsla" => Color::from_hsl(hue(x)?, percentage(y)?, percentage(z)?),
+        "oklch" => Color::from_oklch(fraction(x, 1.0)?, fraction(y, 0.4)?, hue(z)?),
+        _ => return None,
+    };
+    match alpha {
+        Some(alpha) => Some(color.with_alpha(fraction(alpha, 1.0)?.clamp(0.0, 1.0))),
+        None => Some(color),
+    }
+}
+
+fn number(css: &str) -> Option<f32> {
+    css.parse::<f32>().ok().filter(|n| n.is_finite())
+}
+
+/// A number, or a percentage of full
+fn fraction(css: &str, full: f32) -> Option<f32> {
+    match css.strip_suffix('%') {
+        Some(percent) => Some(number(percent)? / 100.0 * full),
+        None => number(css),
+    }
+}
+
+/// A component of rgb(), from 0 to 255 or from 0% to 100%
+fn rgb_component(css: &str) -> Option<f32> {
+    let component = match css.strip_suffix('%') {
+        Some(percent) => number(percent)? / 100.0,
+        None => number(css)? / 255.0,
+    };
+    Some(component.clamp(0.0, 1.0))
+}
+
+/// Saturation or lightness of hsl(), a percentage (the % may be left out)
+fn percentage(css: &str) -> Option<f32> {
+    number(css.strip_suffix('%').unwrap_or(css)).map(|n| n / 100.0)
+}
+
+/// An angle in degrees, or with a deg, rad, grad or turn unit
+fn hue(css: &str) -> Option<f32> {
+    let units = [
+        ("deg", 1.0),
+        ("grad", 0.9),
+        ("rad", 180.0 / PI),
+        ("turn", 360.0),
+    ];
+    for (unit, degrees) in units {
+        if let Some(angle) = css.strip_suffix(unit) {
+            return Some(number(angle)? * degrees);
+        }
+    }
+    number(css)
+}
+
+/// The CSS named colors, sorted by name, as 0xRRGGBB
+static NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
@@ -1,15 +1,55 @@
 //! dx2d
 
+use crate::blend::srgb_only;
+use crate::image::ImageData;
+use crate::paint::{color_rgba, premultiplied_stops};
 use crate::Backend;
-use crate::Point2;
+use crate::Error;
+use crate::Point;
+use crate::Transform2D;
+use crate::{BlendMode, BlendSpace};
+use crate::{ExtendMode, GlyphRun, GradientStop, Paint};
+use crate::{Image, ImageFilter, Rect};
+use crate::{LineCap, LineJoin, StrokeStyle};
+use crate::{Path, PathElement};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 use windows::{
     core::*, Foundation::Numerics::*, Win32::Foundation::*, Win32::Graphics::Direct2D::Common::*,
-    Win32::Graphics::Direct2D::*, Win32::UI::WindowsAndMessaging::*,
+    Win32::Graphics::Direct2D::*, Win32::Graphics::Dxgi::Common::*,
+    Win32::UI::WindowsAndMessaging::*,
 };
 
+impl From<windows::core::Error> for Error {
+    fn from(error: windows::core::Error) -> Self {
+        Error::PlatformError(error.message().to_string())
+    }
+}
+
+thread_local! {
+    /// Direct2D factory shared by every canvas created on this thread.
+    /// The factory is single threaded, so it is not shared across threads
+    static FACTORY: RefCell<Weak<ID2D1Factory1>> = RefCell::new(Weak::new());
+}
+
+/// Returns the factory of this thread, creating it if no canvas currently holds it
+fn shared_factory() -> std::result::Result<Rc<ID2D1Factory1>, Error> {
+    FACTORY.with(|slot| {
+        if let Some(factory) = slot.borrow().upgrade() {
+            return Ok(factory);
+        }
+        let factory = Rc::new(
+            create_factory().map_err(|e| Error::ContextCreationFailed(e.message().to_string()))?,
+        );
+        *slot.borrow_mut() = Rc::downgrade(&factory);
+        Ok(factory)
+    })
+}
+
 #[doc(hidden)]
-pub fn create_backend(hwnd: isize) -> std::result::Result<Direct2DBackend, ()> {
-    let backend = Direct2DBackend::new(HWND(hwnd)).unwrap();
+pub fn create_backend(hwnd: isize) -> std::result::Result<Direct2DBackend, Error> {
+    let backend = Direct2DBackend::new(HWND(hwnd))?;
 
     Ok(backend)
 }
@@ -21,22 +61,45 @@ pub struct Direct2DBackend {
     width: u32,
     height: u32,
 
-    factory: ID2D1Factory1,
+    factory: Rc<ID2D1Factory1>,
     target: ID2D1HwndRenderTarget,
+    /// Images uploaded by draw_image(), until the last clone of the image is dropped.
+    /// Bitmaps belong to the target, so they are uploaded again when it is re-created
+    bitmaps: HashMap<u64, (std::sync::Weak<ImageData>, ID2D1Bitmap)>,
+    /// One entry per pushed clip or layer, false if creating its layer failed and there is nothing to pop
+    layers: Vec<bool>,
+
+    /// First error that occurred while drawing the current frame
+    error: Option<Error>,
 }
 
 impl Backend for Direct2DBackend {
     #[inline]
-    unsafe fn begin_draw(&mut self) {
-        self.update_target();
+    unsafe fn begin_draw(&mut self) -> std::result::Result<(), Error> {
+        self.error = None;
+        self.layers.clear();
+        self.update_target()?;
+        self.bitmaps
+            .retain(|_, (owner, _)| owner.strong_count() > 0);
         self.target.BeginDraw();
+        Ok(())
     }
 
     #[inline]
-    unsafe fn flush(&mut self) {
-        self.target
+    unsafe fn flush(&mut self) -> std::result::Result<(), Error> {
+        if let Err(e) = self
+            .target
             .EndDraw(std::ptr::null_mut(), std::ptr::null_mut())
-            .unwrap();
+        {
+            // The target is lost (e.g. D2DERR_RECREATE_TARGET), so create it again on the next frame
+            self.width = 0;
+            self.height = 0;
+            return Err(Error::DrawingFailed(e.message().to_string()));
+        }
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
     }
 
     #[inline]
@@ -47,30 +110,127 @@ impl Backend for Direct2DBackend {
         }
     }
 
+    #[inline]
+    unsafe fn resize(&mut self, _width: u32, _height: u32) {
+        // The render target follows the size of the window in begin_draw
+    }
+
+    #[inline]
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> std::result::Result<(), Error> {
+        // The render target blends its 8 bits sRGB values
+        srgb_only(space)
+    }
+
     #[inline]
     unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
         self.target.Clear(&D2D1_COLOR_F { r, g, b, a });
     }
 
     #[inline]
-    unsafe fn ellipse(&mut self, point: Point2<f32>, radius: f32, r: f32, g: f32, b: f32, a: f32) {
-        let color = D2D1_COLOR_F { r, g, b, a };
+    unsafe fn set_transform(&mut self, transform: &Transform2D) {
+        self.target.SetTransform(&Matrix3x2 {
+            M11: transform.m11,
+            M12: transform.m12,
+            M21: transform.m21,
+            M22: transform.m22,
+            M31: transform.m31,
+            M32: transform.m32,
+        });
+    }
 
-        let properties = D2D1_BRUSH_PROPERTIES {
-            opacity: a,
-            transform: Matrix3x2::identity(),
+    #[inline]
+    unsafe fn push_clip_rect(&mut self, rect: Rect) {
+        let geometry = self
+            .factory
+            .CreateRectangleGeometry(&D2D_RECT_F {
+                left: rect.x,
+                right: rect.right(),
+                top: rect.y,
+                bottom: rect.bottom(),
+            })
+            .map(|geometry| Some(geometry.into()));
+        self.push_masked_layer(geometry, 1.0);
+    }
+
+    #[inline]
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        let geometry = self
+            .factory
+            .CreateRoundedRectangleGeometry(&D2D1_ROUNDED_RECT {
+                rect: D2D_RECT_F {
+                    left: rect.x,
+                    right: rect.right(),
+                    top: rect.y,
+                    bottom: rect.bottom(),
+                },
+                radiusX: radius,
+                radiusY: radius,
+            })
+            .map(|geometry| Some(geometry.into()));
+        self.push_masked_layer(geometry, 1.0);
+    }
+
+    #[inline]
+    unsafe fn push_clip_path(&mut self, path: &Path) {
+        let geometry = self
+            .create_geometry(path, D2D1_FIGURE_BEGIN_FILLED)
+            .map(|geometry| Some(geometry.into()));
+        self.push_masked_layer(geometry, 1.0);
+    }
+
+    #[inline]
+    unsafe fn pop_clip(&mut self) {
+        if self.layers.pop() == Some(true) {
+            self.target.PopLayer();
+        }
+    }
+
+    #[inline]
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode) {
+        // A render target only blends primitives with source-over, min, max and add
+        let blend = match mode {
+            BlendMode::Normal => D2D1_PRIMITIVE_BLEND_SOURCE_OVER,
+            BlendMode::Add => D2D1_PRIMITIVE_BLEND_ADD,
+            _ => {
+                warn!("{:?} is not supported by Direct2D, Normal is used", mode);
+                D2D1_PRIMITIVE_BLEND_SOURCE_OVER
+            }
         };
+        match self.target.cast::<ID2D1DeviceContext>() {
+            Ok(context) => context.SetPrimitiveBlend(blend),
+            Err(e) => self.record_error(e),
+        }
+    }
 
-        let brush = &self
-            .target
-            .CreateSolidColorBrush(&color, &properties)
-            .unwrap();
+    #[inline]
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        if mode != BlendMode::Normal {
+            warn!(
+                "{:?} is not supported by Direct2D layers, Normal is used",
+                mode
+            );
+        }
+        self.push_masked_layer(Ok(None), opacity);
+    }
+
+    #[inline]
+    unsafe fn pop_layer(&mut self) {
+        self.pop_clip();
+    }
+
+    #[inline]
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint) {
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
+        };
+        let brush = &brush;
 
         self.target.FillEllipse(
             &mut D2D1_ELLIPSE {
                 point: D2D_POINT_2F {
-                    x: point.0 + radius,
-                    y: point.1 + radius,
+                    x: point.x + radius,
+                    y: point.y + radius,
                 },
                 radiusX: radius,
                 radiusY: radius,
@@ -80,65 +240,153 @@ impl Backend for Direct2DBackend {
     }
 
     #[inline]
-    unsafe fn rectangle(
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint) {
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
+        };
+        let brush = &brush;
+
+        let rect = D2D_RECT_F {
+            left: rect.x,
+            right: rect.right(),
+            top: rect.y,
+            bottom: rect.bottom(),
+        };
+
+        self.target.FillRectangle(&rect, brush);
+    }
+
+    #[inline]
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint) {
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
+        };
+        let brush = &brush;
+
+        let rect = D2D_RECT_F {
+            left: rect.x,
+            right: rect.right(),
+            top: rect.y,
+            bottom: rect.bottom(),
+        };
+
+        let rounded_rect = D2D1_ROUNDED_RECT {
+            rect,
+            radiusX: radius,
+            radiusY: radius,
+        };
+
+        self.target.FillRoundedRectangle(&rounded_rect, brush);
+    }
+
+    #[inline]
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint) {
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
+        };
+
+        let stroke_style = match self.create_stroke_style(style) {
+            Ok(stroke_style) => stroke_style,
+            Err(e) => return self.record_error(e),
+        };
+
+        self.target.DrawLine(
+            D2D_POINT_2F {
+                x: point1.x,
+                y: point1.y,
+            },
+            D2D_POINT_2F {
+                x: point2.x,
+                y: point2.y,
+            },
+            &brush,
+            style.width,
+            &stroke_style,
+        );
+    }
+
+    #[inline]
+    unsafe fn stroke_ellipse(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        point: Point,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
-        let color = D2D1_COLOR_F { r, g, b, a };
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
+        };
 
-        let properties = D2D1_BRUSH_PROPERTIES {
-            opacity: a,
-            transform: Matrix3x2::identity(),
+        let stroke_style = match self.create_stroke_style(style) {
+            Ok(stroke_style) => stroke_style,
+            Err(e) => return self.record_error(e),
         };
 
-        let brush = &self
-            .target
-            .CreateSolidColorBrush(&color, &properties)
-            .unwrap();
+        self.target.DrawEllipse(
+            &D2D1_ELLIPSE {
+                point: D2D_POINT_2F {
+                    x: point.x + radius,
+                    y: point.y + radius,
+                },
+                radiusX: radius,
+                radiusY: radius,
+            },
+            &brush,
+            style.width,
+            &stroke_style,
+        );
+    }
+
+    #[inline]
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint) {
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
+        };
+
+        let stroke_style = match self.create_stroke_style(style) {
+            Ok(stroke_style) => stroke_style,
+            Err(e) => return self.record_error(e),
+        };
 
         let rect = D2D_RECT_F {
-            left: point1.0,
-            right: point1.0 + point2.0,
-            top: point1.1,
-            bottom: point1.1 + point2.1,
+            left: rect.x,
+            right: rect.right(),
+            top: rect.y,
+            bottom: rect.bottom(),
         };
 
-        self.target.FillRectangle(&rect, brush);
+        self.target
+            .DrawRectangle(&rect, &brush, style.width, &stroke_style);
     }
 
     #[inline]
-    unsafe fn rounded_rectangle(
+    unsafe fn stroke_rounded_rectangle(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
+        rect: Rect,
         radius: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
-        let color = D2D1_COLOR_F { r, g, b, a };
-
-        let properties = D2D1_BRUSH_PROPERTIES {
-            opacity: a,
-            transform: Matrix3x2::identity(),
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
         };
 
-        let brush = &self
-            .target
-            .CreateSolidColorBrush(&color, &properties)
-            .unwrap();
+        let stroke_style = match self.create_stroke_style(style) {
+            Ok(stroke_style) => stroke_style,
+            Err(e) => return self.record_error(e),
+        };
 
         let rect = D2D_RECT_F {
-            left: point1.0,
-            right: point1.0 + point2.0,
-            top: point1.1,
-            bottom: point1.1 + point2.1,
+            left: rect.x,
+            right: rect.right(),
+            top: rect.y,
+            bottom: rect.bottom(),
         };
 
         let rounded_rect = D2D1_ROUNDED_RECT {
@@ -147,57 +395,98 @@ impl Backend for Direct2DBackend {
             radiusY: radius,
         };
 
-        self.target.FillRoundedRectangle(&rounded_rect, brush);
+        self.target
+            .DrawRoundedRectangle(&rounded_rect, &brush, style.width, &stroke_style);
     }
 
     #[inline]
-    unsafe fn line(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        stroke: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    ) {
-        let color = D2D1_COLOR_F { r, g, b, a };
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint) {
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
+        };
 
-        let properties = D2D1_BRUSH_PROPERTIES {
-            opacity: a,
-            transform: Matrix3x2::identity(),
+        let geometry = match self.create_geometry(path, D2D1_FIGURE_BEGIN_FILLED) {
+            Ok(geometry) => geometry,
+            Err(e) => return self.record_error(e),
         };
 
-        let brush1 = &self
-            .target
-            .CreateSolidColorBrush(&color, &properties)
-            .unwrap();
+        self.target
+            .FillGeometry(&geometry, &brush, None::<&ID2D1Brush>);
+    }
 
-        let props = D2D1_STROKE_STYLE_PROPERTIES {
-            startCap: D2D1_CAP_STYLE_ROUND,
-            endCap: D2D1_CAP_STYLE_TRIANGLE,
-            ..Default::default()
+    #[inline]
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint) {
+        let brush = match self.create_brush(paint) {
+            Ok(brush) => brush,
+            Err(e) => return self.record_error(e),
         };
 
-        let style = unsafe { self.factory.CreateStrokeStyle(&props, &[]).unwrap() };
+        let stroke_style = match self.create_stroke_style(style) {
+            Ok(stroke_style) => stroke_style,
+            Err(e) => return self.record_error(e),
+        };
 
-        self.target.DrawLine(
-            D2D_POINT_2F {
-                x: point1.0,
-                y: point1.1,
-            },
-            D2D_POINT_2F {
-                x: point2.0,
-                y: point2.1,
+        let geometry = match self.create_geometry(path, D2D1_FIGURE_BEGIN_HOLLOW) {
+            Ok(geometry) => geometry,
+            Err(e) => return self.record_error(e),
+        };
+
+        self.target
+            .DrawGeometry(&geometry, &brush, style.width, &stroke_style);
+    }
+
+    #[inline]
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        let bitmap = match self.bitmaps.get(&image.id()) {
+            Some((_, bitmap)) => bitmap.clone(),
+            None => match self.create_bitmap(image) {
+                Ok(bitmap) => {
+                    self.bitmaps.insert(
+                        image.id(),
+                        (std::sync::Arc::downgrade(image.data()), bitmap.clone()),
+                    );
+                    bitmap
+                }
+                Err(e) => return self.record_error(e),
             },
-            brush1,
-            stroke,
-            style,
-        );
+        };
+
+        let dest = D2D_RECT_F {
+            left: dest.x,
+            top: dest.y,
+            right: dest.x + dest.width,
+            bottom: dest.y + dest.height,
+        };
+        let src = D2D_RECT_F {
+            left: src.x,
+            top: src.y,
+            right: src.x + src.width,
+            bottom: src.y + src.height,
+        };
+        let interpolation = match image.filter() {
+            ImageFilter::Nearest => D2D1_BITMAP_INTERPOLATION_MODE_NEAREST_NEIGHBOR,
+            ImageFilter::Bilinear => D2D1_BITMAP_INTERPOLATION_MODE_LINEAR,
+        };
+        self.target
+            .DrawBitmap(&bitmap, &dest, opacity, interpolation, &src);
+    }
+
+    #[inline]
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint) {
+        self.fill_path(&run.to_path(origin), paint);
+    }
+
+    #[inline]
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        None
     }
 }
 
-fn create_target(hwnd: HWND, factory: &ID2D1Factory1) -> (ID2D1HwndRenderTarget, u32, u32) {
+fn create_target(
+    hwnd: HWND,
+    factory: &ID2D1Factory1,
+) -> std::result::Result<(ID2D1HwndRenderTarget, u32, u32), Error> {
     let mut rect = RECT::default();
 
     unsafe {
@@ -220,31 +509,309 @@ fn create_target(hwnd: HWND, factory: &ID2D1Factory1) -> (ID2D1HwndRenderTarget,
     let target = unsafe {
         factory
             .CreateHwndRenderTarget(&render_properties, &hwnd_render_properties)
-            .unwrap()
+            .map_err(|e| Error::SurfaceCreationFailed(e.message().to_string()))?
     };
-    (
+    Ok((
         target,
         (rect.right - rect.left) as u32,
         (rect.bottom - rect.top) as u32,
-    )
+    ))
 }
 
 impl Direct2DBackend {
     /// Create a new backend
-    fn new(hwnd: HWND) -> Result<Self> {
-        let factory = create_factory()?;
-        let (target, width, height) = create_target(hwnd, &factory);
+    fn new(hwnd: HWND) -> std::result::Result<Self, Error> {
+        let factory = shared_factory()?;
+        let (target, width, height) = create_target(hwnd, &factory)?;
         Ok(Self {
             handle: hwnd,
             width,
             height,
             factory,
             target,
+            bitmaps: HashMap::new(),
+            layers: Vec::new(),
+            error: None,
         })
     }
 
+    /// Creates a brush that draws the paint
+    fn create_brush(&self, paint: &Paint) -> Result<ID2D1Brush> {
+        // The alpha of the colors is already applied by the brush, so the brush itself is opaque
+        let properties = D2D1_BRUSH_PROPERTIES {
+            opacity: 1.0,
+            transform: Matrix3x2::identity(),
+        };
+        let solid = |r: f32, g: f32, b: f32, a: f32| -> Result<ID2D1Brush> {
+            let brush = unsafe {
+                self.target
+                    .CreateSolidColorBrush(&D2D1_COLOR_F { r, g, b, a }, &properties)?
+            };
+            Ok(brush.into())
+        };
+
+        match paint {
+            Paint::Solid(color) => {
+                let (r, g, b, a) = color_rgba(*color);
+                solid(r, g, b, a)
+            }
+            // Gradients without stops are transparent
+            Paint::LinearGradient(gradient) if gradient.stops.is_empty() => {
+                solid(0.0, 0.0, 0.0, 0.0)
+            }
+            Paint::RadialGradient(gradient) if gradient.stops.is_empty() => {
+                solid(0.0, 0.0, 0.0, 0.0)
+            }
+            Paint::LinearGradient(gradient) => {
+                let stops = self.create_gradient_stops(&gradient.stops, gradient.extend)?;
+                let gradient_properties = D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                    startPoint: D2D_POINT_2F {
+                        x: gradient.start.x,
+                        y: gradient.start.y,
+                    },
+                    endPoint: D2D_POINT_2F {
+                        x: gradient.end.x,
+                        y: gradient.end.y,
+                    },
+                };
+                let brush = unsafe {
+                    self.target.CreateLinearGradientBrush(
+                        &gradient_properties,
+                        &properties,
+                        &stops,
+                    )?
+                };
+                Ok(brush.into())
+            }
+            Paint::RadialGradient(gradient) => {
+                let stops = self.create_gradient_stops(&gradient.stops, gradient.extend)?;
+                let gradient_properties = D2D1_RADIAL_GRADIENT_BRUSH_PROPERTIES {
+                    center: D2D_POINT_2F {
+                        x: gradient.center.x,
+                        y: gradient.center.y,
+                    },
+                    gradientOriginOffset: D2D_POINT_2F { x: 0.0, y: 0.0 },
+                    radiusX: gradient.radius,
+                    radiusY: gradient.radius,
+                };
+                let brush = unsafe {
+                    self.target.CreateRadialGradientBrush(
+                        &gradient_properties,
+                        &properties,
+                        &stops,
+                    )?
+                };
+                Ok(brush.into())
+            }
+        }
+    }
+
+    /// Converts gradient stops into a Direct2D stop collection
+    fn create_gradient_stops(
+        &self,
+        stops: &[GradientStop],
+        extend: ExtendMode,
+    ) -> Result<ID2D1GradientStopCollection> {
+        let stops: Vec<D2D1_GRADIENT_STOP> = premultiplied_stops(stops)
+            .iter()
+            .map(|stop| {
+                let (r, g, b, a) = color_rgba(stop.color);
+                D2D1_GRADIENT_STOP {
+                    position: stop.offset,
+                    color: D2D1_COLOR_F { r, g, b, a },
+                }
+            })
+            .collect();
+        let extend = match extend {
+            ExtendMode::Pad => D2D1_EXTEND_MODE_CLAMP,
+            ExtendMode::Repeat => D2D1_EXTEND_MODE_WRAP,
+            ExtendMode::Reflect => D2D1_EXTEND_MODE_MIRROR,
+        };
+        unsafe {
+            self.target
+                .CreateGradientStopCollection(&stops, D2D1_GAMMA_2_2, extend)
+        }
+    }
+
+    /// Converts a StrokeStyle into a Direct2D stroke style
+    fn create_stroke_style(&self, style: &StrokeStyle) -> Result<ID2D1StrokeStyle> {
+        // Direct2D measures dashes in multiples of the stroke width
+        let unit = if style.width > 0.0 { style.width } else { 1.0 };
+        let mut dashes: Vec<f32> = Vec::new();
+        if style.is_dashed() {
+            dashes = style.dash.iter().map(|d| d / unit).collect();
+            if dashes.len() % 2 == 1 {
+                dashes.extend_from_within(..);
+            }
+        }
+
+        let cap = match style.line_cap {
+            LineCap::Butt => D2D1_CAP_STYLE_FLAT,
+            LineCap::Round => D2D1_CAP_STYLE_ROUND,
+            LineCap::Square => D2D1_CAP_STYLE_SQUARE,
+        };
+        let properties = D2D1_STROKE_STYLE_PROPERTIES {
+            startCap: cap,
+            endCap: cap,
+            dashCap: cap,
+            // Plain miter joins are never beveled in Direct2D, unlike cairo
+            lineJoin: match style.line_join {
+                LineJoin::Miter => D2D1_LINE_JOIN_MITER_OR_BEVEL,
+                LineJoin::Round => D2D1_LINE_JOIN_ROUND,
+                LineJoin::Bevel => D2D1_LINE_JOIN_BEVEL,
+            },
+            miterLimit: style.miter_limit,
+            dashStyle: if dashes.is_empty() {
+                D2D1_DASH_STYLE_SOLID
+            } else {
+                D2D1_DASH_STYLE_CUSTOM
+            },
+            dashOffset: style.dash_offset / unit,
+        };
+        unsafe { self.factory.CreateStrokeStyle(&properties, &dashes) }
+    }
+
+    /// Converts a Path into a Direct2D path geometry
+    fn create_geometry(&self, path: &Path, begin: D2D1_FIGURE_BEGIN) -> Result<ID2D1PathGeometry> {
+        let point = |x: f32, y: f32| D2D_POINT_2F { x, y };
+        unsafe {
+            let geometry = self.factory.CreatePathGeometry()?;
+            let sink = geometry.Open()?;
+            sink.SetFillMode(D2D1_FILL_MODE_WINDING);
+
+            let mut open = false;
+            let mut start = point(0.0, 0.0);
+            let mut current = point(0.0, 0.0);
+            for element in path.elements() {
+                // Direct2D requires every segment to be inside of a figure
+                if !open && !matches!(element, PathElement::MoveTo(..) | PathElement::Close) {
+                    sink.BeginFigure(current, begin);
+                    start = current;
+                    open = true;
+                }
+                match *element {
+                    PathElement::MoveTo(x, y) => {
+                        if open {
+                            sink.EndFigure(D2D1_FIGURE_END_OPEN);
+                        }
+                        current = point(x, y);
+                        start = current;
+                        sink.BeginFigure(current, begin);
+                        open = true;
+                    }
+                    PathElement::LineTo(x, y) => {
+                        current = point(x, y);
+                        sink.AddLine(current);
+                    }
+                    PathElement::QuadTo(x1, y1, x, y) => {
+                        current = point(x, y);
+                        sink.AddQuadraticBezier(&D2D1_QUADRATIC_BEZIER_SEGMENT {
+                            point1: point(x1, y1),
+                            point2: current,
+                        });
+                    }
+                    PathElement::CubicTo(x1, y1, x2, y2, x, y) => {
+                        current = point(x, y);
+                        sink.AddBezier(&D2D1_BEZIER_SEGMENT {
+                            point1: point(x1, y1),
+                            point2: point(x2, y2),
+                            point3: current,
+                        });
+                    }
+                    PathElement::Close => {
+                        if open {
+                            sink.EndFigure(D2D1_FIGURE_END_CLOSED);
+                            open = false;
+                        }
+                        current = start;
+                    }
+                }
+            }
+            if open {
+                sink.EndFigure(D2D1_FIGURE_END_OPEN);
+            }
+            sink.Close()?;
+            Ok(geometry)
+        }
+    }
+
+    /// Uploads the pixels of the image as premultiplied BGRA
+    fn create_bitmap(&self, image: &Image) -> Result<ID2D1Bitmap> {
+        let pixels: Vec<u8> = image
+            .pixels()
+            .chunks_exact(4)
+            .flat_map(|px| {
+                let a = px[3] as u32;
+                let premultiply = |c: u8| ((c as u32 * a + 127) / 255) as u8;
+                [
+                    premultiply(px[2]),
+                    premultiply(px[1]),
+                    premultiply(px[0]),
+                    px[3],
+                ]
+            })
+            .collect();
+        let properties = D2D1_BITMAP_PROPERTIES {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0,
+            dpiY: 96.0,
+        };
+        unsafe {
+            self.target.CreateBitmap(
+                D2D_SIZE_U {
+                    width: image.width(),
+                    height: image.height(),
+                },
+                pixels.as_ptr() as *const _,
+                image.width() * 4,
+                &properties,
+            )
+        }
+    }
+
+    /// Pushes a layer masked by the geometry, which is mapped by the current transform.
+    /// Without a geometry, the layer covers the whole target
+    fn push_masked_layer(&mut self, geometry: Result<Option<ID2D1Geometry>>, opacity: f32) {
+        let geometry = match geometry {
+            Ok(geometry) => geometry,
+            Err(e) => {
+                self.layers.push(false);
+                return self.record_error(e);
+            }
+        };
+        let parameters = D2D1_LAYER_PARAMETERS {
+            contentBounds: D2D_RECT_F {
+                left: f32::MIN,
+                top: f32::MIN,
+                right: f32::MAX,
+                bottom: f32::MAX,
+            },
+            geometricMask: geometry,
+            maskAntialiasMode: D2D1_ANTIALIAS_MODE_PER_PRIMITIVE,
+            maskTransform: Matrix3x2::identity(),
+            opacity,
+            opacityBrush: None,
+            layerOptions: D2D1_LAYER_OPTIONS_NONE,
+        };
+        unsafe {
+            self.target.PushLayer(&parameters, None::<&ID2D1Layer>);
+        }
+        self.layers.push(true);
+    }
+
+    /// Remembers the first error of the frame, it is returned from flush()
+    fn record_error(&mut self, error: windows::core::Error) {
+        error!("Direct2D error: {}", error.message());
+        if self.error.is_none() {
+            self.error = Some(error.into());
+        }
+    }
+
     /// Regenerate Target (to accommodate window resizing)
-    fn update_target(&mut self) {
+    fn update_target(&mut self) -> std::result::Result<(), Error> {
         let mut rect = RECT::default();
 
         unsafe {
@@ -272,9 +839,11 @@ impl Direct2DBackend {
             self.target = unsafe {
                 self.factory
                     .CreateHwndRenderTarget(&render_properties, &hwnd_render_properties)
-                    .unwrap()
+                    .map_err(|e| Error::SurfaceCreationFailed(e.message().to_string()))?
             };
+            self.bitmaps.clear();
         }
+        Ok(())
     }
 }
 
@@ -0,0 +1,61 @@
+//! Errors reported by Bly
+
+use std::fmt;
+
+/// Errors that can occur while creating a canvas or drawing on it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The window handle belongs to a platform that Bly cannot draw on.
+    /// Contains the kind of the handle (e.g. "UiKit")
+    UnsupportedPlatform(&'static str),
+    /// The backend of the canvas cannot do what was asked.
+    /// Contains a description of it (e.g. "blending in linear light")
+    Unsupported(&'static str),
+    /// Connecting to the display server failed
+    DisplayConnectionFailed,
+    /// The surface to draw on could not be created
+    SurfaceCreationFailed(String),
+    /// The drawing context could not be created
+    ContextCreationFailed(String),
+    /// An EGL call failed
+    EglError(String),
+    /// An API of the underlying platform (Direct2D, the DOM, etc.) failed
+    PlatformError(String),
+    /// Drawing a frame failed
+    DrawingFailed(String),
+    /// An image could not be read or decoded
+    ImageDecodingFailed(String),
+    /// A font could not be read or parsed
+    FontLoadingFailed(String),
+    /// A color could not be parsed
+    ColorParsingFailed(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedPlatform(kind) => write!(f, "unsupported platform: {}", kind),
+            Error::Unsupported(what) => write!(f, "not supported by the backend: {}", what),
+            Error::DisplayConnectionFailed => write!(f, "failed to connect to the display"),
+            Error::SurfaceCreationFailed(reason) => {
+                write!(f, "failed to create the surface: {}", reason)
+            }
+            Error::ContextCreationFailed(reason) => {
+                write!(f, "failed to create the drawing context: {}", reason)
+            }
+            Error::EglError(reason) => write!(f, "EGL error: {}", reason),
+            Error::PlatformError(reason) => write!(f, "platform error: {}", reason),
+            Error::DrawingFailed(reason) => write!(f, "drawing failed: {}", reason),
+            Error::ImageDecodingFailed(reason) => {
+                write!(f, "failed to decode the image: {}", reason)
+            }
+            Error::FontLoadingFailed(reason) => write!(f, "failed to load the font: {}", reason),
+            Error::ColorParsingFailed(reason) => {
+                write!(f, "failed to parse the color: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
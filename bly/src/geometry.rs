@@ -0,0 +1,509 @@
+//! Points, vectors, sizes and rectangles in pixels of the canvas
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// # Point
+/// A position, with the y axis pointing down.
+/// Moving a point by a Vector gives another point, and the difference of two points is a Vector.
+/// A tuple can be passed wherever a point is expected
+/// ```
+/// use bly::{Point, Vector};
+///
+/// let a = Point::new(10.0, 20.0);
+/// let b = a + Vector::new(5.0, -5.0);
+/// assert_eq!(b, Point::new(15.0, 15.0));
+/// assert_eq!(b - a, Vector::new(5.0, -5.0));
+/// assert_eq!(Point::from((15.0, 15.0)), b);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    /// The top left of the canvas
+    pub const ORIGIN: Point = Point::new(0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    /// The vector from the origin to the point
+    #[inline]
+    pub fn to_vector(self) -> Vector {
+        Vector::new(self.x, self.y)
+    }
+
+    /// Distance between two points
+    #[inline]
+    pub fn distance(self, other: Point) -> f32 {
+        (other - self).length()
+    }
+}
+
+/// # Vector
+/// A displacement or a direction
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Vector {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vector {
+    pub const ZERO: Vector = Vector::new(0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    #[inline]
+    pub fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    #[inline]
+    pub fn dot(self, other: Vector) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The point at this displacement from the origin
+    #[inline]
+    pub fn to_point(self) -> Point {
+        Point::new(self.x, self.y)
+    }
+}
+
+/// # Size
+/// A width and a height
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Size {
+    pub const ZERO: Size = Size::new(0.0, 0.0);
+
+    pub const fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
+    /// Returns true if the size covers no area
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        !(self.width > 0.0 && self.height > 0.0)
+    }
+}
+
+/// # Rectangle
+/// A rectangle given by its top left corner and its size.
+/// Rectangles without a positive width and height are empty
+/// ```
+/// use bly::{Point, Rect, Size};
+///
+/// let a = Rect::new(0.0, 0.0, 100.0, 50.0);
+/// let b = Rect::from_origin_size((50.0, 25.0), (100.0, 100.0));
+/// assert!(a.contains(Point::new(10.0, 10.0)));
+/// assert_eq!(a.intersect(&b), Some(Rect::new(50.0, 25.0, 50.0, 25.0)));
+/// assert_eq!(a.union(&b), Rect::new(0.0, 0.0, 150.0, 125.0));
+/// assert_eq!(a.inset(10.0, 5.0).size(), Size::new(80.0, 40.0));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_origin_size(origin: impl Into<Point>, size: impl Into<Size>) -> Self {
+        let (origin, size) = (origin.into(), size.into());
+        Self::new(origin.x, origin.y, size.width, size.height)
+    }
+
+    /// The smallest rectangle containing both points
+    pub fn from_points(a: impl Into<Point>, b: impl Into<Point>) -> Self {
+        let (a, b) = (a.into(), b.into());
+        let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+        Self::new(x, y, a.x.max(b.x) - x, a.y.max(b.y) - y)
+    }
+
+    /// The top left corner
+    #[inline]
+    pub fn origin(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+
+    #[inline]
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    #[inline]
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    #[inline]
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    #[inline]
+    pub fn center(&self) -> Point {
+        Point::new(self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.size().is_empty()
+    }
+
+    /// Returns true if the point is inside of the rectangle.
+    /// The left and top edges are inside, the right and bottom ones are not
+    #[inline]
+    pub fn contains(&self, point: impl Into<Point>) -> bool {
+        let point = point.into();
+        point.x >= self.x && point.x < self.right() && point.y >= self.y && point.y < self.bottom()
+    }
+
+    /// The part of the rectangle that is also inside of other, None if they do not overlap
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let rect = Rect::new(
+            x,
+            y,
+            self.right().min(other.right()) - x,
+            self.bottom().min(other.bottom()) - y,
+        );
+        (!rect.is_empty()).then_some(rect)
+    }
+
+    /// The smallest rectangle containing both rectangles. Empty rectangles are left out
+    pub fn union(&self, other: &Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Rect::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    /// The rectangle with its left and right edges moved inside by dx, and its top and bottom edges by dy.
+    /// Negative values grow the rectangle
+    pub fn inset(&self, dx: f32, dy: f32) -> Rect {
+        Rect::new(
+            self.x + dx,
+            self.y + dy,
+            self.width - 2.0 * dx,
+            self.height - 2.0 * dy,
+        )
+    }
+}
+
+impl Add<Vector> for Point {
+    type Output = Point;
+
+    #[inline]
+    fn add(self, vector: Vector) -> Point {
+        Point::new(self.x + vector.x, self.y + vector.y)
+    }
+}
+
+impl Sub<Vector> for Point {
+    type Output = Point;
+
+    #[inline]
+    fn sub(self, vector: Vector) -> Point {
+        Point::new(self.x - vector.x, self.y - vector.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Vector;
+
+    #[inline]
+    fn sub(self, other: Point) -> Vector {
+        Vector::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl AddAssign<Vector> for Point {
+    #[inline]
+    fn add_assign(&mut self, vector: Vector) {
+        *self = *self + vector;
+    }
+}
+
+impl SubAssign<Vector> for Point {
+    #[inline]
+    fn sub_assign(&mut self, vector: Vector) {
+        *self = *self - vector;
+    }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+
+    #[inline]
+    fn add(self, other: Vector) -> Vector {
+        Vector::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+
+    #[inline]
+    fn sub(self, other: Vector) -> Vector {
+        Vector::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Neg for Vector {
+    type Output = Vector;
+
+    #[inline]
+    fn neg(self) -> Vector {
+        Vector::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<f32> for Vector {
+    type Output = Vector;
+
+    #[inline]
+    fn mul(self, factor: f32) -> Vector {
+        Vector::new(self.x * factor, self.y * factor)
+    }
+}
+
+impl Mul<Vector> for f32 {
+    type Output = Vector;
+
+    #[inline]
+    fn mul(self, vector: Vector) -> Vector {
+        vector * self
+    }
+}
+
+impl Div<f32> for Vector {
+    type Output = Vector;
+
+    #[inline]
+    fn div(self, divisor: f32) -> Vector {
+        Vector::new(self.x / divisor, self.y / divisor)
+    }
+}
+
+impl AddAssign for Vector {
+    #[inline]
+    fn add_assign(&mut self, other: Vector) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vector {
+    #[inline]
+    fn sub_assign(&mut self, other: Vector) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<f32> for Vector {
+    #[inline]
+    fn mul_assign(&mut self, factor: f32) {
+        *self = *self * factor;
+    }
+}
+
+impl DivAssign<f32> for Vector {
+    #[inline]
+    fn div_assign(&mut self, divisor: f32) {
+        *self = *self / divisor;
+    }
+}
+
+impl Mul<f32> for Size {
+    type Output = Size;
+
+    #[inline]
+    fn mul(self, factor: f32) -> Size {
+        Size::new(self.width * factor, self.height * factor)
+    }
+}
+
+impl Div<f32> for Size {
+    type Output = Size;
+
+    #[inline]
+    fn div(self, divisor: f32) -> Size {
+        Size::new(self.width / divisor, self.height / divisor)
+    }
+}
+
+/// Moves the rectangle
+impl Add<Vector> for Rect {
+    type Output = Rect;
+
+    #[inline]
+    fn add(self, vector: Vector) -> Rect {
+        Rect::from_origin_size(self.origin() + vector, self.size())
+    }
+}
+
+/// Moves the rectangle
+impl Sub<Vector> for Rect {
+    type Output = Rect;
+
+    #[inline]
+    fn sub(self, vector: Vector) -> Rect {
+        Rect::from_origin_size(self.origin() - vector, self.size())
+    }
+}
+
+impl From<(f32, f32)> for Point {
+    #[inline]
+    fn from((x, y): (f32, f32)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl From<Point> for (f32, f32) {
+    #[inline]
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl From<(f32, f32)> for Vector {
+    #[inline]
+    fn from((x, y): (f32, f32)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl From<Vector> for (f32, f32) {
+    #[inline]
+    fn from(vector: Vector) -> Self {
+        (vector.x, vector.y)
+    }
+}
+
+impl From<(f32, f32)> for Size {
+    #[inline]
+    fn from((width, height): (f32, f32)) -> Self {
+        Self::new(width, height)
+    }
+}
+
+impl From<Size> for (f32, f32) {
+    #[inline]
+    fn from(size: Size) -> Self {
+        (size.width, size.height)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<mint::Point2<f32>> for Point {
+    #[inline]
+    fn from(point: mint::Point2<f32>) -> Self {
+        Self::new(point.x, point.y)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<Point> for mint::Point2<f32> {
+    #[inline]
+    fn from(point: Point) -> Self {
+        mint::Point2 {
+            x: point.x,
+            y: point.y,
+        }
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<mint::Vector2<f32>> for Vector {
+    #[inline]
+    fn from(vector: mint::Vector2<f32>) -> Self {
+        Self::new(vector.x, vector.y)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<Vector> for mint::Vector2<f32> {
+    #[inline]
+    fn from(vector: Vector) -> Self {
+        mint::Vector2 {
+            x: vector.x,
+            y: vector.y,
+        }
+    }
+}
+
+/// mint has no size type, so sizes are vectors from one corner to the other
+#[cfg(feature = "mint")]
+impl From<mint::Vector2<f32>> for Size {
+    #[inline]
+    fn from(vector: mint::Vector2<f32>) -> Self {
+        Self::new(vector.x, vector.y)
+    }
+}
+
+#[cfg(feature = "mint")]
+impl From<Size> for mint::Vector2<f32> {
+    #[inline]
+    fn from(size: Size) -> Self {
+        mint::Vector2 {
+            x: size.width,
+            y: size.height,
+        }
+    }
+}
+
+/// Pair of values, which Bly used for both points and sizes
+#[deprecated(note = "use Point for positions and Size for sizes")]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Point2<T>(pub T, pub T);
+
+#[allow(deprecated)]
+impl<T> Point2<T> {
+    pub fn new(a: T, b: T) -> Self {
+        Self(a, b)
+    }
+}
+
+#[allow(deprecated)]
+impl From<Point2<f32>> for Point {
+    #[inline]
+    fn from(point: Point2<f32>) -> Self {
+        Self::new(point.0, point.1)
+    }
+}
+
+#[allow(deprecated)]
+impl From<Point2<f32>> for Size {
+    #[inline]
+    fn from(size: Point2<f32>) -> Self {
+        Self::new(size.0, size.1)
+    }
+}
@@ -0,0 +1,220 @@
+//! Bitmaps that can be drawn with Painter::draw_image()
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "decode")]
+use crate::Error;
+
+/// How an image is sampled when it is scaled
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ImageFilter {
+    /// Uses the closest pixel, keeping hard pixel edges (e.g. for pixel art)
+    Nearest,
+    /// Blends the four closest pixels
+    #[default]
+    Bilinear,
+}
+
+/// Pixels shared by every clone of an Image
+#[derive(Debug)]
+#[cfg_attr(
+    not(any(feature = "xlib", feature = "wayland", windows, target_arch = "wasm32")),
+    allow(dead_code)
+)]
+pub(crate) struct ImageData {
+    /// Identifies the pixels in the caches of backends
+    pub id: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Straight (non-premultiplied) RGBA8
+    pub pixels: Vec<u8>,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// # Image
+/// A bitmap that can be drawn with Painter::draw_image().
+/// Cloning an image is cheap, the pixels are shared. Backends may keep a copy of the pixels
+/// in their own format (uploaded) for as long as any clone of the image exists.
+/// ```
+/// // A 2x1 image with a red and a blue pixel
+/// let image = bly::Image::from_rgba(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 255]).unwrap();
+/// assert_eq!(image.width(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Image {
+    data: Arc<ImageData>,
+    filter: ImageFilter,
+}
+
+impl PartialEq for Image {
+    fn eq(&self, other: &Self) -> bool {
+        self.filter == other.filter
+            && self.data.width == other.data.width
+            && self.data.height == other.data.height
+            && self.data.pixels == other.data.pixels
+    }
+}
+
+impl Image {
+    /// Creates an image from straight (non-premultiplied) RGBA8 pixels, row by row from the top left.
+    /// Returns None if the length of pixels is not width * height * 4
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+        Some(Self {
+            data: Arc::new(ImageData {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                width,
+                height,
+                pixels,
+            }),
+            filter: ImageFilter::default(),
+        })
+    }
+
+    /// Decodes a PNG or JPEG image
+    #[cfg(feature = "decode")]
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        const JPEG_SIGNATURE: &[u8] = &[0xff, 0xd8, 0xff];
+
+        let (width, height, pixels) = if bytes.starts_with(PNG_SIGNATURE) {
+            decode_png(bytes)?
+        } else if bytes.starts_with(JPEG_SIGNATURE) {
+            decode_jpeg(bytes)?
+        } else {
+            return Err(Error::ImageDecodingFailed(
+                "unknown image format".to_string(),
+            ));
+        };
+        Self::from_rgba(width, height, pixels).ok_or_else(|| {
+            Error::ImageDecodingFailed("image data is shorter than its size".to_string())
+        })
+    }
+
+    /// Reads and decodes a PNG or JPEG file
+    #[cfg(feature = "decode")]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let bytes = std::fs::read(path).map_err(|e| Error::ImageDecodingFailed(e.to_string()))?;
+        Self::decode(&bytes)
+    }
+
+    /// Returns the same image with another filter
+    pub fn with_filter(mut self, filter: ImageFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// How the image is sampled when it is scaled
+    #[inline]
+    pub fn filter(&self) -> ImageFilter {
+        self.filter
+    }
+
+    /// Width of the image in pixels
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.data.width
+    }
+
+    /// Height of the image in pixels
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.data.height
+    }
+
+    /// RGBA8 pixels of the image
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.data.pixels
+    }
+
+    /// Identifies the pixels in the caches of backends
+    #[inline]
+    #[cfg_attr(
+        not(any(feature = "xlib", feature = "wayland", windows, target_arch = "wasm32")),
+        allow(dead_code)
+    )]
+    pub(crate) fn id(&self) -> u64 {
+        self.data.id
+    }
+
+    /// Used by backends to find out when the image is gone
+    #[inline]
+    #[cfg_attr(
+        not(any(feature = "xlib", feature = "wayland", windows, target_arch = "wasm32")),
+        allow(dead_code)
+    )]
+    pub(crate) fn data(&self) -> &Arc<ImageData> {
+        &self.data
+    }
+}
+
+#[cfg(feature = "decode")]
+fn decode_png(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), Error> {
+    let error = |e: png::DecodingError| Error::ImageDecodingFailed(e.to_string());
+
+    let mut decoder = png::Decoder::new(bytes);
+    // Palettes are expanded and 16 bit channels are reduced to 8 bit
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(error)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(error)?;
+    buffer.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(Error::ImageDecodingFailed(
+                "indexed colors were not expanded".to_string(),
+            ))
+        }
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+#[cfg(feature = "decode")]
+fn decode_jpeg(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), Error> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let buffer = decoder
+        .decode()
+        .map_err(|e| Error::ImageDecodingFailed(e.to_string()))?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| Error::ImageDecodingFailed("missing JPEG header".to_string()))?;
+
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        jpeg_decoder::PixelFormat::L8 => buffer.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        // Big endian 16 bit luminance, only the high byte is kept
+        jpeg_decoder::PixelFormat::L16 => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], 255])
+            .collect(),
+        // Adobe JPEGs store inverted CMYK
+        jpeg_decoder::PixelFormat::CMYK32 => buffer
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let k = p[3] as u32;
+                let channel = |c: u8| (c as u32 * k / 255) as u8;
+                [channel(p[0]), channel(p[1]), channel(p[2]), 255]
+            })
+            .collect(),
+    };
+    Ok((info.width as u32, info.height as u32, pixels))
+}
@@ -0,0 +1,404 @@
+//! Multi-line text layout
+
+use crate::{Font, Glyph, GlyphRun, Point, Rect};
+use std::ops::Range;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+/// Horizontal alignment of the lines of a TextLayout
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches the spaces so that every line but the last one of a paragraph fills the width
+    Justify,
+}
+
+/// A line of a TextLayout
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutLine {
+    range: Range<usize>,
+    run: GlyphRun,
+    /// The run before it was justified
+    shaped: GlyphRun,
+    /// Whether the line ends its paragraph (hard line break or end of the text)
+    ends_paragraph: bool,
+    rect: Rect,
+    baseline: f32,
+}
+
+impl LayoutLine {
+    /// Byte range of the line in the text, including the trailing spaces and line break
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Glyphs of the line. Their clusters are byte offsets in the whole text
+    #[inline]
+    pub fn glyph_run(&self) -> &GlyphRun {
+        &self.run
+    }
+
+    /// Box of the line relative to the top left of the layout.
+    /// It is as wide as the glyphs (trailing spaces excluded) and one line height tall
+    #[inline]
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Position of the baseline relative to the top of the layout
+    #[inline]
+    pub fn baseline(&self) -> f32 {
+        self.baseline
+    }
+}
+
+/// Box of a glyph of a TextLayout
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlyphBox {
+    /// Byte offset of the first character of the text that the glyph belongs to
+    pub cluster: usize,
+    /// Index of the line in TextLayout::lines()
+    pub line: usize,
+    /// Advance of the glyph times the height of its line, relative to the top left of the layout
+    pub rect: Rect,
+}
+
+/// # Text layout
+/// Text broken into lines at word boundaries, measured and aligned.
+/// A layout does not need a canvas, it can be measured before anything is drawn.
+/// Hard line breaks (e.g. '\n') always start a new line.
+/// ```
+/// use bly::{Font, TextAlign, TextLayout};
+///
+/// # fn layout(font: &Font) {
+/// let layout = TextLayout::new("The quick brown fox jumps over the lazy dog", font, 16.0)
+///     .with_max_width(120.0)
+///     .with_align(TextAlign::Center);
+/// for line in layout.lines() {
+///     println!("{:?} {:?}", line.range(), line.rect());
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayout {
+    text: String,
+    font: Font,
+    size: f32,
+    max_width: Option<f32>,
+    align: TextAlign,
+    lines: Vec<LayoutLine>,
+}
+
+impl TextLayout {
+    /// Lays out text without a maximum width, so that only hard line breaks start new lines
+    pub fn new(text: &str, font: &Font, size: f32) -> Self {
+        let mut layout = Self {
+            text: text.to_string(),
+            font: font.clone(),
+            size,
+            max_width: None,
+            align: TextAlign::Left,
+            lines: Vec::new(),
+        };
+        layout.break_lines();
+        layout.align_lines();
+        layout
+    }
+
+    /// Wraps the lines that are wider than max_width.
+    /// A word that is wider on its own is broken between two characters
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self.break_lines();
+        self.align_lines();
+        self
+    }
+
+    /// Sets the alignment of the lines.
+    /// The lines are aligned within the maximum width if it is set, otherwise within the widest line
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self.align_lines();
+        self
+    }
+
+    /// The text that was laid out
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Lines from top to bottom. There is at least one line, even for an empty text
+    #[inline]
+    pub fn lines(&self) -> &[LayoutLine] {
+        &self.lines
+    }
+
+    /// Width of the widest line
+    pub fn width(&self) -> f32 {
+        self.lines
+            .iter()
+            .map(|line| line.shaped.advance())
+            .fold(0.0, f32::max)
+    }
+
+    /// Height of all lines
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.line_height()
+    }
+
+    /// Distance from the top of a line to its baseline
+    #[inline]
+    pub fn ascent(&self) -> f32 {
+        self.font.ascent(self.size)
+    }
+
+    /// Distance from the baseline of a line to the bottom of its glyphs
+    #[inline]
+    pub fn descent(&self) -> f32 {
+        self.font.descent(self.size)
+    }
+
+    /// Distance between the baselines of two lines
+    #[inline]
+    pub fn line_height(&self) -> f32 {
+        self.ascent() + self.descent() + self.font.line_gap(self.size)
+    }
+
+    /// Boxes of all glyphs, line by line from left to right
+    pub fn glyph_boxes(&self) -> Vec<GlyphBox> {
+        let mut boxes = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            for glyph in line.run.glyphs() {
+                boxes.push(GlyphBox {
+                    cluster: glyph.cluster,
+                    line: index,
+                    rect: Rect::new(
+                        line.rect.x + glyph.x,
+                        line.rect.y,
+                        glyph.advance,
+                        line.rect.height,
+                    ),
+                });
+            }
+        }
+        boxes
+    }
+
+    /// Byte offset of the character under the point, relative to the top left of the layout.
+    /// Points above or below the text hit the first or last line, and points beside a line
+    /// hit the glyph closest to them. Returns the start of the line if it has no glyphs
+    pub fn hit_test(&self, point: impl Into<Point>) -> usize {
+        let point = point.into();
+        let index =
+            ((point.y / self.line_height()).floor().max(0.0) as usize).min(self.lines.len() - 1);
+        let line = &self.lines[index];
+        let x = point.x - line.rect.x;
+        line.run
+            .glyphs()
+            .iter()
+            .min_by(|a, b| {
+                let distance = |glyph: &Glyph| {
+                    if x < glyph.x {
+                        glyph.x - x
+                    } else {
+                        (x - glyph.x - glyph.advance).max(0.0)
+                    }
+                };
+                distance(a).total_cmp(&distance(b))
+            })
+            .map_or(line.range.start, |glyph| glyph.cluster)
+    }
+
+    /// Box of a text cursor at the left edge of the glyph of the character at the byte offset,
+    /// one line tall and 0 wide. An offset in the trailing spaces of a line (or at the end of the text)
+    /// places the cursor after the last glyph of the line
+    pub fn caret_rect(&self, offset: usize) -> Rect {
+        let index = self
+            .lines
+            .iter()
+            .position(|line| offset < line.range.end)
+            .unwrap_or(self.lines.len() - 1);
+        let line = &self.lines[index];
+        let glyphs = line.run.glyphs();
+        // The glyph of the character, or of the ligature that contains it
+        let x = glyphs
+            .iter()
+            .filter(|glyph| glyph.cluster <= offset)
+            .max_by_key(|glyph| glyph.cluster)
+            .filter(|glyph| glyph.cluster == offset || offset < self.content_end(line))
+            .map_or(line.rect.width, |glyph| glyph.x);
+        Rect::new(line.rect.x + x, line.rect.y, 0.0, line.rect.height)
+    }
+
+    /// End of the part of a line that has glyphs, before its trailing spaces and line break
+    fn content_end(&self, line: &LayoutLine) -> usize {
+        line.range.start + self.text[line.range.clone()].trim_end().len()
+    }
+
+    /// Breaks the text into lines and shapes them
+    fn break_lines(&mut self) {
+        let text = self.text.as_str();
+        let max_width = self.max_width.unwrap_or(f32::INFINITY);
+
+        let mut opportunities: Vec<(usize, BreakOpportunity)> = linebreaks(text).collect();
+        opportunities.dedup_by_key(|(offset, _)| *offset);
+
+        let mut ranges: Vec<(Range<usize>, bool)> = Vec::new();
+        let mut paragraph_start = 0;
+        for paragraph_end in opportunities
+            .iter()
+            .filter(|(_, opportunity)| *opportunity == BreakOpportunity::Mandatory)
+            .map(|(offset, _)| *offset)
+        {
+            // The paragraph is shaped once to measure the candidate lines
+            let paragraph = paragraph_start..paragraph_end;
+            let measured = GlyphRun::new(&text[paragraph.clone()], &self.font, self.size);
+            let width = |range: Range<usize>| {
+                let end = range.start + text[range.clone()].trim_end().len();
+                measured
+                    .glyphs()
+                    .iter()
+                    .map(|glyph| (paragraph.start + glyph.cluster, glyph.advance))
+                    .filter(|(cluster, _)| (range.start..end).contains(cluster))
+                    .map(|(_, advance)| advance)
+                    .sum::<f32>()
+            };
+
+            let mut line_start = paragraph_start;
+            let mut last_fit = None;
+            let breaks = opportunities
+                .iter()
+                .map(|(offset, _)| *offset)
+                .filter(|offset| paragraph_start < *offset && *offset < paragraph_end)
+                .chain(std::iter::once(paragraph_end));
+            for offset in breaks {
+                while width(line_start..offset) > max_width {
+                    let end = match last_fit.take() {
+                        Some(end) => end,
+                        // A word that does not fit on its own is broken after the last character that fits
+                        None => {
+                            let mut ends = text[line_start..offset]
+                                .trim_end()
+                                .char_indices()
+                                .skip(1)
+                                .map(|(i, _)| line_start + i);
+                            match ends.next() {
+                                Some(first) => ends
+                                    .take_while(|end| width(line_start..*end) <= max_width)
+                                    .last()
+                                    .unwrap_or(first),
+                                // A single character that does not fit stays on the line
+                                None => break,
+                            }
+                        }
+                    };
+                    ranges.push((line_start..end, false));
+                    line_start = end;
+                }
+                last_fit = Some(offset);
+            }
+            ranges.push((line_start..paragraph_end, true));
+            paragraph_start = paragraph_end;
+        }
+        // A line break at the end of the text is followed by an empty line
+        let ends_with_break = text.chars().next_back().is_none_or(is_line_break);
+        if ends_with_break {
+            ranges.push((text.len()..text.len(), true));
+        }
+
+        let line_height = self.line_height();
+        let ascent = self.ascent();
+        self.lines = ranges
+            .into_iter()
+            .enumerate()
+            .map(|(index, (range, ends_paragraph))| {
+                let start = range.start;
+                let shaped = GlyphRun::new(text[range.clone()].trim_end(), &self.font, self.size);
+                let glyphs = shaped
+                    .glyphs()
+                    .iter()
+                    .map(|glyph| Glyph {
+                        cluster: start + glyph.cluster,
+                        ..*glyph
+                    })
+                    .collect();
+                let shaped = shaped.with_glyphs(glyphs, shaped.advance());
+                let top = index as f32 * line_height;
+                LayoutLine {
+                    range,
+                    run: shaped.clone(),
+                    rect: Rect::new(0.0, top, shaped.advance(), line_height),
+                    shaped,
+                    ends_paragraph,
+                    baseline: top + ascent,
+                }
+            })
+            .collect();
+    }
+
+    /// Positions the lines horizontally and justifies them
+    fn align_lines(&mut self) {
+        let width = self.max_width.unwrap_or_else(|| self.width());
+        for line in &mut self.lines {
+            let free = (width - line.shaped.advance()).max(0.0);
+            line.run = line.shaped.clone();
+            line.rect.width = line.shaped.advance();
+            line.rect.x = match self.align {
+                TextAlign::Left | TextAlign::Justify => 0.0,
+                TextAlign::Center => free / 2.0,
+                TextAlign::Right => free,
+            };
+
+            if self.align != TextAlign::Justify || line.ends_paragraph || free <= 0.0 {
+                continue;
+            }
+            let is_space = |cluster: usize| {
+                self.text[cluster..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_whitespace)
+            };
+            let spaces = line
+                .shaped
+                .glyphs()
+                .iter()
+                .filter(|glyph| is_space(glyph.cluster))
+                .count();
+            if spaces == 0 {
+                continue;
+            }
+            let extra = free / spaces as f32;
+            let mut shift = 0.0;
+            let glyphs = line
+                .shaped
+                .glyphs()
+                .iter()
+                .map(|glyph| {
+                    let mut glyph = *glyph;
+                    glyph.x += shift;
+                    if is_space(glyph.cluster) {
+                        glyph.advance += extra;
+                        shift += extra;
+                    }
+                    glyph
+                })
+                .collect();
+            line.run = line.shaped.with_glyphs(glyphs, width);
+            line.rect.width = width;
+        }
+    }
+}
+
+/// Characters that force a line break (UAX #14 classes BK, CR, LF and NL)
+#[inline]
+fn is_line_break(c: char) -> bool {
+    matches!(
+        c,
+        '\n' | '\r' | '\u{b}' | '\u{c}' | '\u{85}' | '\u{2028}' | '\u{2029}'
+    )
+}
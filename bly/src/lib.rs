@@ -6,144 +6,253 @@
 extern crate log;
 extern crate env_logger as logger;
 
-use once_cell::sync::OnceCell;
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawWindowHandle};
 
+pub use blend::{BlendMode, BlendSpace};
+pub use color::Color;
+pub use error::Error;
+#[allow(deprecated)]
+pub use geometry::Point2;
+pub use geometry::{Point, Rect, Size, Vector};
+pub use image::{Image, ImageFilter};
+pub use layout::{GlyphBox, LayoutLine, TextAlign, TextLayout};
+pub use paint::{ExtendMode, GradientStop, LinearGradient, Paint, RadialGradient};
+pub use path::{Path, PathElement, Polyline};
+pub use snapshot::Snapshot;
+pub use stroke::{LineCap, LineJoin, StrokeStyle};
+pub use text::{Font, Glyph, GlyphRun};
+pub use transform::Transform2D;
+
+mod blend;
 #[cfg(target_os = "linux")]
+#[cfg(any(feature = "xlib", feature = "xcb"))]
 pub(crate) mod cairo;
+mod color;
 #[cfg(target_os = "windows")]
 mod dx2d;
+mod error;
+mod geometry;
+mod image;
+mod layout;
+mod paint;
+mod path;
+mod snapshot;
+mod soft;
+mod stroke;
+mod text;
+mod transform;
 #[cfg(target_os = "linux")]
+#[cfg(any(feature = "xlib", feature = "xcb", feature = "wayland"))]
 mod unix;
 #[cfg(target_os = "linux")]
+#[cfg(feature = "wayland")]
 pub(crate) mod wayland;
 #[cfg(target_arch = "wasm32")]
 mod web;
 
-/// Trait for common back-end processing
+/// Trait for common back-end processing  
+/// Drawing methods do not report errors themselves.
+/// A backend remembers the first error of a frame and returns it from flush()
 pub trait Backend {
     // Initialize
     /// Processing to start drawing (initialization, etc.)
     /// # Safety
     /// Call the method from Painter
-    unsafe fn begin_draw(&mut self);
+    unsafe fn begin_draw(&mut self) -> Result<(), Error>;
 
     /// Processing to finish drawing
     /// # Safety
     /// Call the method from Painter
-    unsafe fn flush(&mut self);
+    unsafe fn flush(&mut self) -> Result<(), Error>;
     /// Get display size
     /// # Safety
     /// Call the method from Painter
     unsafe fn get_display_size(&mut self) -> (u32, u32);
+    /// Tells the backend that the window is now width x height pixels.
+    /// Backends that query the size of their window themselves ignore it
+    /// # Safety
+    /// Call the method from Canvas, outside of a frame
+    unsafe fn resize(&mut self, width: u32, height: u32);
+    /// Sets the color space in which colors are blended, keeping what is already drawn.
+    /// Backends that cannot blend in the space return Error::Unsupported and keep their space
+    /// # Safety
+    /// Call the method from Canvas, outside of a frame
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error>;
 
-    /// Fills the window background with a specific color
+    /// Fills the window background with a specific color, given as straight sRGB components
     /// # Safety
     /// Call the method from Painter
     unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32);
 
+    /// Sets the transform applied to everything drawn afterwards, except clear()
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn set_transform(&mut self, transform: &Transform2D);
+
+    /// Restricts drawing to a rectangle, inside of the current clip
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn push_clip_rect(&mut self, rect: Rect);
+    /// Restricts drawing to a rounded rectangle, inside of the current clip
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32);
+    /// Restricts drawing to the inside of a path, inside of the current clip
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn push_clip_path(&mut self, path: &Path);
+    /// Removes the last pushed clip
+    /// # Safety
+    /// Call the method from Painter, at most once per pushed clip
+    unsafe fn pop_clip(&mut self);
+
+    /// Sets the blend mode of everything drawn afterwards, except clear()
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode);
+    /// Starts drawing into a transparent layer, composited with the opacity and the blend mode by pop_layer()
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode);
+    /// Composites the last pushed layer into the layer below it (or the canvas)
+    /// # Safety
+    /// Call the method from Painter, at most once per pushed layer and only after popping the clips pushed into it
+    unsafe fn pop_layer(&mut self);
+
     // Primitives
     /// Draws a ellipse
     /// # Safety
     /// Call the method from Painter
-    unsafe fn ellipse(&mut self, point: Point2<f32>, radius: f32, r: f32, g: f32, b: f32, a: f32);
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint);
 
     /// Draws a rectangle
     /// # Safety
     /// Call the method from Painter
-    unsafe fn rectangle(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    );
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint);
 
     /// Draws a rounded rectangle
     /// # Safety
     /// Call the method from Painter
-    unsafe fn rounded_rectangle(
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint);
+
+    /// Draws a line
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint);
+
+    /// Draws the outline of an ellipse
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn stroke_ellipse(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
+        point: Point,
         radius: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     );
 
-    /// Draws a line
+    /// Draws the outline of a rectangle
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint);
+
+    /// Draws the outline of a rounded rectangle
     /// # Safety
     /// Call the method from Painter
-    unsafe fn line(
+    unsafe fn stroke_rounded_rectangle(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        stroke: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        rect: Rect,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     );
-}
 
-/// Represents two points in two dimensions
-pub struct Point2<T>(pub T, pub T);
-impl<T> Point2<T> {
-    pub fn new(a: T, b: T) -> Self {
-        Self { 0: a, 1: b }
-    }
-}
+    /// Fills the inside of a path (non-zero fill rule)
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint);
 
-/// Represents three points in two dimensions
-pub struct Point3<T>(pub T, pub T, pub T);
-impl<T> Point3<T> {
-    pub fn new(a: T, b: T, c: T) -> Self {
-        Self { 0: a, 1: b, 2: c }
-    }
-}
+    /// Draws the outline of a path
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint);
 
-/// Represents four points in two dimensions
-pub struct Point4<T>(pub T, pub T, pub T, pub T);
-impl<T> Point4<T> {
-    pub fn new(a: T, b: T, c: T, d: T) -> Self {
-        Self {
-            0: a,
-            1: b,
-            2: c,
-            3: d,
-        }
-    }
+    /// Draws the src part of an image into the dest rectangle
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32);
+
+    /// Fills the glyphs of a run, with the baseline starting at origin
+    /// # Safety
+    /// Call the method from Painter
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint);
+
+    /// Reads back the pixels of the last drawn frame as straight sRGB RGBA8,
+    /// with the width and height of that frame, which a resize may have changed since
+    /// Returns None if the backend cannot read back its pixels
+    /// # Safety
+    /// Call the method from Canvas
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)>;
 }
 
 /// # Bly Drawing Context - Wrapper for Backend
 /// Used for actual drawing  
 pub struct Painter {
     pub(crate) backend: Box<dyn Backend>,
+    /// Transform applied to all coordinates
+    transform: Transform2D,
+    blend_mode: BlendMode,
+    /// Transforms and blend modes stored by save()
+    saved: Vec<(Transform2D, BlendMode)>,
+    /// Clips and layers pushed and not popped yet, the last one on top
+    pushed: Vec<Pushed>,
+}
+
+/// An entry of the stack of clips and layers of Painter
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pushed {
+    Clip,
+    Layer,
 }
 
 impl Painter {
+    pub(crate) fn new(backend: Box<dyn Backend>) -> Self {
+        Self {
+            backend,
+            transform: Transform2D::identity(),
+            blend_mode: BlendMode::Normal,
+            saved: Vec::new(),
+            pushed: Vec::new(),
+        }
+    }
+
     /// Requests Backend to process the start of drawing
     /// This method is called internally in Bly::draw(). Therefore,  
     /// it is not possible for the library user to call this method.
+    /// Every frame starts without a transform and with BlendMode::Normal.
     #[inline]
-    pub(crate) fn begin_draw(&mut self) {
-        unsafe {
-            self.backend.begin_draw();
-        }
+    pub(crate) fn begin_draw(&mut self) -> Result<(), Error> {
+        unsafe { self.backend.begin_draw()? };
+        self.saved.clear();
+        self.pushed.clear();
+        self.set_transform(Transform2D::identity());
+        self.set_blend_mode(BlendMode::Normal);
+        Ok(())
     }
 
     /// Requests the backend to process the end of drawing
     /// This method is called internally in Bly::draw(). Therefore,   
     /// it is not possible for the library user to call this method.
+    /// Clips and layers that are still pushed are popped first.
     #[inline]
-    pub(crate) fn flush(&mut self) {
-        unsafe {
-            self.backend.flush();
+    pub(crate) fn flush(&mut self) -> Result<(), Error> {
+        while let Some(pushed) = self.pushed.last() {
+            match pushed {
+                Pushed::Clip => self.pop_clip(),
+                Pushed::Layer => self.pop_layer(),
+            }
         }
+        unsafe { self.backend.flush() }
     }
 
     /// Get display size
@@ -152,86 +261,351 @@ impl Painter {
         unsafe { self.backend.get_display_size() }
     }
 
-    /// Fills the window background with the specified color
+    /// Stores the current transform and blend mode, so that they can be brought back with restore()
+    #[inline]
+    pub fn save(&mut self) {
+        self.saved.push((self.transform, self.blend_mode));
+    }
+
+    /// Brings back the transform and the blend mode stored by the last call to save().
+    /// Does nothing if there is nothing saved
+    #[inline]
+    pub fn restore(&mut self) {
+        if let Some((transform, blend_mode)) = self.saved.pop() {
+            self.set_transform(transform);
+            self.set_blend_mode(blend_mode);
+        }
+    }
+
+    /// Moves the origin of the coordinates by (x, y)
+    #[inline]
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.set_transform(Transform2D::translation(x, y).then(&self.transform));
+    }
+
+    /// Rotates the coordinates around the origin by an angle in radians (clockwise on the screen)
+    #[inline]
+    pub fn rotate(&mut self, angle: f32) {
+        self.set_transform(Transform2D::rotation(angle).then(&self.transform));
+    }
+
+    /// Scales the coordinates, including the width of strokes, around the origin
+    #[inline]
+    pub fn scale(&mut self, sx: f32, sy: f32) {
+        self.set_transform(Transform2D::scale(sx, sy).then(&self.transform));
+    }
+
+    /// Skews the coordinates by the angles in radians (see Transform2D::skew())
+    #[inline]
+    pub fn skew(&mut self, x_angle: f32, y_angle: f32) {
+        self.set_transform(Transform2D::skew(x_angle, y_angle).then(&self.transform));
+    }
+
+    /// Replaces the current transform.
+    /// The transform maps the coordinates passed to the drawing methods to pixels of the canvas
+    #[inline]
+    pub fn set_transform(&mut self, transform: Transform2D) {
+        self.transform = transform;
+        unsafe {
+            self.backend.set_transform(&transform);
+        }
+    }
+
+    /// The current transform, e.g. to map a pointer position back with Transform2D::inverse()
+    #[inline]
+    pub fn transform(&self) -> Transform2D {
+        self.transform
+    }
+
+    /// Restricts drawing to a rectangle until pop_clip() is called.
+    /// The rectangle is mapped by the current transform, later changes of the transform do not move it.
+    /// Clips can be nested, drawing is then restricted to the intersection of all pushed clips
+    #[inline]
+    pub fn push_clip_rect(&mut self, rect: Rect) {
+        self.pushed.push(Pushed::Clip);
+        unsafe {
+            self.backend.push_clip_rect(rect);
+        }
+    }
+
+    /// Restricts drawing to a rounded rectangle until pop_clip() is called (see push_clip_rect())
+    #[inline]
+    pub fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        self.pushed.push(Pushed::Clip);
+        unsafe {
+            self.backend.push_clip_rounded_rect(rect, radius);
+        }
+    }
+
+    /// Restricts drawing to the inside of a path until pop_clip() is called (see push_clip_rect()).
+    /// Open subpaths are closed
+    #[inline]
+    pub fn push_clip_path(&mut self, path: &Path) {
+        self.pushed.push(Pushed::Clip);
+        unsafe {
+            self.backend.push_clip_path(path);
+        }
+    }
+
+    /// Removes the clip pushed last.
+    /// Does nothing if there is no clip, or if a layer was pushed after the last clip
+    #[inline]
+    pub fn pop_clip(&mut self) {
+        if self.pushed.last() == Some(&Pushed::Clip) {
+            self.pushed.pop();
+            unsafe {
+                self.backend.pop_clip();
+            }
+        }
+    }
+
+    /// Sets how everything drawn afterwards is combined with the canvas
+    #[inline]
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+        unsafe {
+            self.backend.set_blend_mode(mode);
+        }
+    }
+
+    /// The current blend mode
+    #[inline]
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Draws everything into a transparent layer until pop_layer() is called.
+    /// The layer is then composited as a whole with the opacity and the blend mode,
+    /// so overlapping shapes inside of it do not show through each other
+    #[inline]
+    pub fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        self.pushed.push(Pushed::Layer);
+        unsafe {
+            self.backend.push_layer(opacity.clamp(0.0, 1.0), mode);
+        }
+    }
+
+    /// Composites the layer pushed last. Clips pushed after the layer are popped with it.
+    /// Does nothing if there is no layer
+    #[inline]
+    pub fn pop_layer(&mut self) {
+        if !self.pushed.contains(&Pushed::Layer) {
+            return;
+        }
+        while let Some(pushed) = self.pushed.pop() {
+            unsafe {
+                match pushed {
+                    Pushed::Clip => self.backend.pop_clip(),
+                    Pushed::Layer => {
+                        self.backend.pop_layer();
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fills the window background with the specified color.
+    /// The transform does not apply, the whole canvas (inside of the clip) is filled
     #[inline]
     pub fn clear(&mut self, color: Color) {
         unsafe {
-            let vec: Vec4 = color.into();
-            self.backend
-                .clear(vec.0 as f32, vec.1 as f32, vec.2 as f32, vec.3 as f32);
+            self.backend.clear(color.r, color.g, color.b, color.a);
         }
     }
 
     /// Draws an ellipse
     #[inline]
-    pub fn ellipse(&mut self, pos: Point2<f32>, radius: f32, color: Color) {
+    pub fn ellipse(&mut self, pos: impl Into<Point>, radius: f32, paint: impl Into<Paint>) {
         unsafe {
-            let vec: Vec4 = color.into();
-            self.backend.ellipse(
-                pos,
-                radius,
-                vec.0 as f32,
-                vec.1 as f32,
-                vec.2 as f32,
-                vec.3 as f32,
-            );
+            self.backend.ellipse(pos.into(), radius, &paint.into());
         }
     }
 
-    /// Draws a rectangle
+    /// Draws a rectangle with its top left corner at pos
     #[inline]
-    pub fn rectangle(&mut self, pos: Point2<f32>, size: Point2<f32>, color: Color) {
+    pub fn rectangle(
+        &mut self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+        paint: impl Into<Paint>,
+    ) {
         unsafe {
-            let vec: Vec4 = color.into();
-            self.backend.rectangle(
-                pos,
-                size,
-                vec.0 as f32,
-                vec.1 as f32,
-                vec.2 as f32,
-                vec.3 as f32,
-            );
+            self.backend
+                .rectangle(Rect::from_origin_size(pos, size), &paint.into());
         }
     }
 
     #[inline]
     pub fn rounded_rectangle(
         &mut self,
-        pos: Point2<f32>,
-        size: Point2<f32>,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
         radius: f32,
-        color: Color,
+        paint: impl Into<Paint>,
     ) {
-        let vec: Vec4 = color.into();
         unsafe {
             self.backend.rounded_rectangle(
-                pos,
-                size,
+                Rect::from_origin_size(pos, size),
                 radius,
-                vec.0 as f32,
-                vec.1 as f32,
-                vec.2 as f32,
-                vec.3 as f32,
+                &paint.into(),
             );
         }
     }
 
-    /// Draws a line
+    /// Draws a line.
+    /// The stroke is either a width in pixels or a StrokeStyle
     #[inline]
-    pub fn line(&mut self, point1: Point2<f32>, point2: Point2<f32>, stroke: f32, color: Color) {
+    pub fn line(
+        &mut self,
+        point1: impl Into<Point>,
+        point2: impl Into<Point>,
+        stroke: impl Into<StrokeStyle>,
+        paint: impl Into<Paint>,
+    ) {
         unsafe {
-            let vec: Vec4 = color.into();
-            self.backend.line(
-                point1,
-                point2,
-                stroke,
-                vec.0 as f32,
-                vec.1 as f32,
-                vec.2 as f32,
-                vec.3 as f32,
+            self.backend
+                .line(point1.into(), point2.into(), &stroke.into(), &paint.into());
+        }
+    }
+
+    /// Draws the outline of an ellipse
+    #[inline]
+    pub fn stroke_ellipse(
+        &mut self,
+        pos: impl Into<Point>,
+        radius: f32,
+        stroke: impl Into<StrokeStyle>,
+        paint: impl Into<Paint>,
+    ) {
+        unsafe {
+            self.backend
+                .stroke_ellipse(pos.into(), radius, &stroke.into(), &paint.into());
+        }
+    }
+
+    /// Draws the outline of a rectangle
+    #[inline]
+    pub fn stroke_rectangle(
+        &mut self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+        stroke: impl Into<StrokeStyle>,
+        paint: impl Into<Paint>,
+    ) {
+        unsafe {
+            self.backend.stroke_rectangle(
+                Rect::from_origin_size(pos, size),
+                &stroke.into(),
+                &paint.into(),
+            );
+        }
+    }
+
+    /// Draws the outline of a rounded rectangle
+    #[inline]
+    pub fn stroke_rounded_rectangle(
+        &mut self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+        radius: f32,
+        stroke: impl Into<StrokeStyle>,
+        paint: impl Into<Paint>,
+    ) {
+        unsafe {
+            self.backend.stroke_rounded_rectangle(
+                Rect::from_origin_size(pos, size),
+                radius,
+                &stroke.into(),
+                &paint.into(),
             );
         }
     }
+
+    /// Fills the inside of a path
+    #[inline]
+    pub fn fill_path(&mut self, path: &Path, paint: impl Into<Paint>) {
+        unsafe {
+            self.backend.fill_path(path, &paint.into());
+        }
+    }
+
+    /// Draws the outline of a path.
+    /// The stroke is either a width in pixels or a StrokeStyle
+    #[inline]
+    pub fn stroke_path(
+        &mut self,
+        path: &Path,
+        stroke: impl Into<StrokeStyle>,
+        paint: impl Into<Paint>,
+    ) {
+        unsafe {
+            self.backend
+                .stroke_path(path, &stroke.into(), &paint.into());
+        }
+    }
+
+    /// Draws the src part of an image (the whole image if src is None) scaled into dest.  
+    /// The opacity between 0.0 and 1.0 is applied to the whole image.
+    /// The image is sampled with its filter (see Image::with_filter())
+    #[inline]
+    pub fn draw_image(&mut self, image: &Image, dest: Rect, src: Option<Rect>, opacity: f32) {
+        let src =
+            src.unwrap_or_else(|| Rect::new(0.0, 0.0, image.width() as f32, image.height() as f32));
+        if src.width <= 0.0 || src.height <= 0.0 || image.width() == 0 || image.height() == 0 {
+            return;
+        }
+        unsafe {
+            self.backend
+                .draw_image(image, dest, src, opacity.clamp(0.0, 1.0));
+        }
+    }
+
+    /// Draws a single line of text with its baseline starting at position.
+    /// The size is the size of the font in pixels (the height of an em)
+    #[inline]
+    pub fn draw_text(
+        &mut self,
+        text: &str,
+        position: impl Into<Point>,
+        font: &Font,
+        size: f32,
+        paint: impl Into<Paint>,
+    ) {
+        let run = GlyphRun::new(text, font, size);
+        self.draw_glyph_run(&run, position, paint);
+    }
+
+    /// Draws the lines of a layout, with the top left of the layout at position
+    pub fn draw_text_layout(
+        &mut self,
+        layout: &TextLayout,
+        position: impl Into<Point>,
+        paint: impl Into<Paint>,
+    ) {
+        let (position, paint) = (position.into(), paint.into());
+        for line in layout.lines() {
+            let origin = position + Vector::new(line.rect().x, line.baseline());
+            self.draw_glyph_run(line.glyph_run(), origin, paint.clone());
+        }
+    }
+
+    /// Draws text shaped with GlyphRun::new(), with its baseline starting at position
+    #[inline]
+    pub fn draw_glyph_run(
+        &mut self,
+        run: &GlyphRun,
+        position: impl Into<Point>,
+        paint: impl Into<Paint>,
+    ) {
+        if run.glyphs().is_empty() || run.size() <= 0.0 {
+            return;
+        }
+        unsafe {
+            self.backend
+                .draw_glyphs(run, position.into(), &paint.into());
+        }
+    }
 }
 
 /// An interface to make Painter easier to use
@@ -241,144 +615,199 @@ pub struct Canvas {
 }
 
 impl Canvas {
-    /// drawing via painter.
-    pub fn draw<F>(&mut self, mut f: F)
+    /// drawing via painter.  
+    /// Returns an error if the backend fails to prepare or to present the frame
+    pub fn draw<F>(&mut self, mut f: F) -> Result<(), Error>
     where
         F: FnMut(&mut Painter),
     {
-        self.painter.begin_draw();
+        self.painter.begin_draw()?;
         f(&mut self.painter);
-        self.painter.flush();
+        self.painter.flush()
+    }
+
+    /// Tells the canvas that its window is now width x height pixels.  
+    /// Wayland surfaces have no size of their own, so call it on WindowEvent::Resized
+    /// (and once after create_canvas()). Other windows are followed automatically.
+    /// An offscreen canvas is resized to the new size and its pixels are cleared
+    pub fn resize(&mut self, width: u32, height: u32) {
+        unsafe { self.painter.backend.resize(width, height) }
+    }
+
+    /// Sets the color space in which colors are blended and interpolated, BlendSpace::Srgb by default.
+    /// What is already drawn is kept.
+    /// Only canvases drawn on the CPU (offscreen canvases and Wayland windows without EGL)
+    /// can blend in linear light, the other ones return Error::Unsupported
+    pub fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error> {
+        unsafe { self.painter.backend.set_blend_space(space) }
+    }
+
+    /// Reads back the pixels of the last drawn frame.  
+    /// The pixels are straight (non-premultiplied) sRGB RGBA8, row by row from the top left.
+    /// If the backend cannot read back its pixels, None is returned.
+    /// The canvas may have been resized since that frame, see snapshot() for its size.
+    pub fn read_pixels(&mut self) -> Option<Vec<u8>> {
+        unsafe { self.painter.backend.read_pixels() }.map(|(_, _, pixels)| pixels)
+    }
+
+    /// Captures the last drawn frame, at the size it was drawn at.  
+    /// If the backend cannot read back its pixels, None is returned.
+    pub fn snapshot(&mut self) -> Option<Snapshot> {
+        let (width, height, pixels) = unsafe { self.painter.backend.read_pixels() }?;
+        Snapshot::new(width, height, pixels)
     }
 }
 /// Mainly used to store vertex information
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Vec4(pub f64, pub f64, pub f64, pub f64);
 
-/// Enumeration of colors defined by default.
-/// Used to specify fill color, etc.
-#[derive(Debug, Copy, Clone, PartialEq)]
-pub enum Color {
-    White,
-    WhiteGray,
-    Gray,
-    Black,
-    Red,
-    Green,
-    Blue,
-    Rgba(f32, f32, f32, f32),
-}
-
-// Converts a Color enumerator to a vector.
-impl Into<Vec4> for Color {
-    fn into(self) -> Vec4 {
-        match self {
-            Color::White => Vec4(255.0, 255.0, 255.0, 1.0),
-            Color::WhiteGray => Vec4(0.9, 0.9, 0.9, 1.0),
-            Color::Gray => Vec4(0.9, 0.9, 0.9, 1.0),
-            Color::Black => Vec4(0.0, 0.0, 0.0, 255.0),
-            Color::Red => Vec4(1.0, 0.0, 0.0, 1.0),
-            Color::Green => Vec4(0.0, 1.0, 0.0, 1.0),
-            Color::Blue => Vec4(0.0, 0.0, 1.0, 1.0),
-            Color::Rgba(r, g, b, a) => Vec4(r as f64, g as f64, b as f64, a as f64),
-        }
+impl From<Color> for Vec4 {
+    fn from(color: Color) -> Self {
+        Vec4(
+            color.r as f64,
+            color.g as f64,
+            color.b as f64,
+            color.a as f64,
+        )
     }
 }
 
 /// Initialize bly  
-/// If Backend is not supported or some error occurs during initialization, Err is returned.
-pub fn create_canvas(handle: &impl HasRawWindowHandle) -> Result<Canvas, ()> {
-    //panic if the canvas has been created once
-    static CANVAS_CREATED: OnceCell<()> = OnceCell::new();
-    if CANVAS_CREATED.set(()).is_err() {
-        error!("Creating EventLoop multiple times is not supported.");
-        panic!("Creating EventLoop multiple times is not supported.");
-    }
-
+/// If Backend is not supported or some error occurs during initialization, Err is returned.  
+/// Any number of canvases can be created, e.g. one for each window.  
+/// The display handle must be the one of the connection that owns the window.  
+/// Xlib connections are not locked by Bly: an application using Xlib from several threads
+/// must call XInitThreads() before any other Xlib call (libX11 1.8 and later does it by itself).
+// Without any window backend every arm of the match returns an error
+#[cfg_attr(
+    not(any(
+        all(
+            target_os = "linux",
+            any(feature = "xlib", feature = "xcb", feature = "wayland")
+        ),
+        target_os = "windows",
+        target_arch = "wasm32"
+    )),
+    allow(unreachable_code, unused_variables)
+)]
+pub fn create_canvas(
+    handle: &(impl HasRawWindowHandle + HasRawDisplayHandle),
+) -> Result<Canvas, Error> {
     #[cfg(feature = "experimental")]
     info!("You are using the experimental version of Bly");
 
-    let backend = match handle.raw_window_handle() {
+    let backend: Box<dyn Backend> = match handle.raw_window_handle() {
         RawWindowHandle::UiKit(_) => {
             info!("Platform: UiKit");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("UiKit"));
         }
         #[cfg(target_os = "macos")]
         RawWindowHandle::AppKit(handle) => {
             info!("Platform: AppKit");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("AppKit"));
         }
         RawWindowHandle::Orbital(_) => {
             info!("Platform: Orbital");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("Orbital"));
         }
         #[cfg(target_os = "linux")]
         #[cfg(feature = "xlib")]
-        RawWindowHandle::Xlib(handle) => {
+        RawWindowHandle::Xlib(window) => {
             info!("Platform: Xlib Drawing backend is Cairo");
-            {
-                unix::create_xlib_backend(handle.window)
-            }
+            let display = match handle.raw_display_handle() {
+                raw_window_handle::RawDisplayHandle::Xlib(display) => display.display,
+                _ => {
+                    error!("The display of an Xlib window must be an Xlib display");
+                    return Err(Error::DisplayConnectionFailed);
+                }
+            };
+            Box::new(unix::create_xlib_backend(display, window.window)?)
         }
-        RawWindowHandle::Xcb(_) => {
-            info!("Platform: Xcb");
-            error!("This platform is unsupported");
-            return Err(());
+        #[cfg(target_os = "linux")]
+        #[cfg(feature = "xcb")]
+        RawWindowHandle::Xcb(window) => {
+            info!("Platform: Xcb Drawing backend is Cairo");
+            let connection = match handle.raw_display_handle() {
+                raw_window_handle::RawDisplayHandle::Xcb(display) => display.connection,
+                _ => {
+                    error!("The display of an XCB window must be an XCB connection");
+                    return Err(Error::DisplayConnectionFailed);
+                }
+            };
+            Box::new(unix::create_xcb_backend(
+                connection,
+                window.window,
+                window.visual_id,
+            )?)
         }
         #[cfg(target_os = "linux")]
         #[cfg(feature = "wayland")]
-        RawWindowHandle::Wayland(handle) => {
-            info!("Platform: Wayland Drawing backend is EGL");
-            unix::create_wayland_backend(handle.surface)
+        RawWindowHandle::Wayland(window) => {
+            info!("Platform: Wayland");
+            let display = match handle.raw_display_handle() {
+                raw_window_handle::RawDisplayHandle::Wayland(display) => display.display,
+                _ => {
+                    error!("The display of a Wayland window must be a Wayland display");
+                    return Err(Error::DisplayConnectionFailed);
+                }
+            };
+            Box::new(unix::create_wayland_backend(display, window.surface)?)
         }
         RawWindowHandle::Drm(_) => {
             info!("Platform: Drm");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("Drm"));
         }
         RawWindowHandle::Gbm(_) => {
             info!("Platform: Gbm");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("Gbm"));
         }
         #[cfg(target_os = "windows")]
         RawWindowHandle::Win32(handle) => {
             info!("Platform: Win32 Drawing backend is Dx2D");
-            dx2d::create_backend(handle.hwnd as isize).unwrap()
+            Box::new(dx2d::create_backend(handle.hwnd as isize)?)
         }
         RawWindowHandle::WinRt(_) => {
             info!("Platform: WinRt");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("WinRt"));
         }
         #[cfg(target_arch = "wasm32")]
         RawWindowHandle::Web(handle) => {
             info!("Platform: Web Drawing backend is web-sys");
-            web::create_backend(handle.id)
+            Box::new(web::create_backend(handle.id)?)
         }
         RawWindowHandle::AndroidNdk(_) => {
             info!("Platform: AndroidNDK");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("AndroidNdk"));
         }
         RawWindowHandle::Haiku(_) => {
             info!("Platform: Haiku");
             error!("This platform is unsupported");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("Haiku"));
         }
         _ => {
             error!("Unknown platform");
-            return Err(());
+            return Err(Error::UnsupportedPlatform("Unknown"));
         }
     };
     info!("Successfully acquired backend");
 
     Ok(Canvas {
-        painter: Painter {
-            backend: Box::new(backend),
-        },
+        painter: Painter::new(backend),
     })
 }
+
+/// Initialize bly without a window  
+/// Drawing is done on the CPU into an in-memory buffer of the given size,
+/// which can be read back with Canvas::read_pixels()
+pub fn create_offscreen_canvas(width: u32, height: u32) -> Canvas {
+    Canvas {
+        painter: Painter::new(Box::new(soft::create_backend(width, height))),
+    }
+}
@@ -0,0 +1,232 @@
+//! Describes how shapes are filled and outlined
+
+use crate::{Color, Point};
+
+/// What a gradient shows outside of the range between its first and last stop
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ExtendMode {
+    /// The colors of the first and last stop continue forever
+    #[default]
+    Pad,
+    /// The gradient starts over
+    Repeat,
+    /// The gradient is repeated, every other time in reverse
+    Reflect,
+}
+
+/// A color at a position of a gradient.
+/// Between two stops, colors are interpolated with premultiplied alpha like in CSS,
+/// so that a transparent stop fades its neighbour out instead of tinting it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GradientStop {
+    /// Position of the stop between 0.0 (start of the gradient) and 1.0 (end of the gradient)
+    pub offset: f32,
+    /// Color at the position
+    pub color: Color,
+}
+
+/// # Linear gradient
+/// Colors change along the line from start to end and stay constant perpendicular to it
+/// ```
+/// use bly::{Color, ExtendMode, LinearGradient};
+///
+/// let gradient = LinearGradient::new((0.0, 0.0), (100.0, 0.0))
+///     .with_stop(0.0, Color::RED)
+///     .with_stop(1.0, Color::BLUE)
+///     .with_extend(ExtendMode::Reflect);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearGradient {
+    /// Point where the offset of the gradient is 0.0
+    pub start: Point,
+    /// Point where the offset of the gradient is 1.0
+    pub end: Point,
+    /// Stops of the gradient
+    pub stops: Vec<GradientStop>,
+    /// What is drawn before start and after end
+    pub extend: ExtendMode,
+}
+
+impl LinearGradient {
+    /// Creates a gradient from start to end without stops
+    pub fn new(start: impl Into<Point>, end: impl Into<Point>) -> Self {
+        Self {
+            start: start.into(),
+            end: end.into(),
+            stops: Vec::new(),
+            extend: ExtendMode::Pad,
+        }
+    }
+
+    /// Adds a color stop
+    pub fn with_stop(mut self, offset: f32, color: Color) -> Self {
+        self.stops.push(GradientStop { offset, color });
+        self
+    }
+
+    /// Sets what is drawn before start and after end
+    pub fn with_extend(mut self, extend: ExtendMode) -> Self {
+        self.extend = extend;
+        self
+    }
+}
+
+/// # Radial gradient
+/// Colors change from the center (offset 0.0) to the circle of the given radius (offset 1.0)
+/// ```
+/// use bly::{Color, Point, RadialGradient};
+///
+/// let gradient = RadialGradient::new(Point::new(50.0, 50.0), 50.0)
+///     .with_stop(0.0, Color::WHITE)
+///     .with_stop(1.0, Color::BLACK);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadialGradient {
+    /// Center of the gradient
+    pub center: Point,
+    /// Distance from the center where the offset of the gradient is 1.0
+    pub radius: f32,
+    /// Stops of the gradient
+    pub stops: Vec<GradientStop>,
+    /// What is drawn outside of the circle
+    pub extend: ExtendMode,
+}
+
+impl RadialGradient {
+    /// Creates a gradient around center without stops
+    pub fn new(center: impl Into<Point>, radius: f32) -> Self {
+        Self {
+            center: center.into(),
+            radius,
+            stops: Vec::new(),
+            extend: ExtendMode::Pad,
+        }
+    }
+
+    /// Adds a color stop
+    pub fn with_stop(mut self, offset: f32, color: Color) -> Self {
+        self.stops.push(GradientStop { offset, color });
+        self
+    }
+
+    /// Sets what is drawn outside of the circle
+    pub fn with_extend(mut self, extend: ExtendMode) -> Self {
+        self.extend = extend;
+        self
+    }
+}
+
+/// # Paint
+/// What the drawing methods of Painter fill shapes and outlines with.
+/// A Color, LinearGradient or RadialGradient can be passed wherever a Paint is expected.
+/// Gradients without stops are transparent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    /// A single color
+    Solid(Color),
+    /// A linear gradient
+    LinearGradient(LinearGradient),
+    /// A radial gradient
+    RadialGradient(RadialGradient),
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Paint::Solid(color)
+    }
+}
+
+impl From<LinearGradient> for Paint {
+    fn from(gradient: LinearGradient) -> Self {
+        Paint::LinearGradient(gradient)
+    }
+}
+
+impl From<RadialGradient> for Paint {
+    fn from(gradient: RadialGradient) -> Self {
+        Paint::RadialGradient(gradient)
+    }
+}
+
+/// Components of a color as f32, the way backends take them
+#[inline]
+pub(crate) fn color_rgba(color: Color) -> (f32, f32, f32, f32) {
+    (color.r, color.g, color.b, color.a)
+}
+
+/// Maps an offset outside of 0.0..=1.0 back into it according to the extend mode
+#[inline]
+pub(crate) fn extend_offset(offset: f32, extend: ExtendMode) -> f32 {
+    match extend {
+        ExtendMode::Pad => offset.clamp(0.0, 1.0),
+        ExtendMode::Repeat => offset.rem_euclid(1.0),
+        ExtendMode::Reflect => {
+            let t = offset.rem_euclid(2.0);
+            if t > 1.0 {
+                2.0 - t
+            } else {
+                t
+            }
+        }
+    }
+}
+
+/// Number of intervals that premultiplied_stops() cuts the space between two stops into
+#[cfg_attr(
+    not(any(
+        feature = "xlib",
+        feature = "xcb",
+        target_os = "windows",
+        target_arch = "wasm32"
+    )),
+    allow(dead_code)
+)]
+const PREMULTIPLIED_STEPS: usize = 8;
+
+/// Sorted stops (see sorted_stops()) for the backends that interpolate straight colors.
+/// Straight and premultiplied interpolation only differ between stops of different opacity,
+/// so intermediate stops mixed with premultiplied alpha are added there
+#[cfg_attr(
+    not(any(
+        feature = "xlib",
+        feature = "xcb",
+        target_os = "windows",
+        target_arch = "wasm32"
+    )),
+    allow(dead_code)
+)]
+pub(crate) fn premultiplied_stops(stops: &[GradientStop]) -> Vec<GradientStop> {
+    let sorted = sorted_stops(stops);
+    let mut result = Vec::with_capacity(sorted.len());
+    let mut previous: Option<GradientStop> = None;
+    for stop in sorted {
+        if let Some(previous) = previous {
+            if previous.color.a != stop.color.a && previous.offset < stop.offset {
+                for step in 1..PREMULTIPLIED_STEPS {
+                    let t = step as f32 / PREMULTIPLIED_STEPS as f32;
+                    result.push(GradientStop {
+                        offset: previous.offset + (stop.offset - previous.offset) * t,
+                        color: previous.color.mix(stop.color, t),
+                    });
+                }
+            }
+        }
+        result.push(stop);
+        previous = Some(stop);
+    }
+    result
+}
+
+/// Stops sorted by offset, with the offsets clamped to 0.0..=1.0
+pub(crate) fn sorted_stops(stops: &[GradientStop]) -> Vec<GradientStop> {
+    let mut sorted: Vec<GradientStop> = stops
+        .iter()
+        .map(|stop| GradientStop {
+            offset: stop.offset.clamp(0.0, 1.0),
+            color: stop.color,
+        })
+        .collect();
+    // A stable sort keeps stops at the same offset in order, so they form a hard edge
+    sorted.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    sorted
+}
@@ -0,0 +1,273 @@
+//! Vector paths made of lines and Bézier curves
+
+use std::f32::consts::PI;
+
+/// A single command of a Path
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathElement {
+    /// Starts a new sub-path at (x, y)
+    MoveTo(f32, f32),
+    /// Straight line to (x, y)
+    LineTo(f32, f32),
+    /// Quadratic Bézier curve with the control point (x1, y1), ending at (x, y)
+    QuadTo(f32, f32, f32, f32),
+    /// Cubic Bézier curve with the control points (x1, y1) and (x2, y2), ending at (x, y)
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    /// Closes the current sub-path with a straight line to its start
+    Close,
+}
+
+/// A polyline produced by Path::flatten()
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    /// Points of the polyline
+    pub points: Vec<(f32, f32)>,
+    /// Whether the last point is connected back to the first one
+    pub closed: bool,
+}
+
+/// # Vector path
+/// Describes an arbitrary shape that can be drawn with Painter::fill_path() or Painter::stroke_path()
+/// ```
+/// let mut path = bly::Path::new();
+/// path.move_to(10.0, 10.0)
+///     .line_to(100.0, 10.0)
+///     .quad_to(100.0, 100.0, 10.0, 100.0)
+///     .close();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    elements: Vec<PathElement>,
+    start: (f32, f32),
+    current: Option<(f32, f32)>,
+}
+
+impl Path {
+    /// Creates an empty path
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Elements of the path in the order they were added
+    #[inline]
+    pub fn elements(&self) -> &[PathElement] {
+        &self.elements
+    }
+
+    /// Returns true if the path has no elements
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Starts a new sub-path at (x, y)
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        self.elements.push(PathElement::MoveTo(x, y));
+        self.start = (x, y);
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a straight line from the current point to (x, y).
+    /// If there is no current point, this behaves like move_to()
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        if self.current.is_none() {
+            return self.move_to(x, y);
+        }
+        self.elements.push(PathElement::LineTo(x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a quadratic Bézier curve with the control point (x1, y1), ending at (x, y)
+    pub fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) -> &mut Self {
+        if self.current.is_none() {
+            self.move_to(x1, y1);
+        }
+        self.elements.push(PathElement::QuadTo(x1, y1, x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a cubic Bézier curve with the control points (x1, y1) and (x2, y2), ending at (x, y)
+    pub fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) -> &mut Self {
+        if self.current.is_none() {
+            self.move_to(x1, y1);
+        }
+        self.elements
+            .push(PathElement::CubicTo(x1, y1, x2, y2, x, y));
+        self.current = Some((x, y));
+        self
+    }
+
+    /// Adds a circular arc of the given radius that is tangent to the line from the current point
+    /// to (x1, y1) and to the line from (x1, y1) to (x2, y2), like arcTo() of the HTML canvas.
+    /// A straight line connects the current point to the start of the arc.
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) -> &mut Self {
+        let p0 = match self.current {
+            Some(p) => p,
+            None => return self.move_to(x1, y1),
+        };
+
+        let v1 = (p0.0 - x1, p0.1 - y1);
+        let v2 = (x2 - x1, y2 - y1);
+        let len1 = (v1.0 * v1.0 + v1.1 * v1.1).sqrt();
+        let len2 = (v2.0 * v2.0 + v2.1 * v2.1).sqrt();
+        let cross = v1.0 * v2.1 - v1.1 * v2.0;
+        if radius <= 0.0 || len1 == 0.0 || len2 == 0.0 || cross.abs() < f32::EPSILON {
+            // Degenerate arc, the corner is drawn as a straight line
+            return self.line_to(x1, y1);
+        }
+
+        let v1 = (v1.0 / len1, v1.1 / len1);
+        let v2 = (v2.0 / len2, v2.1 / len2);
+        // Angle between the two tangent lines
+        let angle = (v1.0 * v2.0 + v1.1 * v2.1).clamp(-1.0, 1.0).acos();
+        let tangent_distance = radius / (angle / 2.0).tan();
+        let center_distance = radius / (angle / 2.0).sin();
+
+        let t1 = (x1 + v1.0 * tangent_distance, y1 + v1.1 * tangent_distance);
+        let bisector = (v1.0 + v2.0, v1.1 + v2.1);
+        let bisector_len = (bisector.0 * bisector.0 + bisector.1 * bisector.1).sqrt();
+        let center = (
+            x1 + bisector.0 / bisector_len * center_distance,
+            y1 + bisector.1 / bisector_len * center_distance,
+        );
+
+        let start = (t1.1 - center.1).atan2(t1.0 - center.0);
+        let sweep = if cross > 0.0 {
+            -(PI - angle)
+        } else {
+            PI - angle
+        };
+
+        self.line_to(t1.0, t1.1);
+        self.append_arc(center, radius, start, sweep);
+        self
+    }
+
+    /// Closes the current sub-path with a straight line to its start
+    pub fn close(&mut self) -> &mut Self {
+        if self.current.is_some() {
+            self.elements.push(PathElement::Close);
+            self.current = Some(self.start);
+        }
+        self
+    }
+
+    /// Appends a circular arc as cubic Bézier curves, starting at the current point
+    pub(crate) fn append_arc(&mut self, center: (f32, f32), radius: f32, start: f32, sweep: f32) {
+        // Each curve spans at most a quarter of a circle to keep the approximation error tiny
+        let segments = (sweep.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+        let step = sweep / segments as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        let mut angle = start;
+        for _ in 0..segments {
+            let (sin0, cos0) = angle.sin_cos();
+            let (sin1, cos1) = (angle + step).sin_cos();
+            self.cubic_to(
+                center.0 + radius * (cos0 - k * sin0),
+                center.1 + radius * (sin0 + k * cos0),
+                center.0 + radius * (cos1 + k * sin1),
+                center.1 + radius * (sin1 - k * cos1),
+                center.0 + radius * cos1,
+                center.1 + radius * sin1,
+            );
+            angle += step;
+        }
+    }
+
+    /// Approximates the path with polylines.
+    /// Curves are subdivided so that no point of the polylines is further than tolerance from the real curve.
+    /// This is used by backends that cannot draw curves natively.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = tolerance.max(0.001);
+        let mut polylines = Vec::new();
+        let mut points: Vec<(f32, f32)> = Vec::new();
+        let mut start = (0.0, 0.0);
+        let mut current = (0.0, 0.0);
+
+        let finish = |points: &mut Vec<(f32, f32)>, polylines: &mut Vec<Polyline>, closed| {
+            if points.len() > 1 || (closed && !points.is_empty()) {
+                polylines.push(Polyline {
+                    points: std::mem::take(points),
+                    closed,
+                });
+            } else {
+                points.clear();
+            }
+        };
+
+        for element in &self.elements {
+            match *element {
+                PathElement::MoveTo(x, y) => {
+                    finish(&mut points, &mut polylines, false);
+                    points.push((x, y));
+                    start = (x, y);
+                    current = (x, y);
+                }
+                PathElement::LineTo(x, y) => {
+                    if points.is_empty() {
+                        points.push(current);
+                    }
+                    points.push((x, y));
+                    current = (x, y);
+                }
+                PathElement::QuadTo(x1, y1, x, y) => {
+                    if points.is_empty() {
+                        points.push(current);
+                    }
+                    let (p0, p1, p2) = (current, (x1, y1), (x, y));
+                    let dd = length(p0.0 - 2.0 * p1.0 + p2.0, p0.1 - 2.0 * p1.1 + p2.1);
+                    let n = segment_count(0.25 * dd, tolerance);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        points.push((
+                            mt * mt * p0.0 + 2.0 * mt * t * p1.0 + t * t * p2.0,
+                            mt * mt * p0.1 + 2.0 * mt * t * p1.1 + t * t * p2.1,
+                        ));
+                    }
+                    current = p2;
+                }
+                PathElement::CubicTo(x1, y1, x2, y2, x, y) => {
+                    if points.is_empty() {
+                        points.push(current);
+                    }
+                    let (p0, p1, p2, p3) = (current, (x1, y1), (x2, y2), (x, y));
+                    let dd = length(p0.0 - 2.0 * p1.0 + p2.0, p0.1 - 2.0 * p1.1 + p2.1)
+                        .max(length(p1.0 - 2.0 * p2.0 + p3.0, p1.1 - 2.0 * p2.1 + p3.1));
+                    let n = segment_count(0.75 * dd, tolerance);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let mt = 1.0 - t;
+                        let (a, b, c, d) =
+                            (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                        points.push((
+                            a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                            a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+                        ));
+                    }
+                    current = p3;
+                }
+                PathElement::Close => {
+                    finish(&mut points, &mut polylines, true);
+                    current = start;
+                }
+            }
+        }
+        finish(&mut points, &mut polylines, false);
+        polylines
+    }
+}
+
+#[inline]
+fn length(x: f32, y: f32) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+/// Number of segments needed to approximate a curve within the tolerance (Wang's formula)
+#[inline]
+fn segment_count(deviation: f32, tolerance: f32) -> usize {
+    ((deviation / tolerance).sqrt().ceil() as usize).clamp(1, 1000)
+}
@@ -0,0 +1,91 @@
+//! Blend modes of the software backend on premultiplied colors
+
+use crate::BlendMode;
+
+/// Composites the premultiplied color src over dst with a blend mode
+#[inline]
+pub(crate) fn composite(dst: &mut [f32; 4], src: [f32; 4], mode: BlendMode) {
+    let (sa, da) = (src[3], dst[3]);
+    match mode {
+        BlendMode::Normal => {
+            for (d, s) in dst.iter_mut().zip(src) {
+                *d = s + *d * (1.0 - sa);
+            }
+        }
+        BlendMode::Add => {
+            for (d, s) in dst.iter_mut().zip(src) {
+                *d = (s + *d).min(1.0);
+            }
+        }
+        _ => {
+            for i in 0..3 {
+                let (s, d) = (src[i], dst[i]);
+                // The blend functions work on straight colors
+                let cs = if sa > 0.0 { s / sa } else { 0.0 };
+                let cb = if da > 0.0 { d / da } else { 0.0 };
+                dst[i] = s * (1.0 - da) + d * (1.0 - sa) + sa * da * blend(mode, cb, cs);
+            }
+            dst[3] = sa + da - sa * da;
+        }
+    }
+}
+
+/// Blended straight color of the backdrop cb and the source cs
+#[inline]
+fn blend(mode: BlendMode, cb: f32, cs: f32) -> f32 {
+    match mode {
+        BlendMode::Normal | BlendMode::Add => cs,
+        BlendMode::Multiply => cb * cs,
+        BlendMode::Screen => screen(cb, cs),
+        BlendMode::Overlay => hard_light(cs, cb),
+        BlendMode::Darken => cb.min(cs),
+        BlendMode::Lighten => cb.max(cs),
+        BlendMode::ColorDodge => {
+            if cb <= 0.0 {
+                0.0
+            } else if cs >= 1.0 {
+                1.0
+            } else {
+                (cb / (1.0 - cs)).min(1.0)
+            }
+        }
+        BlendMode::ColorBurn => {
+            if cb >= 1.0 {
+                1.0
+            } else if cs <= 0.0 {
+                0.0
+            } else {
+                1.0 - ((1.0 - cb) / cs).min(1.0)
+            }
+        }
+        BlendMode::HardLight => hard_light(cb, cs),
+        BlendMode::SoftLight => {
+            if cs <= 0.5 {
+                cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+            } else {
+                let d = if cb <= 0.25 {
+                    ((16.0 * cb - 12.0) * cb + 4.0) * cb
+                } else {
+                    cb.sqrt()
+                };
+                cb + (2.0 * cs - 1.0) * (d - cb)
+            }
+        }
+        BlendMode::Difference => (cb - cs).abs(),
+        BlendMode::Exclusion => cb + cs - 2.0 * cb * cs,
+    }
+}
+
+#[inline]
+fn screen(cb: f32, cs: f32) -> f32 {
+    cb + cs - cb * cs
+}
+
+#[inline]
+fn hard_light(cb: f32, cs: f32) -> f32 {
+    if cs <= 0.5 {
+        cb * 2.0 * cs
+    } else {
+        screen(cb, 2.0 * cs - 1.0)
+    }
+}
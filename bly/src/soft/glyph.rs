@@ -0,0 +1,118 @@
+//! Cache of rasterized glyphs for the software backend
+
+use crate::soft::raster::{Mask, Rasterizer};
+use crate::soft::TOLERANCE;
+use crate::text::append_outline;
+use crate::{Font, Path};
+use std::collections::HashMap;
+
+/// Glyph positions are rounded to a quarter pixel, so a glyph is rasterized at most 16 times per size
+const SUBPIXEL_STEPS: f32 = 4.0;
+/// The cache is emptied when it is full, which keeps its memory bounded when the text keeps changing
+const MAX_GLYPHS: usize = 2048;
+/// Larger glyphs are not cached, they are filled as paths instead
+pub(crate) const MAX_CACHED_SIZE: f32 = 256.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: u64,
+    glyph: u16,
+    size: u32,
+    subpixel: (u8, u8),
+}
+
+/// Coverage of a glyph.
+/// The mask is placed relative to the pixel that contains the glyph origin, moved by (left, top)
+pub(crate) struct GlyphMask {
+    pub mask: Mask,
+    pub left: isize,
+    pub top: isize,
+}
+
+#[derive(Default)]
+pub(crate) struct GlyphCache {
+    glyphs: HashMap<GlyphKey, Option<GlyphMask>>,
+}
+
+impl GlyphCache {
+    /// Returns the mask of a glyph whose origin is at (x, y), and the pixel that contains the origin.
+    /// None if the glyph has no outline (e.g. a space)
+    pub fn get(
+        &mut self,
+        font: &Font,
+        size: f32,
+        glyph: u16,
+        x: f32,
+        y: f32,
+    ) -> Option<(&GlyphMask, isize, isize)> {
+        let (ix, fx) = split(x);
+        let (iy, fy) = split(y);
+        let key = GlyphKey {
+            font: font.id(),
+            glyph,
+            size: size.to_bits(),
+            subpixel: (fx, fy),
+        };
+        if self.glyphs.len() >= MAX_GLYPHS && !self.glyphs.contains_key(&key) {
+            self.glyphs.clear();
+        }
+        let mask = self.glyphs.entry(key).or_insert_with(|| {
+            rasterize_glyph(
+                font,
+                size,
+                glyph,
+                fx as f32 / SUBPIXEL_STEPS,
+                fy as f32 / SUBPIXEL_STEPS,
+            )
+        });
+        mask.as_ref().map(|mask| (mask, ix, iy))
+    }
+}
+
+/// Splits a coordinate into its pixel and the subpixel step inside of it
+#[inline]
+fn split(v: f32) -> (isize, u8) {
+    let steps = (v * SUBPIXEL_STEPS).round();
+    let pixel = (steps / SUBPIXEL_STEPS).floor();
+    (pixel as isize, (steps - pixel * SUBPIXEL_STEPS) as u8)
+}
+
+/// Rasterizes a glyph whose origin is at (x, y) inside of the pixel (0, 0)
+fn rasterize_glyph(font: &Font, size: f32, glyph: u16, x: f32, y: f32) -> Option<GlyphMask> {
+    let outline = font.glyph_outline(glyph)?;
+    let mut path = Path::new();
+    append_outline(&mut path, &outline, font.scale(size), (x, y));
+    let polylines = path.flatten(TOLERANCE);
+
+    let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+    let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+    for &(x, y) in polylines.iter().flat_map(|polyline| &polyline.points) {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    if min_x > max_x {
+        return None;
+    }
+
+    // The rasterizer clips to positive coordinates, so the glyph is moved there
+    let (left, top) = (min_x.floor(), min_y.floor());
+    let mut rasterizer = Rasterizer::new();
+    for polyline in &polylines {
+        let points: Vec<(f32, f32)> = polyline
+            .points
+            .iter()
+            .map(|&(x, y)| (x - left, y - top))
+            .collect();
+        rasterizer.add_polygon(&points);
+    }
+    let width = (max_x - left).ceil() as usize + 1;
+    let height = (max_y - top).ceil() as usize + 1;
+    let mask = rasterizer.rasterize(width, height)?;
+    Some(GlyphMask {
+        mask,
+        left: left as isize,
+        top: top as isize,
+    })
+}
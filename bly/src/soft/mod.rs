@@ -0,0 +1,452 @@
+//! Software (CPU) drawing backend for Bly
+//! Draws into an in-memory pixel buffer, so no display server is required
+
+use crate::path::Polyline;
+use crate::{
+    Backend, BlendMode, BlendSpace, Error, GlyphRun, Image, Paint, Path, Point, Rect, StrokeStyle,
+    Transform2D,
+};
+use std::f32::consts::PI;
+
+mod blend;
+mod glyph;
+mod pixmap;
+mod raster;
+pub(crate) mod shader;
+pub(crate) mod stroke;
+
+use glyph::GlyphCache;
+use pixmap::Pixmap;
+use raster::{Mask, Rasterizer};
+use shader::Shader;
+
+/// Maximum distance between a flattened curve and the real curve, in pixels of the canvas
+const TOLERANCE: f32 = 0.1;
+
+#[doc(hidden)]
+pub fn create_backend(width: u32, height: u32) -> SoftBackend {
+    info!("SoftBackend is being created. {}x{}", width, height);
+    SoftBackend {
+        pixmap: Pixmap::new(width as usize, height as usize, BlendSpace::Srgb),
+        space: BlendSpace::Srgb,
+        glyphs: GlyphCache::default(),
+        transform: Transform2D::identity(),
+        tolerance: TOLERANCE,
+        clips: Vec::new(),
+        blend: BlendMode::Normal,
+        layers: Vec::new(),
+    }
+}
+
+#[doc(hidden)]
+pub struct SoftBackend {
+    pixmap: Pixmap,
+    /// Blend space of the pixmaps
+    space: BlendSpace,
+    glyphs: GlyphCache,
+    transform: Transform2D,
+    /// TOLERANCE in user space, so that scaled up curves stay smooth
+    tolerance: f32,
+    /// Pushed clips, each one already intersected with the previous ones
+    clips: Vec<Mask>,
+    blend: BlendMode,
+    /// Pushed layers, the canvas is drawn into a new pixmap until the layer is popped
+    layers: Vec<Layer>,
+}
+
+struct Layer {
+    /// Pixmap that the layer is composited over when it is popped
+    parent: Pixmap,
+    opacity: f32,
+    blend: BlendMode,
+}
+
+impl Backend for SoftBackend {
+    #[inline]
+    unsafe fn begin_draw(&mut self) -> Result<(), Error> {
+        self.clips.clear();
+        // Layers left open by the last frame are dropped, the canvas is their bottom parent
+        if let Some(layer) = self.layers.drain(..).next() {
+            self.pixmap = layer.parent;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn get_display_size(&mut self) -> (u32, u32) {
+        (self.pixmap.width() as u32, self.pixmap.height() as u32)
+    }
+
+    #[inline]
+    unsafe fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != self.get_display_size() {
+            info!("SoftBackend is being resized. {}x{}", width, height);
+            self.pixmap = Pixmap::new(width as usize, height as usize, self.space);
+        }
+    }
+
+    #[inline]
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error> {
+        self.space = space;
+        self.pixmap.set_space(space);
+        for layer in &mut self.layers {
+            layer.parent.set_space(space);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.pixmap.fill(r, g, b, a, self.clips.last());
+    }
+
+    #[inline]
+    unsafe fn set_transform(&mut self, transform: &Transform2D) {
+        self.transform = *transform;
+        let scale = transform.max_scale();
+        self.tolerance = if scale > 0.0 && scale.is_finite() {
+            TOLERANCE / scale
+        } else {
+            TOLERANCE
+        };
+    }
+
+    #[inline]
+    unsafe fn push_clip_rect(&mut self, rect: Rect) {
+        let mut rasterizer = Rasterizer::new();
+        self.add_polygon(&mut rasterizer, &rectangle_points(&rect));
+        self.push_clip(rasterizer);
+    }
+
+    #[inline]
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        let points = rounded_rectangle_points(&rect, radius, self.tolerance);
+        let mut rasterizer = Rasterizer::new();
+        self.add_polygon(&mut rasterizer, &points);
+        self.push_clip(rasterizer);
+    }
+
+    #[inline]
+    unsafe fn push_clip_path(&mut self, path: &Path) {
+        let mut rasterizer = Rasterizer::new();
+        for polyline in path.flatten(self.tolerance) {
+            self.add_polygon(&mut rasterizer, &polyline.points);
+        }
+        self.push_clip(rasterizer);
+    }
+
+    #[inline]
+    unsafe fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    #[inline]
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend = mode;
+    }
+
+    #[inline]
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        let pixmap = Pixmap::new(self.pixmap.width(), self.pixmap.height(), self.space);
+        self.layers.push(Layer {
+            parent: std::mem::replace(&mut self.pixmap, pixmap),
+            opacity,
+            blend: mode,
+        });
+    }
+
+    #[inline]
+    unsafe fn pop_layer(&mut self) {
+        if let Some(layer) = self.layers.pop() {
+            let pixmap = std::mem::replace(&mut self.pixmap, layer.parent);
+            self.pixmap
+                .draw_pixmap(&pixmap, layer.opacity, self.clips.last(), layer.blend);
+        }
+    }
+
+    #[inline]
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint) {
+        self.fill_polygon(&ellipse_points(&point, radius, self.tolerance), paint);
+    }
+
+    #[inline]
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint) {
+        self.fill_polygon(&rectangle_points(&rect), paint);
+    }
+
+    #[inline]
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint) {
+        let points = rounded_rectangle_points(&rect, radius, self.tolerance);
+        self.fill_polygon(&points, paint);
+    }
+
+    #[inline]
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint) {
+        let polyline = Polyline {
+            points: vec![point1.into(), point2.into()],
+            closed: false,
+        };
+        self.stroke_polylines(&[polyline], style, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_ellipse(
+        &mut self,
+        point: Point,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
+    ) {
+        let polyline = Polyline {
+            points: ellipse_points(&point, radius, self.tolerance),
+            closed: true,
+        };
+        self.stroke_polylines(&[polyline], style, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint) {
+        let polyline = Polyline {
+            points: rectangle_points(&rect),
+            closed: true,
+        };
+        self.stroke_polylines(&[polyline], style, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_rounded_rectangle(
+        &mut self,
+        rect: Rect,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
+    ) {
+        let polyline = Polyline {
+            points: rounded_rectangle_points(&rect, radius, self.tolerance),
+            closed: true,
+        };
+        self.stroke_polylines(&[polyline], style, paint);
+    }
+
+    #[inline]
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint) {
+        let mut rasterizer = Rasterizer::new();
+        for polyline in path.flatten(self.tolerance) {
+            self.add_polygon(&mut rasterizer, &polyline.points);
+        }
+        self.fill_rasterizer(rasterizer, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint) {
+        self.stroke_polylines(&path.flatten(self.tolerance), style, paint);
+    }
+
+    #[inline]
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        if dest.width == 0.0 || dest.height == 0.0 {
+            return;
+        }
+        let mut rasterizer = Rasterizer::new();
+        self.add_polygon(
+            &mut rasterizer,
+            &[
+                (dest.x, dest.y),
+                (dest.x + dest.width, dest.y),
+                (dest.x + dest.width, dest.y + dest.height),
+                (dest.x, dest.y + dest.height),
+            ],
+        );
+        if let Some(mask) = rasterizer.rasterize(self.pixmap.width(), self.pixmap.height()) {
+            let shader =
+                Shader::image(image, dest, src, opacity, self.space).transformed(&self.transform);
+            self.pixmap
+                .fill_mask(&mask, &shader, self.clips.last(), self.blend);
+        }
+    }
+
+    #[inline]
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint) {
+        // Cached masks can only be moved, other transforms draw the outlines
+        if run.size() > glyph::MAX_CACHED_SIZE || !self.transform.is_translation() {
+            self.fill_path(&run.to_path(origin), paint);
+            return;
+        }
+        let shader = Shader::new(paint, self.space).transformed(&self.transform);
+        let (origin_x, origin_y) = self.transform.apply(origin.x, origin.y);
+        for glyph in run.glyphs() {
+            let x = origin_x + glyph.x;
+            let y = origin_y + glyph.y;
+            if let Some((glyph_mask, x, y)) =
+                self.glyphs.get(run.font(), run.size(), glyph.id, x, y)
+            {
+                self.pixmap.fill_mask_at(
+                    &glyph_mask.mask,
+                    x + glyph_mask.left,
+                    y + glyph_mask.top,
+                    &shader,
+                    self.clips.last(),
+                    self.blend,
+                );
+            }
+        }
+    }
+
+    #[inline]
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        let (width, height) = self.get_display_size();
+        Some((width, height, self.pixmap.to_rgba8()))
+    }
+}
+
+impl SoftBackend {
+    /// The drawn pixels for a Wayland shm buffer, see Pixmap::to_argb8888()
+    #[cfg_attr(not(feature = "wayland"), allow(dead_code))]
+    pub(crate) fn to_argb8888(&self) -> Vec<u8> {
+        self.pixmap.to_argb8888()
+    }
+
+    fn fill_polygon(&mut self, points: &[(f32, f32)], paint: &Paint) {
+        let mut rasterizer = Rasterizer::new();
+        self.add_polygon(&mut rasterizer, points);
+        self.fill_rasterizer(rasterizer, paint);
+    }
+
+    /// Adds a polygon in user space to the rasterizer, which works in pixels of the canvas
+    fn add_polygon(&self, rasterizer: &mut Rasterizer, points: &[(f32, f32)]) {
+        if self.transform.is_identity() {
+            rasterizer.add_polygon(points);
+        } else {
+            let points: Vec<_> = points
+                .iter()
+                .map(|&(x, y)| self.transform.apply(x, y))
+                .collect();
+            rasterizer.add_polygon(&points);
+        }
+    }
+
+    fn stroke_polylines(&mut self, polylines: &[Polyline], style: &StrokeStyle, paint: &Paint) {
+        let canvas = Rect::new(
+            0.0,
+            0.0,
+            self.pixmap.width() as f32,
+            self.pixmap.height() as f32,
+        );
+        // A transform that cannot be inverted flattens everything, so nothing is visible
+        let Some(visible) = self.transform.inverse_bounds(&canvas) else {
+            return;
+        };
+        let mut polygons = Vec::new();
+        for polyline in polylines {
+            stroke::stroke_polyline(polyline, style, self.tolerance, &visible, &mut polygons);
+        }
+        // Strokes are built in user space, so the transform also applies to their width
+        let mut rasterizer = Rasterizer::new();
+        for polygon in &polygons {
+            self.add_polygon(&mut rasterizer, polygon);
+        }
+        self.fill_rasterizer(rasterizer, paint);
+    }
+
+    fn fill_rasterizer(&mut self, mut rasterizer: Rasterizer, paint: &Paint) {
+        if let Some(mask) = rasterizer.rasterize(self.pixmap.width(), self.pixmap.height()) {
+            let shader = Shader::new(paint, self.space).transformed(&self.transform);
+            self.pixmap
+                .fill_mask(&mask, &shader, self.clips.last(), self.blend);
+        }
+    }
+
+    fn push_clip(&mut self, mut rasterizer: Rasterizer) {
+        let mut clip = rasterizer
+            .rasterize(self.pixmap.width(), self.pixmap.height())
+            .unwrap_or_else(Mask::empty);
+        if let Some(outer) = self.clips.last() {
+            clip.intersect(outer);
+        }
+        self.clips.push(clip);
+    }
+}
+
+/// Outline of the circle drawn by ellipse(), whose bounding box starts at point
+pub(crate) fn ellipse_points(point: &Point, radius: f32, tolerance: f32) -> Vec<(f32, f32)> {
+    let mut points = Vec::new();
+    arc(
+        &mut points,
+        (point.x + radius, point.y + radius),
+        radius,
+        (0.0, 2.0 * PI),
+        tolerance,
+    );
+    // The last point is the first one again
+    points.pop();
+    points
+}
+
+/// Outline of a rectangle
+pub(crate) fn rectangle_points(rect: &Rect) -> Vec<(f32, f32)> {
+    let (x, y, w, h) = (rect.x, rect.y, rect.width, rect.height);
+    vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)]
+}
+
+/// Outline of a rounded rectangle
+pub(crate) fn rounded_rectangle_points(
+    rect: &Rect,
+    radius: f32,
+    tolerance: f32,
+) -> Vec<(f32, f32)> {
+    let (x, y, w, h) = (rect.x, rect.y, rect.width, rect.height);
+    let radius = radius.min(w.abs() / 2.0).min(h.abs() / 2.0).max(0.0);
+
+    let mut points = Vec::new();
+    let corners = [
+        ((x + w - radius, y + radius), -PI / 2.0),
+        ((x + w - radius, y + h - radius), 0.0),
+        ((x + radius, y + h - radius), PI / 2.0),
+        ((x + radius, y + radius), PI),
+    ];
+    for (center, start) in corners {
+        arc(
+            &mut points,
+            center,
+            radius,
+            (start, start + PI / 2.0),
+            tolerance,
+        );
+    }
+    points
+}
+
+/// Appends the points of a circular arc between two angles (clockwise in screen space)
+fn arc(
+    points: &mut Vec<(f32, f32)>,
+    center: (f32, f32),
+    radius: f32,
+    (a0, a1): (f32, f32),
+    tolerance: f32,
+) {
+    let segments = arc_segments(radius, a1 - a0, tolerance);
+    for i in 0..=segments {
+        let angle = a0 + (a1 - a0) * i as f32 / segments as f32;
+        points.push((
+            center.0 + radius * angle.cos(),
+            center.1 + radius * angle.sin(),
+        ));
+    }
+}
+
+/// Number of segments needed to approximate an arc of the given angle within the tolerance.
+/// The count is clamped like for curves: once the radius is about 1e7 times the tolerance,
+/// 1 - tolerance / radius rounds to 1 and the step to 0
+pub(crate) fn arc_segments(radius: f32, angle: f32, tolerance: f32) -> usize {
+    if radius > tolerance {
+        let step = 2.0 * (1.0 - tolerance / radius).acos();
+        ((angle.abs() / step).ceil() as usize).clamp(1, 1000)
+    } else {
+        1
+    }
+}
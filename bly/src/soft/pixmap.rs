@@ -0,0 +1,201 @@
+//! In-memory pixel storage for the software backend
+
+use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::soft::blend::composite;
+use crate::soft::raster::Mask;
+use crate::soft::shader::{premultiply_in, Shader};
+use crate::{BlendMode, BlendSpace};
+
+/// RGBA pixel buffer.
+/// Pixels are stored as premultiplied floats so that compositing does not lose precision,
+/// with the colors encoded in the blend space
+pub(crate) struct Pixmap {
+    width: usize,
+    height: usize,
+    space: BlendSpace,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Pixmap {
+    pub fn new(width: usize, height: usize, space: BlendSpace) -> Self {
+        Self {
+            width,
+            height,
+            space,
+            pixels: vec![[0.0; 4]; width * height],
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Converts the pixels to another blend space, keeping the colors they show
+    pub fn set_space(&mut self, space: BlendSpace) {
+        if space == self.space {
+            return;
+        }
+        let convert = match space {
+            BlendSpace::Srgb => linear_to_srgb,
+            BlendSpace::Linear => srgb_to_linear,
+        };
+        for p in &mut self.pixels {
+            *p = map_straight(*p, convert);
+        }
+        self.space = space;
+    }
+
+    /// Replaces every pixel inside of the clip with the given straight sRGB color
+    pub fn fill(&mut self, r: f32, g: f32, b: f32, a: f32, clip: Option<&Mask>) {
+        let color = premultiply_in(self.space, r, g, b, a);
+        let clip = match clip {
+            Some(clip) => clip,
+            None => {
+                self.pixels.iter_mut().for_each(|p| *p = color);
+                return;
+            }
+        };
+        for row in 0..clip.height {
+            let start = (clip.y + row) * self.width + clip.x;
+            let pixels = &mut self.pixels[start..start + clip.width];
+            let coverage = &clip.data[row * clip.width..(row + 1) * clip.width];
+            for (dst, &c) in pixels.iter_mut().zip(coverage) {
+                for (d, s) in dst.iter_mut().zip(color) {
+                    *d = s * c + *d * (1.0 - c);
+                }
+            }
+        }
+    }
+
+    /// Composites the colors of a shader over the pixmap through a coverage mask and a clip
+    #[inline]
+    pub fn fill_mask(
+        &mut self,
+        mask: &Mask,
+        shader: &Shader,
+        clip: Option<&Mask>,
+        blend: BlendMode,
+    ) {
+        self.fill_mask_at(mask, 0, 0, shader, clip, blend);
+    }
+
+    /// Composites another pixmap of the same size over this one, e.g. a layer over its parent
+    pub fn draw_pixmap(
+        &mut self,
+        src: &Pixmap,
+        opacity: f32,
+        clip: Option<&Mask>,
+        blend: BlendMode,
+    ) {
+        for (i, (dst, s)) in self.pixels.iter_mut().zip(&src.pixels).enumerate() {
+            let c = match clip {
+                Some(clip) => opacity * clip.coverage(i % self.width, i / self.width),
+                None => opacity,
+            };
+            if c <= 0.0 || s[3] <= 0.0 {
+                continue;
+            }
+            composite(dst, s.map(|v| v * c), blend);
+        }
+    }
+
+    /// Same as fill_mask() with the mask moved by (dx, dy).
+    /// The parts of the mask outside of the pixmap are skipped
+    pub fn fill_mask_at(
+        &mut self,
+        mask: &Mask,
+        dx: isize,
+        dy: isize,
+        shader: &Shader,
+        clip: Option<&Mask>,
+        blend: BlendMode,
+    ) {
+        let left = mask.x as isize + dx;
+        let top = mask.y as isize + dy;
+        let first_column = (-left).clamp(0, mask.width as isize) as usize;
+        let last_column = (self.width as isize - left).clamp(0, mask.width as isize) as usize;
+        if first_column >= last_column {
+            return;
+        }
+        for row in 0..mask.height {
+            let y = top + row as isize;
+            if y < 0 || y >= self.height as isize {
+                continue;
+            }
+            let coverage =
+                &mask.data[row * mask.width + first_column..row * mask.width + last_column];
+            let start = y as usize * self.width + (left + first_column as isize) as usize;
+            let pixels = &mut self.pixels[start..start + coverage.len()];
+            let (row_y, row_x) = (y as usize, (left + first_column as isize) as usize);
+            // Colors are sampled at the pixel centers
+            let y = y as f32 + 0.5;
+            let x = row_x as f32 + 0.5;
+            for (column, (dst, &c)) in pixels.iter_mut().zip(coverage).enumerate() {
+                let c = match clip {
+                    Some(clip) => c * clip.coverage(row_x + column, row_y),
+                    None => c,
+                };
+                if c <= 0.0 {
+                    continue;
+                }
+                let color = shader.color_at(x + column as f32, y);
+                composite(dst, color.map(|v| v * c), blend);
+            }
+        }
+    }
+
+    /// Converts the pixmap to straight (non-premultiplied) sRGB RGBA8, row by row from the top left
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.pixels.len() * 4);
+        for p in &self.pixels {
+            let a = p[3];
+            if a <= 0.0 {
+                out.extend_from_slice(&[0, 0, 0, 0]);
+                continue;
+            }
+            let (r, g, b) = (p[0] / a, p[1] / a, p[2] / a);
+            let [r, g, b] = match self.space {
+                BlendSpace::Srgb => [r, g, b],
+                BlendSpace::Linear => [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b)],
+            };
+            out.extend_from_slice(&[to_u8(r), to_u8(g), to_u8(b), to_u8(a)]);
+        }
+        out
+    }
+
+    /// Premultiplied sRGB pixels as little-endian ARGB8888 words (B, G, R, A bytes),
+    /// the pixel format that every Wayland compositor supports
+    #[cfg_attr(not(feature = "wayland"), allow(dead_code))]
+    pub fn to_argb8888(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.pixels.len() * 4);
+        for p in &self.pixels {
+            let p = match self.space {
+                BlendSpace::Srgb => *p,
+                BlendSpace::Linear => map_straight(*p, linear_to_srgb),
+            };
+            out.extend_from_slice(&[to_u8(p[2]), to_u8(p[1]), to_u8(p[0]), to_u8(p[3])]);
+        }
+        out
+    }
+}
+
+/// Applies f to the straight color components of a premultiplied pixel
+#[inline]
+fn map_straight(p: [f32; 4], f: fn(f32) -> f32) -> [f32; 4] {
+    let a = p[3];
+    if a <= 0.0 {
+        return p;
+    }
+    [f(p[0] / a) * a, f(p[1] / a) * a, f(p[2] / a) * a, a]
+}
+
+#[inline]
+fn to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
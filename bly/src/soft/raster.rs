@@ -0,0 +1,226 @@
+//! Scanline coverage rasterizer for the software backend
+
+/// Number of sub-scanlines sampled per pixel row.
+/// Horizontal coverage is computed exactly, so this only affects vertical anti-aliasing
+const SUBSAMPLES: usize = 16;
+
+#[derive(Debug, Copy, Clone)]
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+
+impl Edge {
+    #[inline]
+    fn x_at(&self, y: f32) -> f32 {
+        self.x0 + (y - self.y0) * (self.x1 - self.x0) / (self.y1 - self.y0)
+    }
+}
+
+/// Coverage of a shape inside its bounding box, each value between 0.0 and 1.0
+pub(crate) struct Mask {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<f32>,
+}
+
+impl Mask {
+    /// A mask that covers nothing
+    pub fn empty() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            data: Vec::new(),
+        }
+    }
+
+    /// Coverage of the pixel (x, y) of the target, 0.0 outside of the bounding box
+    #[inline]
+    pub fn coverage(&self, x: usize, y: usize) -> f32 {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return 0.0;
+        }
+        self.data[(y - self.y) * self.width + x - self.x]
+    }
+
+    /// Keeps only the coverage that is also inside of other
+    pub fn intersect(&mut self, other: &Mask) {
+        for row in 0..self.height {
+            for column in 0..self.width {
+                self.data[row * self.width + column] *=
+                    other.coverage(self.x + column, self.y + row);
+            }
+        }
+    }
+}
+
+/// Accumulates polygon edges and turns them into a coverage mask using the non-zero fill rule
+#[derive(Default)]
+pub(crate) struct Rasterizer {
+    edges: Vec<Edge>,
+}
+
+impl Rasterizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a closed polygon. The last point is connected to the first one
+    pub fn add_polygon(&mut self, points: &[(f32, f32)]) {
+        if points.len() < 3 {
+            return;
+        }
+        for i in 0..points.len() {
+            let p0 = points[i];
+            let p1 = points[(i + 1) % points.len()];
+            self.add_edge(p0, p1);
+        }
+    }
+
+    fn add_edge(&mut self, p0: (f32, f32), p1: (f32, f32)) {
+        if p0.1 == p1.1 || !p0.0.is_finite() || !p0.1.is_finite() {
+            return;
+        }
+        if !p1.0.is_finite() || !p1.1.is_finite() {
+            return;
+        }
+        let edge = if p0.1 < p1.1 {
+            Edge {
+                x0: p0.0,
+                y0: p0.1,
+                x1: p1.0,
+                y1: p1.1,
+                winding: 1,
+            }
+        } else {
+            Edge {
+                x0: p1.0,
+                y0: p1.1,
+                x1: p0.0,
+                y1: p0.1,
+                winding: -1,
+            }
+        };
+        self.edges.push(edge);
+    }
+
+    /// Rasterizes the accumulated edges, clipped to a target of the given size
+    pub fn rasterize(&mut self, width: usize, height: usize) -> Option<Mask> {
+        if self.edges.is_empty() || width == 0 || height == 0 {
+            return None;
+        }
+
+        let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+        let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+        for e in &self.edges {
+            min_x = min_x.min(e.x0).min(e.x1);
+            max_x = max_x.max(e.x0).max(e.x1);
+            min_y = min_y.min(e.y0);
+            max_y = max_y.max(e.y1);
+        }
+
+        let bx0 = min_x.floor().max(0.0) as usize;
+        let by0 = min_y.floor().max(0.0) as usize;
+        let bx1 = (max_x.ceil().max(0.0) as usize).min(width);
+        let by1 = (max_y.ceil().max(0.0) as usize).min(height);
+        if bx0 >= bx1 || by0 >= by1 {
+            return None;
+        }
+
+        let mask_width = bx1 - bx0;
+        let mask_height = by1 - by0;
+        let mut data = vec![0.0; mask_width * mask_height];
+
+        self.edges
+            .sort_by(|a, b| a.y0.partial_cmp(&b.y0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let weight = 1.0 / SUBSAMPLES as f32;
+        let mut next = 0;
+        let mut active: Vec<Edge> = Vec::new();
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+        let mut partial = vec![0.0f32; mask_width + 1];
+        let mut delta = vec![0.0f32; mask_width + 1];
+
+        for row in by0..by1 {
+            let top = row as f32;
+            let bottom = top + 1.0;
+
+            active.retain(|e| e.y1 > top);
+            while next < self.edges.len() && self.edges[next].y0 < bottom {
+                if self.edges[next].y1 > top {
+                    active.push(self.edges[next]);
+                }
+                next += 1;
+            }
+            if active.is_empty() {
+                continue;
+            }
+
+            partial.iter_mut().for_each(|v| *v = 0.0);
+            delta.iter_mut().for_each(|v| *v = 0.0);
+
+            for s in 0..SUBSAMPLES {
+                let sy = top + (s as f32 + 0.5) * weight;
+                crossings.clear();
+                for e in &active {
+                    if e.y0 <= sy && sy < e.y1 {
+                        crossings.push((e.x_at(sy), e.winding));
+                    }
+                }
+                crossings
+                    .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    if winding != 0 {
+                        let xa = (pair[0].0 - bx0 as f32).clamp(0.0, mask_width as f32);
+                        let xb = (pair[1].0 - bx0 as f32).clamp(0.0, mask_width as f32);
+                        add_span(&mut partial, &mut delta, xa, xb, weight);
+                    }
+                }
+            }
+
+            let out = &mut data[(row - by0) * mask_width..(row - by0 + 1) * mask_width];
+            let mut running = 0.0;
+            for (x, value) in out.iter_mut().enumerate() {
+                running += delta[x];
+                *value = (running + partial[x]).min(1.0);
+            }
+        }
+
+        Some(Mask {
+            x: bx0,
+            y: by0,
+            width: mask_width,
+            height: mask_height,
+            data,
+        })
+    }
+}
+
+/// Adds the coverage of the horizontal span [xa, xb) to a row.
+/// Fully covered pixels go to the difference array, partially covered ones are added directly
+#[inline]
+fn add_span(partial: &mut [f32], delta: &mut [f32], xa: f32, xb: f32, weight: f32) {
+    if xb <= xa {
+        return;
+    }
+    let ia = xa.floor() as usize;
+    let ib = xb.floor() as usize;
+    if ia == ib {
+        partial[ia] += (xb - xa) * weight;
+        return;
+    }
+    partial[ia] += (ia as f32 + 1.0 - xa) * weight;
+    delta[ia + 1] += weight;
+    delta[ib] -= weight;
+    partial[ib] += (xb - ib as f32) * weight;
+}
@@ -0,0 +1,255 @@
+//! Per-pixel colors of a Paint for the software backend
+
+use crate::color::srgb_to_linear;
+use crate::paint::{color_rgba, extend_offset, sorted_stops};
+use crate::{BlendSpace, ExtendMode, GradientStop, Image, ImageFilter, Paint, Rect, Transform2D};
+
+/// A Paint or an image prepared for evaluation at pixel centers.
+/// Colors are premultiplied and in the blend space, and gradients are interpolated in premultiplied space
+pub(crate) enum Shader<'a> {
+    Solid([f32; 4]),
+    Linear {
+        start: (f32, f32),
+        /// Direction of the gradient divided by its squared length
+        direction: (f32, f32),
+        stops: Vec<(f32, [f32; 4])>,
+        extend: ExtendMode,
+    },
+    Radial {
+        center: (f32, f32),
+        radius: f32,
+        stops: Vec<(f32, [f32; 4])>,
+        extend: ExtendMode,
+    },
+    Image {
+        image: &'a Image,
+        /// Maps canvas coordinates to image coordinates: u = x * scale.0 + offset.0
+        scale: (f32, f32),
+        offset: (f32, f32),
+        /// Pixels that may be sampled, from the top left to the bottom right (inclusive)
+        min: (i64, i64),
+        max: (i64, i64),
+        opacity: f32,
+        space: BlendSpace,
+    },
+    /// Another shader drawn with a transform
+    Transformed {
+        /// Maps canvas coordinates back to the coordinates of the shader
+        inverse: Transform2D,
+        shader: Box<Shader<'a>>,
+    },
+}
+
+impl<'a> Shader<'a> {
+    pub fn new(paint: &Paint, space: BlendSpace) -> Self {
+        match paint {
+            Paint::Solid(color) => {
+                let (r, g, b, a) = color_rgba(*color);
+                Shader::Solid(premultiply_in(space, r, g, b, a))
+            }
+            Paint::LinearGradient(gradient) => {
+                let stops = prepare_stops(&gradient.stops, space);
+                let (dx, dy) = (gradient.end - gradient.start).into();
+                let length = dx * dx + dy * dy;
+                if stops.len() < 2 || length <= 0.0 {
+                    return Shader::Solid(last_color(&stops));
+                }
+                Shader::Linear {
+                    start: gradient.start.into(),
+                    direction: (dx / length, dy / length),
+                    stops,
+                    extend: gradient.extend,
+                }
+            }
+            Paint::RadialGradient(gradient) => {
+                let stops = prepare_stops(&gradient.stops, space);
+                if stops.len() < 2 || gradient.radius <= 0.0 {
+                    return Shader::Solid(last_color(&stops));
+                }
+                Shader::Radial {
+                    center: gradient.center.into(),
+                    radius: gradient.radius,
+                    stops,
+                    extend: gradient.extend,
+                }
+            }
+        }
+    }
+
+    /// Samples the src part of an image drawn into dest.
+    /// Outside of src, the pixels at the edge of src are repeated
+    pub fn image(image: &'a Image, dest: Rect, src: Rect, opacity: f32, space: BlendSpace) -> Self {
+        let scale = (src.width / dest.width, src.height / dest.height);
+        let last = (image.width() as i64 - 1, image.height() as i64 - 1);
+        let min = (
+            (src.x.floor() as i64).clamp(0, last.0),
+            (src.y.floor() as i64).clamp(0, last.1),
+        );
+        let max = (
+            ((src.x + src.width).ceil() as i64 - 1).clamp(min.0, last.0),
+            ((src.y + src.height).ceil() as i64 - 1).clamp(min.1, last.1),
+        );
+        Shader::Image {
+            image,
+            scale,
+            offset: (src.x - dest.x * scale.0, src.y - dest.y * scale.1),
+            min,
+            max,
+            opacity,
+            space,
+        }
+    }
+
+    /// The same shader with its coordinates mapped to the canvas by transform
+    pub fn transformed(self, transform: &Transform2D) -> Self {
+        if transform.is_identity() {
+            return self;
+        }
+        match (self, transform.inverse()) {
+            (Shader::Solid(color), _) => Shader::Solid(color),
+            (shader, Some(inverse)) => Shader::Transformed {
+                inverse,
+                shader: Box::new(shader),
+            },
+            // Nothing is visible through a transform that cannot be inverted
+            (_, None) => Shader::Solid([0.0; 4]),
+        }
+    }
+
+    /// Premultiplied color at the point (x, y)
+    #[inline]
+    pub fn color_at(&self, x: f32, y: f32) -> [f32; 4] {
+        match self {
+            Shader::Solid(color) => *color,
+            Shader::Linear {
+                start,
+                direction,
+                stops,
+                extend,
+            } => {
+                let t = (x - start.0) * direction.0 + (y - start.1) * direction.1;
+                interpolate(stops, extend_offset(t, *extend))
+            }
+            Shader::Radial {
+                center,
+                radius,
+                stops,
+                extend,
+            } => {
+                let (dx, dy) = (x - center.0, y - center.1);
+                let t = (dx * dx + dy * dy).sqrt() / radius;
+                interpolate(stops, extend_offset(t, *extend))
+            }
+            Shader::Image {
+                image,
+                scale,
+                offset,
+                min,
+                max,
+                opacity,
+                space,
+            } => {
+                let u = x * scale.0 + offset.0;
+                let v = y * scale.1 + offset.1;
+                let fetch = |px: i64, py: i64| {
+                    let px = px.clamp(min.0, max.0) as usize;
+                    let py = py.clamp(min.1, max.1) as usize;
+                    let i = (py * image.width() as usize + px) * 4;
+                    let p = &image.pixels()[i..i + 4];
+                    premultiply_in(
+                        *space,
+                        p[0] as f32 / 255.0,
+                        p[1] as f32 / 255.0,
+                        p[2] as f32 / 255.0,
+                        p[3] as f32 / 255.0 * opacity,
+                    )
+                };
+                match image.filter() {
+                    ImageFilter::Nearest => fetch(u.floor() as i64, v.floor() as i64),
+                    ImageFilter::Bilinear => {
+                        // Pixel centers are at half coordinates
+                        let (u, v) = (u - 0.5, v - 0.5);
+                        let (x0, y0) = (u.floor(), v.floor());
+                        let (fx, fy) = (u - x0, v - y0);
+                        let (x0, y0) = (x0 as i64, y0 as i64);
+                        let (c00, c10) = (fetch(x0, y0), fetch(x0 + 1, y0));
+                        let (c01, c11) = (fetch(x0, y0 + 1), fetch(x0 + 1, y0 + 1));
+                        let mut color = [0.0; 4];
+                        for (i, c) in color.iter_mut().enumerate() {
+                            let top = c00[i] + (c10[i] - c00[i]) * fx;
+                            let bottom = c01[i] + (c11[i] - c01[i]) * fx;
+                            *c = top + (bottom - top) * fy;
+                        }
+                        color
+                    }
+                }
+            }
+            Shader::Transformed { inverse, shader } => {
+                let (x, y) = inverse.apply(x, y);
+                shader.color_at(x, y)
+            }
+        }
+    }
+}
+
+fn prepare_stops(stops: &[GradientStop], space: BlendSpace) -> Vec<(f32, [f32; 4])> {
+    sorted_stops(stops)
+        .into_iter()
+        .map(|stop| {
+            let (r, g, b, a) = color_rgba(stop.color);
+            (stop.offset, premultiply_in(space, r, g, b, a))
+        })
+        .collect()
+}
+
+/// Color of a gradient with less than two stops, transparent if it has none
+fn last_color(stops: &[(f32, [f32; 4])]) -> [f32; 4] {
+    stops.last().map_or([0.0; 4], |stop| stop.1)
+}
+
+/// Color of the sorted stops at the offset t, which is between 0.0 and 1.0
+#[inline]
+pub(crate) fn interpolate(stops: &[(f32, [f32; 4])], t: f32) -> [f32; 4] {
+    let first = stops[0];
+    if t <= first.0 {
+        return first.1;
+    }
+    for pair in stops.windows(2) {
+        let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+        if t <= t1 {
+            if t1 <= t0 {
+                return c1;
+            }
+            let f = (t - t0) / (t1 - t0);
+            return [
+                c0[0] + (c1[0] - c0[0]) * f,
+                c0[1] + (c1[1] - c0[1]) * f,
+                c0[2] + (c1[2] - c0[2]) * f,
+                c0[3] + (c1[3] - c0[3]) * f,
+            ];
+        }
+    }
+    stops[stops.len() - 1].1
+}
+
+#[inline]
+pub(crate) fn premultiply(r: f32, g: f32, b: f32, a: f32) -> [f32; 4] {
+    let a = a.clamp(0.0, 1.0);
+    [
+        r.clamp(0.0, 1.0) * a,
+        g.clamp(0.0, 1.0) * a,
+        b.clamp(0.0, 1.0) * a,
+        a,
+    ]
+}
+
+/// Premultiplied color of straight sRGB components, converted to the blend space
+#[inline]
+pub(crate) fn premultiply_in(space: BlendSpace, r: f32, g: f32, b: f32, a: f32) -> [f32; 4] {
+    match space {
+        BlendSpace::Srgb => premultiply(r, g, b, a),
+        BlendSpace::Linear => {
+            premultiply(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Converts polylines into fillable polygons that cover their outline
+
+use crate::path::Polyline;
+use crate::soft::arc_segments;
+use crate::{LineCap, LineJoin, Rect, StrokeStyle};
+use std::f32::consts::{PI, SQRT_2};
+
+/// Builds the polygons covering a stroked polyline.
+/// All polygons have the same orientation, so they can be filled together with the non-zero rule.
+/// Dashes are only built inside of visible, the area of the canvas in user space
+pub(crate) fn stroke_polyline(
+    polyline: &Polyline,
+    style: &StrokeStyle,
+    tolerance: f32,
+    visible: &Rect,
+    out: &mut Vec<Vec<(f32, f32)>>,
+) {
+    if style.is_dashed() {
+        // Dashes cut outside of the area must not reach into it with their joins and caps
+        let margin = style.width.abs() / 2.0 * style.miter_limit.max(SQRT_2);
+        let visible = visible.inset(-margin, -margin);
+        let mut dashes = Vec::new();
+        dash_polyline(
+            polyline,
+            &style.dash,
+            style.dash_offset,
+            &visible,
+            &mut dashes,
+        );
+        for dash in &dashes {
+            stroke_solid(dash, style, tolerance, out);
+        }
+    } else {
+        stroke_solid(polyline, style, tolerance, out);
+    }
+}
+
+/// Position in a dash pattern.
+/// Distances are summed in f64, as f32 stops advancing on outlines millions of pixels long
+struct Dasher {
+    pattern: Vec<f64>,
+    total: f64,
+    index: usize,
+    /// Length left in the current dash or gap
+    remaining: f64,
+}
+
+impl Dasher {
+    fn new(dash: &[f32], offset: f32) -> Self {
+        // An odd pattern is repeated, so that dashes and gaps alternate
+        let pattern: Vec<f64> = if dash.len() % 2 == 1 {
+            dash.iter().chain(dash.iter()).map(|&d| d as f64).collect()
+        } else {
+            dash.iter().map(|&d| d as f64).collect()
+        };
+        let mut dasher = Self {
+            total: pattern.iter().sum(),
+            remaining: pattern[0],
+            pattern,
+            index: 0,
+        };
+        dasher.advance((offset as f64).rem_euclid(dasher.total));
+        dasher
+    }
+
+    /// Returns true in a dash, false in a gap
+    #[inline]
+    fn on(&self) -> bool {
+        self.index.is_multiple_of(2)
+    }
+
+    /// Moves to the next dash or gap
+    #[inline]
+    fn next(&mut self) {
+        self.index = (self.index + 1) % self.pattern.len();
+        self.remaining = self.pattern[self.index];
+    }
+
+    /// Moves along the pattern without building dashes, e.g. over parts that are not visible
+    fn advance(&mut self, mut distance: f64) {
+        if distance >= self.remaining {
+            // Whole repetitions of the pattern lead back to the same place in it
+            distance = self.remaining + (distance - self.remaining).rem_euclid(self.total);
+        }
+        while distance > 0.0 {
+            if distance >= self.remaining {
+                distance -= self.remaining;
+                self.next();
+            } else {
+                self.remaining -= distance;
+                distance = 0.0;
+            }
+        }
+    }
+}
+
+/// Splits a polyline into the open polylines of its dashes.
+/// Parts of the outline outside of visible are left out, cutting the dashes crossing its edges.
+/// The dash pattern must be valid (see StrokeStyle::is_dashed())
+pub(crate) fn dash_polyline(
+    polyline: &Polyline,
+    dash: &[f32],
+    offset: f32,
+    visible: &Rect,
+    out: &mut Vec<Polyline>,
+) {
+    let mut dasher = Dasher::new(dash, offset);
+
+    let mut points = polyline.points.clone();
+    if polyline.closed {
+        if let Some(&first) = points.first() {
+            points.push(first);
+        }
+    }
+    if points.is_empty() {
+        return;
+    }
+
+    let first = out.len();
+    let starts_on = dasher.on();
+    let mut broken = false;
+    let mut current = Vec::new();
+
+    // Ends the current dash where the outline leaves the visible area
+    let cut = |current: &mut Vec<(f32, f32)>, out: &mut Vec<Polyline>| {
+        if !current.is_empty() {
+            out.push(Polyline {
+                points: std::mem::take(current),
+                closed: false,
+            });
+        }
+    };
+
+    for window in points.windows(2) {
+        let (a, b) = (window[0], window[1]);
+        let (dx, dy) = (b.0 as f64 - a.0 as f64, b.1 as f64 - a.1 as f64);
+        let length = (dx * dx + dy * dy).sqrt();
+        let clipped = match length.is_finite() {
+            true => clip_segment(a, (dx, dy), visible),
+            false => None,
+        };
+        let Some((start, end)) = clipped else {
+            cut(&mut current, out);
+            broken = true;
+            if length.is_finite() {
+                dasher.advance(length);
+            }
+            continue;
+        };
+        let (start, end) = (start * length, end * length);
+        let point = |position: f64| {
+            let t = position / length;
+            ((a.0 as f64 + dx * t) as f32, (a.1 as f64 + dy * t) as f32)
+        };
+
+        if start > 0.0 {
+            cut(&mut current, out);
+            broken = true;
+            dasher.advance(start);
+        }
+        if dasher.on() && current.is_empty() {
+            current.push(if start > 0.0 { point(start) } else { a });
+        }
+        let mut position = start;
+        while end - position > dasher.remaining {
+            position += dasher.remaining;
+            // The point either ends the current dash or starts the next one
+            current.push(point(position));
+            if dasher.on() {
+                out.push(Polyline {
+                    points: std::mem::take(&mut current),
+                    closed: false,
+                });
+            }
+            broken = true;
+            dasher.next();
+        }
+        dasher.remaining -= end - position;
+        if end < length {
+            if dasher.on() {
+                current.push(point(end));
+            }
+            cut(&mut current, out);
+            broken = true;
+            dasher.advance(length - end);
+        } else if dasher.on() {
+            current.push(b);
+        }
+    }
+
+    if !broken {
+        // The whole outline fits in the first dash
+        if dasher.on() {
+            out.push(polyline.clone());
+        }
+        return;
+    }
+    if dasher.on() && !current.is_empty() {
+        if polyline.closed
+            && starts_on
+            && out.len() > first
+            && out[first].points.first() == Some(&points[0])
+        {
+            // The last dash runs into the first one across the start of the outline
+            let mut head = std::mem::take(&mut out[first].points);
+            current.extend(head.drain(1..));
+            out[first].points = current;
+        } else {
+            out.push(Polyline {
+                points: current,
+                closed: false,
+            });
+        }
+    }
+}
+
+/// Part of the segment from a along d that is inside of rect, as fractions of d (Liang-Barsky).
+/// Returns None if the segment misses rect
+fn clip_segment(a: (f32, f32), d: (f64, f64), rect: &Rect) -> Option<(f64, f64)> {
+    let (x, y) = (a.0 as f64, a.1 as f64);
+    let (mut start, mut end) = (0.0f64, 1.0f64);
+    let edges = [
+        (-d.0, x - rect.x as f64),
+        (d.0, rect.right() as f64 - x),
+        (-d.1, y - rect.y as f64),
+        (d.1, rect.bottom() as f64 - y),
+    ];
+    for (p, q) in edges {
+        if p == 0.0 {
+            // Parallel to the edge, and outside of it
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            start = start.max(q / p);
+        } else {
+            end = end.min(q / p);
+        }
+    }
+    (start <= end && end.is_finite()).then_some((start, end))
+}
+
+/// Builds the polygons covering a polyline without dashes
+fn stroke_solid(
+    polyline: &Polyline,
+    style: &StrokeStyle,
+    tolerance: f32,
+    out: &mut Vec<Vec<(f32, f32)>>,
+) {
+    let half = style.width / 2.0;
+    if half <= 0.0 {
+        return;
+    }
+
+    let mut points: Vec<(f32, f32)> = Vec::with_capacity(polyline.points.len());
+    for &p in &polyline.points {
+        if points.last() != Some(&p) {
+            points.push(p);
+        }
+    }
+    if polyline.closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.is_empty() {
+        return;
+    }
+    if points.len() == 1 {
+        // A sub-path without length only shows its caps, like cairo does
+        let p = points[0];
+        match style.line_cap {
+            LineCap::Butt => {}
+            LineCap::Round => push_polygon(out, circle(p, half, tolerance)),
+            LineCap::Square => push_polygon(
+                out,
+                vec![
+                    (p.0 - half, p.1 - half),
+                    (p.0 + half, p.1 - half),
+                    (p.0 + half, p.1 + half),
+                    (p.0 - half, p.1 + half),
+                ],
+            ),
+        }
+        return;
+    }
+
+    let count = points.len();
+    let segments = if polyline.closed { count } else { count - 1 };
+
+    for i in 0..segments {
+        let a = points[i];
+        let b = points[(i + 1) % count];
+        let n = normal(a, b, half);
+        push_polygon(
+            out,
+            vec![
+                (a.0 + n.0, a.1 + n.1),
+                (b.0 + n.0, b.1 + n.1),
+                (b.0 - n.0, b.1 - n.1),
+                (a.0 - n.0, a.1 - n.1),
+            ],
+        );
+    }
+
+    let joins = if polyline.closed {
+        0..count
+    } else {
+        1..count - 1
+    };
+    for i in joins {
+        let prev = points[(i + count - 1) % count];
+        let p = points[i];
+        let next = points[(i + 1) % count];
+        join(out, prev, p, next, style, tolerance);
+    }
+
+    if !polyline.closed {
+        cap(out, points[1], points[0], style, tolerance);
+        cap(out, points[count - 2], points[count - 1], style, tolerance);
+    }
+}
+
+/// Adds the polygon that fills the gap on the outer side of the corner at p
+fn join(
+    out: &mut Vec<Vec<(f32, f32)>>,
+    prev: (f32, f32),
+    p: (f32, f32),
+    next: (f32, f32),
+    style: &StrokeStyle,
+    tolerance: f32,
+) {
+    let half = style.width / 2.0;
+    if style.line_join == LineJoin::Round {
+        // The segments already cover the inner side, so a full circle adds exactly the round corner
+        push_polygon(out, circle(p, half, tolerance));
+        return;
+    }
+
+    let n0 = normal(prev, p, half);
+    let n1 = normal(p, next, half);
+    let d0 = (p.0 - prev.0, p.1 - prev.1);
+    let d1 = (next.0 - p.0, next.1 - p.1);
+    let cross = d0.0 * d1.1 - d0.1 * d1.0;
+    if cross == 0.0 {
+        return;
+    }
+    // The outer side is opposite to the direction of the turn
+    let (o0, o1) = if cross > 0.0 {
+        ((-n0.0, -n0.1), (-n1.0, -n1.1))
+    } else {
+        (n0, n1)
+    };
+    let a = (p.0 + o0.0, p.1 + o0.1);
+    let b = (p.0 + o1.0, p.1 + o1.1);
+
+    // The miter tip is where the two offset lines meet
+    let bisector = (o0.0 + o1.0, o0.1 + o1.1);
+    let bisector_len = (bisector.0 * bisector.0 + bisector.1 * bisector.1).sqrt();
+    let cos_half = bisector_len / (2.0 * half);
+    if style.line_join == LineJoin::Miter && cos_half > 0.0 && 1.0 / cos_half <= style.miter_limit {
+        let miter = half / cos_half;
+        let tip = (
+            p.0 + bisector.0 / bisector_len * miter,
+            p.1 + bisector.1 / bisector_len * miter,
+        );
+        push_polygon(out, vec![p, a, tip, b]);
+    } else {
+        push_polygon(out, vec![p, a, b]);
+    }
+}
+
+/// Adds the cap at the end point p of the segment coming from prev
+fn cap(
+    out: &mut Vec<Vec<(f32, f32)>>,
+    prev: (f32, f32),
+    p: (f32, f32),
+    style: &StrokeStyle,
+    tolerance: f32,
+) {
+    let half = style.width / 2.0;
+    match style.line_cap {
+        LineCap::Butt => {}
+        LineCap::Round => push_polygon(out, circle(p, half, tolerance)),
+        LineCap::Square => {
+            let n = normal(prev, p, half);
+            // Direction of the segment, scaled to half of the width
+            let d = (n.1, -n.0);
+            push_polygon(
+                out,
+                vec![
+                    (p.0 + n.0, p.1 + n.1),
+                    (p.0 + n.0 + d.0, p.1 + n.1 + d.1),
+                    (p.0 - n.0 + d.0, p.1 - n.1 + d.1),
+                    (p.0 - n.0, p.1 - n.1),
+                ],
+            );
+        }
+    }
+}
+
+/// Polygon approximating a circle within the tolerance
+fn circle(center: (f32, f32), radius: f32, tolerance: f32) -> Vec<(f32, f32)> {
+    let segments = arc_segments(radius, 2.0 * PI, tolerance).max(8);
+    (0..segments)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / segments as f32;
+            (
+                center.0 + radius * angle.cos(),
+                center.1 + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+/// Vector perpendicular to the segment a-b with the given length
+#[inline]
+fn normal(a: (f32, f32), b: (f32, f32), length: f32) -> (f32, f32) {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    (-dy / len * length, dx / len * length)
+}
+
+/// Adds a polygon, reversing it if needed so that every polygon has a positive signed area
+fn push_polygon(out: &mut Vec<Vec<(f32, f32)>>, mut polygon: Vec<(f32, f32)>) {
+    let mut area = 0.0;
+    for i in 0..polygon.len() {
+        let (x0, y0) = polygon[i];
+        let (x1, y1) = polygon[(i + 1) % polygon.len()];
+        area += x0 * y1 - x1 * y0;
+    }
+    if area < 0.0 {
+        polygon.reverse();
+    }
+    out.push(polygon);
+}
@@ -0,0 +1,117 @@
+//! Describes how outlines are drawn
+
+/// Shape used to join two segments of an outline
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// Sharp corner, beveled if it is longer than the miter limit
+    #[default]
+    Miter,
+    /// Rounded corner
+    Round,
+    /// Cut-off corner
+    Bevel,
+}
+
+/// Shape used at the ends of an open outline
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// The outline stops exactly at the end point
+    #[default]
+    Butt,
+    /// A half circle is added at the end point
+    Round,
+    /// A half square is added at the end point
+    Square,
+}
+
+/// # Stroke style
+/// Width, joins, caps and dashes of outlines drawn by Painter::line() and the stroke_* methods of Painter.
+/// The defaults are the same as cairo: 1px wide, miter joins with a miter limit of 10, butt caps and no dashes.
+/// ```
+/// use bly::{LineCap, LineJoin, StrokeStyle};
+///
+/// let style = StrokeStyle::new(4.0)
+///     .with_line_join(LineJoin::Round)
+///     .with_line_cap(LineCap::Square);
+///
+/// // 6px dashes separated by 3px gaps
+/// let dashed = StrokeStyle::new(1.0).with_dash(&[6.0, 3.0], 0.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeStyle {
+    /// Width of the outline in pixels
+    pub width: f32,
+    /// Shape of the corners
+    pub line_join: LineJoin,
+    /// Shape of the ends of open outlines
+    pub line_cap: LineCap,
+    /// Ratio of the miter length to the width above which a miter join is beveled
+    pub miter_limit: f32,
+    /// Lengths of the alternating dashes and gaps, starting with a dash.
+    /// An odd number of lengths is repeated to get an even one.
+    /// The outline is solid if this is empty, or if a length is negative or all of them are zero
+    pub dash: Vec<f32>,
+    /// Distance into the dash pattern at which the outline starts
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            line_join: LineJoin::Miter,
+            line_cap: LineCap::Butt,
+            miter_limit: 10.0,
+            dash: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    /// Creates a stroke style of the given width with the default joins and caps
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the shape of the corners
+    pub fn with_line_join(mut self, line_join: LineJoin) -> Self {
+        self.line_join = line_join;
+        self
+    }
+
+    /// Sets the shape of the ends of open outlines
+    pub fn with_line_cap(mut self, line_cap: LineCap) -> Self {
+        self.line_cap = line_cap;
+        self
+    }
+
+    /// Sets the miter limit
+    pub fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    /// Sets the dash pattern and the distance into it at which the outline starts
+    pub fn with_dash(mut self, dash: &[f32], offset: f32) -> Self {
+        self.dash = dash.to_vec();
+        self.dash_offset = offset;
+        self
+    }
+
+    /// Returns true if the dash pattern is usable, so that the outline has to be dashed
+    pub(crate) fn is_dashed(&self) -> bool {
+        !self.dash.is_empty()
+            && self.dash.iter().all(|d| d.is_finite() && *d >= 0.0)
+            && self.dash.iter().sum::<f32>() > 0.0
+    }
+}
+
+impl From<f32> for StrokeStyle {
+    fn from(width: f32) -> Self {
+        Self::new(width)
+    }
+}
@@ -0,0 +1,327 @@
+//! Fonts and text shaping
+
+use crate::{Error, Path, PathElement, Point};
+use rustybuzz::{Direction, UnicodeBuffer};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use unicode_bidi::BidiInfo;
+
+/// Font file shared by every clone of a Font
+struct FontData {
+    /// Identifies the font in the glyph caches of backends
+    id: u64,
+    bytes: Vec<u8>,
+    /// Index of the font in a font collection (TTC/OTC), 0 for single fonts
+    index: u32,
+    units_per_em: f32,
+    ascender: f32,
+    descender: f32,
+    line_gap: f32,
+    /// Outlines of the glyphs drawn so far in font units (y up), None for glyphs without outline
+    outlines: Mutex<HashMap<u16, Option<Arc<Path>>>>,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// # Font
+/// A TrueType or OpenType font used by Painter::draw_text().
+/// Cloning a font is cheap, the font file is shared.
+/// ```no_run
+/// let font = bly::Font::load("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf").unwrap();
+/// assert!(font.ascent(16.0) > 0.0);
+/// ```
+#[derive(Clone)]
+pub struct Font {
+    data: Arc<FontData>,
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font")
+            .field("id", &self.data.id)
+            .field("index", &self.data.index)
+            .field("units_per_em", &self.data.units_per_em)
+            .finish()
+    }
+}
+
+impl PartialEq for Font {
+    fn eq(&self, other: &Self) -> bool {
+        self.data.index == other.data.index && self.data.bytes == other.data.bytes
+    }
+}
+
+impl Font {
+    /// Loads a font from the contents of a TTF or OTF file
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Result<Self, Error> {
+        Self::from_collection(bytes, 0)
+    }
+
+    /// Loads the font at the given index of a font collection (TTC or OTC file)
+    pub fn from_collection(bytes: impl Into<Vec<u8>>, index: u32) -> Result<Self, Error> {
+        let bytes = bytes.into();
+        let (units_per_em, ascender, descender, line_gap) = {
+            let face = rustybuzz::ttf_parser::Face::parse(&bytes, index)
+                .map_err(|e| Error::FontLoadingFailed(e.to_string()))?;
+            (
+                face.units_per_em() as f32,
+                face.ascender() as f32,
+                face.descender() as f32,
+                face.line_gap() as f32,
+            )
+        };
+        Ok(Self {
+            data: Arc::new(FontData {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                bytes,
+                index,
+                units_per_em,
+                ascender,
+                descender,
+                line_gap,
+                outlines: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+
+    /// Reads a TTF or OTF file
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let bytes = std::fs::read(path).map_err(|e| Error::FontLoadingFailed(e.to_string()))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Distance from the baseline to the top of the tallest glyphs at the given size in pixels
+    #[inline]
+    pub fn ascent(&self, size: f32) -> f32 {
+        self.data.ascender * self.scale(size)
+    }
+
+    /// Distance from the baseline to the bottom of the lowest glyphs at the given size in pixels
+    #[inline]
+    pub fn descent(&self, size: f32) -> f32 {
+        -self.data.descender * self.scale(size)
+    }
+
+    /// Recommended gap between the descent of a line and the ascent of the next one
+    #[inline]
+    pub fn line_gap(&self, size: f32) -> f32 {
+        self.data.line_gap * self.scale(size)
+    }
+
+    /// Identifies the font in the glyph caches of backends
+    #[inline]
+    pub(crate) fn id(&self) -> u64 {
+        self.data.id
+    }
+
+    /// Factor from font units to pixels
+    #[inline]
+    pub(crate) fn scale(&self, size: f32) -> f32 {
+        size / self.data.units_per_em
+    }
+
+    fn face(&self) -> rustybuzz::Face<'_> {
+        rustybuzz::Face::from_slice(&self.data.bytes, self.data.index)
+            .expect("the font was validated when it was loaded")
+    }
+
+    /// Outline of a glyph in font units with the y axis pointing up.
+    /// Outlines are extracted once and kept for as long as the font exists
+    pub(crate) fn glyph_outline(&self, glyph: u16) -> Option<Arc<Path>> {
+        let mut outlines = self.data.outlines.lock().unwrap_or_else(|e| e.into_inner());
+        outlines
+            .entry(glyph)
+            .or_insert_with(|| {
+                let mut builder = OutlineBuilder(Path::new());
+                self.face()
+                    .outline_glyph(rustybuzz::ttf_parser::GlyphId(glyph), &mut builder)?;
+                Some(Arc::new(builder.0))
+            })
+            .clone()
+    }
+}
+
+struct OutlineBuilder(Path);
+
+impl rustybuzz::ttf_parser::OutlineBuilder for OutlineBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+/// A glyph placed by the shaper
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Glyph {
+    /// Index of the glyph in the font
+    pub id: u16,
+    /// Position of the glyph origin in pixels, relative to the start of the baseline (y down)
+    pub x: f32,
+    pub y: f32,
+    /// Distance from the origin of this glyph to the origin of the next one
+    pub advance: f32,
+    /// Byte offset of the first character of the text that the glyph belongs to
+    pub cluster: usize,
+}
+
+/// # Glyph run
+/// Text shaped into glyphs of a font at a given size.
+/// Shaping applies ligatures, kerning and the other features of the font, and lays out
+/// right-to-left scripts (Arabic, Hebrew, ...) in visual order.
+/// A run can be drawn many times with Painter::draw_glyph_run() without shaping the text again
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphRun {
+    font: Font,
+    size: f32,
+    glyphs: Vec<Glyph>,
+    advance: f32,
+}
+
+impl GlyphRun {
+    /// Shapes the text as a single line, line breaks are left out
+    pub fn new(text: &str, font: &Font, size: f32) -> Self {
+        let face = font.face();
+        let scale = font.scale(size);
+        let mut glyphs = Vec::new();
+        let mut x = 0.0;
+
+        let bidi = BidiInfo::new(text, None);
+        for paragraph in &bidi.paragraphs {
+            let mut line = paragraph.range.clone();
+            while text[line.clone()].ends_with(['\n', '\r', '\u{2029}']) {
+                line.end -= text[line.clone()]
+                    .chars()
+                    .next_back()
+                    .map_or(1, char::len_utf8);
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            // Runs come in visual order, and the shaper returns the glyphs of a run left to right
+            let (levels, runs) = bidi.visual_runs(paragraph, line);
+            for run in runs {
+                let mut buffer = UnicodeBuffer::new();
+                buffer.push_str(&text[run.clone()]);
+                buffer.set_direction(if levels[run.start].is_rtl() {
+                    Direction::RightToLeft
+                } else {
+                    Direction::LeftToRight
+                });
+                let output = rustybuzz::shape(&face, &[], buffer);
+                for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+                    glyphs.push(Glyph {
+                        id: info.glyph_id as u16,
+                        x: x + position.x_offset as f32 * scale,
+                        y: -position.y_offset as f32 * scale,
+                        advance: position.x_advance as f32 * scale,
+                        cluster: run.start + info.cluster as usize,
+                    });
+                    x += position.x_advance as f32 * scale;
+                }
+            }
+        }
+
+        Self {
+            font: font.clone(),
+            size,
+            glyphs,
+            advance: x,
+        }
+    }
+
+    /// Font of the glyphs
+    #[inline]
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    /// Size of the font in pixels
+    #[inline]
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Glyphs from left to right
+    #[inline]
+    pub fn glyphs(&self) -> &[Glyph] {
+        &self.glyphs
+    }
+
+    /// Width of the run in pixels
+    #[inline]
+    pub fn advance(&self) -> f32 {
+        self.advance
+    }
+
+    /// Same run with other glyphs, used by TextLayout to move shaped glyphs
+    pub(crate) fn with_glyphs(&self, glyphs: Vec<Glyph>, advance: f32) -> Self {
+        Self {
+            font: self.font.clone(),
+            size: self.size,
+            glyphs,
+            advance,
+        }
+    }
+
+    /// Outlines of all glyphs, with the baseline starting at origin.
+    /// Used by backends that draw glyphs as paths
+    pub(crate) fn to_path(&self, origin: Point) -> Path {
+        let mut path = Path::new();
+        for glyph in &self.glyphs {
+            if let Some(outline) = self.font.glyph_outline(glyph.id) {
+                append_outline(
+                    &mut path,
+                    &outline,
+                    self.font.scale(self.size),
+                    (origin.x + glyph.x, origin.y + glyph.y),
+                );
+            }
+        }
+        path
+    }
+}
+
+/// Appends an outline in font units to a path, scaled and with the y axis flipped
+pub(crate) fn append_outline(path: &mut Path, outline: &Path, scale: f32, origin: (f32, f32)) {
+    let map = |x: f32, y: f32| (origin.0 + x * scale, origin.1 - y * scale);
+    for element in outline.elements() {
+        match *element {
+            PathElement::MoveTo(x, y) => {
+                let (x, y) = map(x, y);
+                path.move_to(x, y);
+            }
+            PathElement::LineTo(x, y) => {
+                let (x, y) = map(x, y);
+                path.line_to(x, y);
+            }
+            PathElement::QuadTo(x1, y1, x, y) => {
+                let ((x1, y1), (x, y)) = (map(x1, y1), map(x, y));
+                path.quad_to(x1, y1, x, y);
+            }
+            PathElement::CubicTo(x1, y1, x2, y2, x, y) => {
+                let ((x1, y1), (x2, y2), (x, y)) = (map(x1, y1), map(x2, y2), map(x, y));
+                path.cubic_to(x1, y1, x2, y2, x, y);
+            }
+            PathElement::Close => {
+                path.close();
+            }
+        }
+    }
+}
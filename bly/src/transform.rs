@@ -0,0 +1,186 @@
+//! 2D affine transforms
+
+use crate::{Point, Rect, Vector};
+use std::ops::Mul;
+
+/// # 2D affine transform
+/// A 3x2 matrix in the layout of Direct2D's Matrix3x2F (row vectors).
+/// A point (x, y) is mapped to (x * m11 + y * m21 + m31, x * m12 + y * m22 + m32).
+///
+/// a.then(&b), also written a * b, first applies a and then b.
+/// ```
+/// use bly::{Point, Transform2D};
+///
+/// let transform = Transform2D::scale(2.0, 2.0).then(&Transform2D::translation(10.0, 0.0));
+/// assert_eq!(transform.transform_point(Point::new(1.0, 1.0)), Point::new(12.0, 2.0));
+///
+/// // Maps a point on the canvas back, e.g. for hit testing
+/// let inverse = transform.inverse().unwrap();
+/// assert_eq!(inverse.transform_point(Point::new(12.0, 2.0)), Point::new(1.0, 1.0));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform2D {
+    pub m11: f32,
+    pub m12: f32,
+    pub m21: f32,
+    pub m22: f32,
+    pub m31: f32,
+    pub m32: f32,
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform2D {
+    /// Creates a transform from the elements of the matrix
+    pub fn new(m11: f32, m12: f32, m21: f32, m22: f32, m31: f32, m32: f32) -> Self {
+        Self {
+            m11,
+            m12,
+            m21,
+            m22,
+            m31,
+            m32,
+        }
+    }
+
+    /// The transform that does not change anything
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    /// Moves by (x, y)
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// Rotates around the origin by an angle in radians, clockwise on the screen (the y axis points down)
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Scales by sx horizontally and sy vertically, around the origin
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self::new(sx, 0.0, 0.0, sy, 0.0, 0.0)
+    }
+
+    /// Skews by the angles in radians: x moves by tan(x_angle) * y, and y by tan(y_angle) * x
+    pub fn skew(x_angle: f32, y_angle: f32) -> Self {
+        Self::new(1.0, y_angle.tan(), x_angle.tan(), 1.0, 0.0, 0.0)
+    }
+
+    /// The transform that applies self first and then other
+    pub fn then(&self, other: &Transform2D) -> Self {
+        Self::new(
+            self.m11 * other.m11 + self.m12 * other.m21,
+            self.m11 * other.m12 + self.m12 * other.m22,
+            self.m21 * other.m11 + self.m22 * other.m21,
+            self.m21 * other.m12 + self.m22 * other.m22,
+            self.m31 * other.m11 + self.m32 * other.m21 + other.m31,
+            self.m31 * other.m12 + self.m32 * other.m22 + other.m32,
+        )
+    }
+
+    /// Determinant of the linear part. The transform cannot be inverted if it is 0
+    #[inline]
+    pub fn determinant(&self) -> f32 {
+        self.m11 * self.m22 - self.m12 * self.m21
+    }
+
+    /// The transform that undoes this one, None if it collapses the plane into a line or a point
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let inv = 1.0 / det;
+        Some(Self::new(
+            self.m22 * inv,
+            -self.m12 * inv,
+            -self.m21 * inv,
+            self.m11 * inv,
+            (self.m21 * self.m32 - self.m22 * self.m31) * inv,
+            (self.m12 * self.m31 - self.m11 * self.m32) * inv,
+        ))
+    }
+
+    /// Returns true if the transform does not change anything
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    /// Returns true if the transform only moves (no rotation, scale or skew)
+    #[inline]
+    pub fn is_translation(&self) -> bool {
+        self.m11 == 1.0 && self.m12 == 0.0 && self.m21 == 0.0 && self.m22 == 1.0
+    }
+
+    /// Maps a point
+    #[inline]
+    pub fn transform_point(&self, point: Point) -> Point {
+        let (x, y) = self.apply(point.x, point.y);
+        Point::new(x, y)
+    }
+
+    /// Maps a direction, ignoring the translation
+    #[inline]
+    pub fn transform_vector(&self, vector: Vector) -> Vector {
+        Vector::new(
+            vector.x * self.m11 + vector.y * self.m21,
+            vector.x * self.m12 + vector.y * self.m22,
+        )
+    }
+
+    /// Maps the point (x, y)
+    #[inline]
+    pub(crate) fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x * self.m11 + y * self.m21 + self.m31,
+            x * self.m12 + y * self.m22 + self.m32,
+        )
+    }
+
+    /// Bounding box of the points that the transform maps into rect,
+    /// None if the transform cannot be inverted
+    pub(crate) fn inverse_bounds(&self, rect: &Rect) -> Option<Rect> {
+        let inverse = self.inverse()?;
+        let corners = [
+            (rect.x, rect.y),
+            (rect.right(), rect.y),
+            (rect.right(), rect.bottom()),
+            (rect.x, rect.bottom()),
+        ]
+        .map(|(x, y)| inverse.apply(x, y));
+        let (mut min, mut max) = (corners[0], corners[0]);
+        for (x, y) in corners {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        Some(Rect::from_points(min, max))
+    }
+
+    /// Largest factor by which the transform stretches a distance
+    pub(crate) fn max_scale(&self) -> f32 {
+        // Square root of the largest eigenvalue of M^T M
+        let a = self.m11 * self.m11 + self.m12 * self.m12;
+        let b = self.m11 * self.m21 + self.m12 * self.m22;
+        let c = self.m21 * self.m21 + self.m22 * self.m22;
+        let half_trace = (a + c) / 2.0;
+        let root = (((a - c) / 2.0).powi(2) + b * b).sqrt();
+        (half_trace + root).sqrt()
+    }
+}
+
+impl Mul for Transform2D {
+    type Output = Transform2D;
+
+    /// Same as self.then(&other)
+    fn mul(self, other: Transform2D) -> Transform2D {
+        self.then(&other)
+    }
+}
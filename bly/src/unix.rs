@@ -1,18 +1,95 @@
-use crate::{Backend, Point2};
-use std::ffi::{c_ulong, c_void};
+use crate::{
+    Backend, BlendMode, BlendSpace, Error, GlyphRun, Image, Paint, Path, Point, Rect, StrokeStyle,
+    Transform2D,
+};
+#[cfg(feature = "xlib")]
+use std::ffi::c_ulong;
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex, Weak};
 
-#[doc(hidden)]
-pub(crate) fn create_xlib_backend(window: c_ulong) -> UnixBackend {
-    UnixBackend {
-        backend: Box::new(crate::cairo::create_xlib_backend(window)),
+/// A native resource shared by all canvases of the process, or of a thread when it is
+/// in a thread_local (e.g. a display connection).  
+/// The resource is created by the first canvas that needs it and
+/// released when the last canvas using it is dropped.
+pub(crate) struct Shared<T> {
+    slots: Mutex<Vec<Weak<T>>>,
+}
+
+// Only the Xlib and Wayland backends share resources; XCB connections belong to the application
+#[cfg_attr(not(any(feature = "xlib", feature = "wayland")), allow(dead_code))]
+impl<T> Shared<T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            slots: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the shared resource, creating it with f if no canvas currently holds it
+    #[cfg_attr(not(feature = "xlib"), allow(dead_code))]
+    pub(crate) fn get_or_try_init<F>(&self, f: F) -> Result<Arc<T>, Error>
+    where
+        F: FnOnce() -> Result<T, Error>,
+    {
+        self.find_or_try_init(|_| true, f)
+    }
+
+    /// Returns the shared resource for which matches() is true,
+    /// creating it with f if no canvas currently holds such a resource.  
+    /// Used when there is one resource per native object (e.g. per connection of the application)
+    pub(crate) fn find_or_try_init<M, F>(&self, matches: M, f: F) -> Result<Arc<T>, Error>
+    where
+        M: Fn(&T) -> bool,
+        F: FnOnce() -> Result<T, Error>,
+    {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        slots.retain(|slot| slot.strong_count() > 0);
+        if let Some(resource) = slots
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|resource| matches(resource))
+        {
+            return Ok(resource);
+        }
+        let resource = Arc::new(f()?);
+        slots.push(Arc::downgrade(&resource));
+        Ok(resource)
     }
 }
 
 #[doc(hidden)]
-pub(crate) fn create_wayland_backend(surface: *mut c_void) -> UnixBackend {
-    UnixBackend {
-        backend: Box::new(crate::wayland::create_wayland_backend(surface).unwrap()),
-    }
+#[cfg(feature = "xlib")]
+pub(crate) fn create_xlib_backend(
+    display: *mut c_void,
+    window: c_ulong,
+) -> Result<UnixBackend, Error> {
+    Ok(UnixBackend {
+        backend: Box::new(crate::cairo::create_xlib_backend(display, window)?),
+    })
+}
+
+#[doc(hidden)]
+#[cfg(feature = "xcb")]
+pub(crate) fn create_xcb_backend(
+    connection: *mut c_void,
+    window: u32,
+    visual_id: u32,
+) -> Result<UnixBackend, Error> {
+    Ok(UnixBackend {
+        backend: Box::new(crate::cairo::create_xcb_backend(
+            connection, window, visual_id,
+        )?),
+    })
+}
+
+#[doc(hidden)]
+#[cfg(feature = "wayland")]
+pub(crate) fn create_wayland_backend(
+    display: *mut c_void,
+    surface: *mut c_void,
+) -> Result<UnixBackend, Error> {
+    Ok(UnixBackend {
+        backend: crate::wayland::create_wayland_backend(display, surface)?,
+    })
 }
 
 #[doc(hidden)]
@@ -23,13 +100,13 @@ pub struct UnixBackend {
 
 impl Backend for UnixBackend {
     #[inline]
-    unsafe fn begin_draw(&mut self) {
-        self.backend.begin_draw();
+    unsafe fn begin_draw(&mut self) -> Result<(), Error> {
+        self.backend.begin_draw()
     }
 
     #[inline]
-    unsafe fn flush(&mut self) {
-        self.backend.flush();
+    unsafe fn flush(&mut self) -> Result<(), Error> {
+        self.backend.flush()
     }
 
     #[inline]
@@ -37,55 +114,131 @@ impl Backend for UnixBackend {
         self.backend.get_display_size()
     }
 
+    #[inline]
+    unsafe fn resize(&mut self, width: u32, height: u32) {
+        self.backend.resize(width, height)
+    }
+
+    #[inline]
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error> {
+        self.backend.set_blend_space(space)
+    }
+
     #[inline]
     unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
         self.backend.clear(r, g, b, a);
     }
 
     #[inline]
-    unsafe fn ellipse(&mut self, point: Point2<f32>, radius: f32, r: f32, g: f32, b: f32, a: f32) {
-        self.backend.ellipse(point, radius, r, g, b, a);
+    unsafe fn set_transform(&mut self, transform: &Transform2D) {
+        self.backend.set_transform(transform)
+    }
+
+    #[inline]
+    unsafe fn push_clip_rect(&mut self, rect: Rect) {
+        self.backend.push_clip_rect(rect)
+    }
+
+    #[inline]
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        self.backend.push_clip_rounded_rect(rect, radius)
+    }
+
+    #[inline]
+    unsafe fn push_clip_path(&mut self, path: &Path) {
+        self.backend.push_clip_path(path)
+    }
+
+    #[inline]
+    unsafe fn pop_clip(&mut self) {
+        self.backend.pop_clip()
     }
 
     #[inline]
-    unsafe fn rectangle(
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.backend.set_blend_mode(mode)
+    }
+
+    #[inline]
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        self.backend.push_layer(opacity, mode)
+    }
+
+    #[inline]
+    unsafe fn pop_layer(&mut self) {
+        self.backend.pop_layer()
+    }
+
+    #[inline]
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint) {
+        self.backend.ellipse(point, radius, paint);
+    }
+
+    #[inline]
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint) {
+        self.backend.rectangle(rect, paint);
+    }
+
+    #[inline]
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint) {
+        self.backend.rounded_rectangle(rect, radius, paint);
+    }
+
+    #[inline]
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint) {
+        self.backend.line(point1, point2, style, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_ellipse(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        point: Point,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
-        self.backend.rectangle(point1, point2, r, g, b, a);
+        self.backend.stroke_ellipse(point, radius, style, paint);
     }
 
     #[inline]
-    unsafe fn rounded_rectangle(
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint) {
+        self.backend.stroke_rectangle(rect, style, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_rounded_rectangle(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
+        rect: Rect,
         radius: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
         self.backend
-            .rounded_rectangle(point1, point2, radius, r, g, b, a);
+            .stroke_rounded_rectangle(rect, radius, style, paint);
     }
 
     #[inline]
-    unsafe fn line(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        stroke: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    ) {
-        self.backend.line(point1, point2, stroke, r, g, b, a);
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint) {
+        self.backend.fill_path(path, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint) {
+        self.backend.stroke_path(path, style, paint);
+    }
+
+    #[inline]
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        self.backend.draw_image(image, dest, src, opacity);
+    }
+
+    #[inline]
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint) {
+        self.backend.draw_glyphs(run, origin, paint)
+    }
+
+    #[inline]
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        self.backend.read_pixels()
     }
 }
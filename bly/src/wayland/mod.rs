@@ -1,29 +1,130 @@
-use crate::{Backend, Point2};
-use khronos_egl::Context;
+use crate::blend::srgb_only;
+use crate::path::Polyline;
+use crate::soft::{ellipse_points, rectangle_points, rounded_rectangle_points};
+use crate::unix::Shared;
+use crate::{
+    Backend, BlendMode, BlendSpace, Error, GlyphRun, Image, Paint, Path, Point, Rect, StrokeStyle,
+    Transform2D,
+};
 use std::ffi::c_void;
+use std::sync::Arc;
 use wayland_sys::client::*;
 extern crate khronos_egl as egl;
 use wayland_sys::client::WAYLAND_CLIENT_HANDLE;
 use wayland_sys::egl::WAYLAND_EGL_HANDLE;
 
+// Generated by gl_generator in build.rs
+#[allow(clippy::all)]
 mod gl {
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
+mod renderer;
+mod shm;
 
+use renderer::Renderer;
+
+impl From<egl::Error> for Error {
+    fn from(error: egl::Error) -> Self {
+        Error::EglError(error.to_string())
+    }
+}
+
+/// EGL display initialized on the Wayland connection of the application,
+/// shared by every Wayland canvas of that connection
+pub(crate) struct WaylandDisplay {
+    egl: egl::Instance<egl::Static>,
+    display: egl::Display,
+    wl_display: *mut wl_display,
+}
+
+// libwayland-client and EGL displays can be used from any thread
+unsafe impl Send for WaylandDisplay {}
+unsafe impl Sync for WaylandDisplay {}
+
+impl Drop for WaylandDisplay {
+    fn drop(&mut self) {
+        info!("Terminating the EGL display of {:?}", self.wl_display);
+        if let Err(e) = self.egl.terminate(self.display) {
+            error!("eglTerminate failed: {}", e);
+        }
+        // The connection itself belongs to the application
+    }
+}
+
+impl WaylandDisplay {
+    fn new(wl_display: *mut wl_display) -> Result<Self, Error> {
+        let egl = egl::Instance::new(egl::Static);
+        let display = egl
+            .get_display(wl_display as *mut c_void)
+            .ok_or_else(|| Error::EglError("no EGL display for the Wayland display".to_string()))?;
+        egl.initialize(display)?;
+        Ok(WaylandDisplay {
+            egl,
+            display,
+            wl_display,
+        })
+    }
+}
+
+static DISPLAY: Shared<WaylandDisplay> = Shared::new();
+
+fn open_display(wl_display: *mut wl_display) -> Result<Arc<WaylandDisplay>, Error> {
+    DISPLAY.find_or_try_init(
+        |shared| shared.wl_display == wl_display,
+        || WaylandDisplay::new(wl_display),
+    )
+}
+
+/// Creates a backend drawing on wl_surface, which must belong to the connection wl_display.  
+/// OpenGL ES is used through EGL when it works. Otherwise frames are drawn on the CPU
+/// and presented with wl_shm buffers, which can also be forced by setting BLY_WAYLAND_SHM
+/// (e.g. to work around a broken driver).  
+/// The surface is 1x1 pixels until it is resized with Backend::resize()
 #[doc(hidden)]
-pub fn create_wayland_backend(wl_surface: *mut c_void) -> Result<WaylandBackend, egl::Error> {
-    let wl_display = unsafe {
-        wayland_sys::ffi_dispatch!(
-            WAYLAND_CLIENT_HANDLE,
-            wl_display_connect,
-            ::std::ptr::null()
-        )
+pub fn create_wayland_backend(
+    wl_display: *mut c_void,
+    wl_surface: *mut c_void,
+) -> Result<Box<dyn Backend>, Error> {
+    if wl_display.is_null() || wl_surface.is_null() {
+        return Err(Error::SurfaceCreationFailed(
+            "the window handle has no Wayland display or surface".to_string(),
+        ));
+    }
+    if !wayland_sys::client::is_lib_available() {
+        error!("libwayland-client could not be loaded");
+        return Err(Error::DisplayConnectionFailed);
+    }
+    let egl = match std::env::var_os("BLY_WAYLAND_SHM") {
+        Some(_) => Err(Error::EglError("disabled by BLY_WAYLAND_SHM".to_string())),
+        None => create_egl_backend(wl_display as *mut wl_display, wl_surface),
     };
+    match egl {
+        Ok(backend) => {
+            info!("Drawing backend is EGL");
+            Ok(Box::new(backend))
+        }
+        Err(e) => {
+            warn!("EGL is not usable ({}), drawing on the CPU with wl_shm", e);
+            let backend =
+                shm::create_backend(wl_display as *mut wl_display, wl_surface as *mut wl_proxy)?;
+            Ok(Box::new(backend))
+        }
+    }
+}
+
+fn create_egl_backend(
+    wl_display: *mut wl_display,
+    wl_surface: *mut c_void,
+) -> Result<WaylandBackend, Error> {
+    if !wayland_sys::egl::is_lib_available() {
+        return Err(Error::EglError(
+            "libwayland-egl could not be loaded".to_string(),
+        ));
+    }
+    let shared = open_display(wl_display)?;
     let egl = egl::Instance::new(egl::Static);
-    let display = egl
-        .get_display(wl_display as *mut std::ffi::c_void)
-        .unwrap();
-    egl.initialize(display)?;
+    let display = shared.display;
+    let wl_display = shared.wl_display;
 
     let attributes = [
         egl::SURFACE_TYPE,
@@ -34,8 +135,8 @@ pub fn create_wayland_backend(wl_surface: *mut c_void) -> Result<WaylandBackend,
         8,
         egl::BLUE_SIZE,
         8,
-        egl::DEPTH_SIZE,
-        24,
+        egl::ALPHA_SIZE,
+        8,
         egl::RENDERABLE_TYPE,
         egl::OPENGL_ES2_BIT,
         egl::NONE,
@@ -43,7 +144,9 @@ pub fn create_wayland_backend(wl_surface: *mut c_void) -> Result<WaylandBackend,
 
     let config = egl
         .choose_first_config(display, &attributes)?
-        .expect("unable to find an appropriate EGL configuration");
+        .ok_or_else(|| {
+            Error::EglError("unable to find an appropriate EGL configuration".to_string())
+        })?;
 
     let context_attributes = [egl::CONTEXT_CLIENT_VERSION, 2, egl::NONE];
 
@@ -51,35 +154,51 @@ pub fn create_wayland_backend(wl_surface: *mut c_void) -> Result<WaylandBackend,
         wayland_sys::ffi_dispatch!(
             WAYLAND_EGL_HANDLE,
             wl_egl_window_create,
-            wl_display as *mut wl_proxy,
-            520,
-            520
+            wl_surface as *mut wl_proxy,
+            1,
+            1
         )
     };
+    if egl_window.is_null() {
+        return Err(Error::SurfaceCreationFailed(
+            "wl_egl_window_create failed".to_string(),
+        ));
+    }
 
-    let surface =
-        unsafe { egl.create_window_surface(display, config, egl_window as *mut c_void, None)? };
-
-    let context = egl.create_context(display, config, None, &context_attributes)?;
-
-    egl.make_current(display, Some(surface), Some(surface), Some(context))?;
-    let gl = gl::load_with(|s| egl.get_proc_address(s).unwrap() as *const _);
-
-    unsafe {
-        use std::ffi::CStr;
-        use std::os::raw::c_char;
+    let surface = match unsafe {
+        egl.create_window_surface(display, config, egl_window as *mut c_void, None)
+    } {
+        Ok(surface) => surface,
+        Err(e) => {
+            unsafe {
+                wayland_sys::ffi_dispatch!(WAYLAND_EGL_HANDLE, wl_egl_window_destroy, egl_window);
+            }
+            return Err(e.into());
+        }
+    };
 
-        let v: *const c_char = gl::GetString(gl::VERSION) as *const i8;
-        let c_str: &CStr = unsafe { CStr::from_ptr(v) };
-        let str_: &str = c_str.to_str().unwrap();
-        info!("Using {}", str_);
-    }
+    let context = match egl.create_context(display, config, None, &context_attributes) {
+        Ok(context) => context,
+        Err(e) => {
+            let _ = egl.destroy_surface(display, surface);
+            unsafe {
+                wayland_sys::ffi_dispatch!(WAYLAND_EGL_HANDLE, wl_egl_window_destroy, egl_window);
+            }
+            return Err(e.into());
+        }
+    };
 
+    // From here on, dropping the backend releases everything created above
     Ok(WaylandBackend {
         egl,
         surface,
+        context,
         display,
+        egl_window,
         wl_display,
+        size: (1, 1),
+        renderer: None,
+        _shared: shared,
     })
 }
 
@@ -87,80 +206,285 @@ pub fn create_wayland_backend(wl_surface: *mut c_void) -> Result<WaylandBackend,
 pub struct WaylandBackend {
     egl: egl::Instance<egl::Static>,
     surface: egl::Surface,
+    context: egl::Context,
     display: egl::Display,
+    egl_window: *mut wayland_sys::egl::wl_egl_window,
     wl_display: *mut wl_display,
+    /// Size of egl_window, which Wayland surfaces don't report by themselves
+    size: (u32, u32),
+    /// Created by the first frame, its objects are released with the context.
+    /// Making the context current allocates a buffer of egl_window,
+    /// so it waits until the application had a chance to resize the window
+    renderer: Option<Renderer>,
+    /// Keeps the shared Wayland and EGL displays alive while this canvas exists
+    _shared: Arc<WaylandDisplay>,
+}
+
+impl Drop for WaylandBackend {
+    fn drop(&mut self) {
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.destroy_surface(self.display, self.surface);
+        unsafe {
+            wayland_sys::ffi_dispatch!(WAYLAND_EGL_HANDLE, wl_egl_window_destroy, self.egl_window);
+        }
+    }
+}
+
+impl WaylandBackend {
+    #[inline]
+    fn renderer(&mut self) -> &mut Renderer {
+        self.renderer
+            .as_mut()
+            .expect("the renderer is created by the first frame")
+    }
+
+    /// Loads OpenGL ES and creates the renderer, with the context current
+    unsafe fn create_renderer(&self) -> Result<Renderer, Error> {
+        use std::ffi::CStr;
+        use std::os::raw::c_char;
+
+        gl::load_with(|s| {
+            self.egl
+                .get_proc_address(s)
+                .map_or(std::ptr::null(), |f| f as *const _)
+        });
+        let v: *const c_char = gl::GetString(gl::VERSION) as *const c_char;
+        if v.is_null() {
+            return Err(Error::ContextCreationFailed(
+                "OpenGL ES functions could not be loaded".to_string(),
+            ));
+        }
+        info!("Using {}", CStr::from_ptr(v).to_string_lossy());
+        Renderer::new()
+    }
 }
 
 impl Backend for WaylandBackend {
     #[inline]
-    unsafe fn begin_draw(&mut self) {
+    unsafe fn begin_draw(&mut self) -> Result<(), Error> {
         unsafe {
-            wayland_sys::ffi_dispatch!(
+            // Events are dispatched by the application, only check that the connection is alive
+            let result = wayland_sys::ffi_dispatch!(
                 WAYLAND_CLIENT_HANDLE,
-                wl_display_dispatch_pending,
+                wl_display_get_error,
                 self.wl_display
             );
-            gl::ClearColor(1.0, 1.0, 1.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+            if result != 0 {
+                return Err(Error::DisplayConnectionFailed);
+            }
+            // Every canvas has its own context, so make ours current before drawing
+            self.egl.make_current(
+                self.display,
+                Some(self.surface),
+                Some(self.surface),
+                Some(self.context),
+            )?;
+            if self.renderer.is_none() {
+                self.renderer = Some(self.create_renderer()?);
+            }
+            let (width, height) = self.size;
+            self.renderer().begin(width, height)
         }
     }
 
     #[inline]
-    unsafe fn flush(&mut self) {
-        self.egl.swap_buffers(self.display, self.surface);
+    unsafe fn flush(&mut self) -> Result<(), Error> {
+        self.renderer().present();
+        self.egl.swap_buffers(self.display, self.surface)?;
+        Ok(())
     }
 
     #[inline]
     unsafe fn get_display_size(&mut self) -> (u32, u32) {
-        todo!()
+        self.size
     }
 
     #[inline]
-    unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {}
+    unsafe fn resize(&mut self, width: u32, height: u32) {
+        let size = (width.max(1), height.max(1));
+        if size == self.size {
+            return;
+        }
+        // The next buffer of the surface gets the new size
+        wayland_sys::ffi_dispatch!(
+            WAYLAND_EGL_HANDLE,
+            wl_egl_window_resize,
+            self.egl_window,
+            size.0 as i32,
+            size.1 as i32,
+            0,
+            0
+        );
+        self.size = size;
+    }
 
     #[inline]
-    unsafe fn ellipse(&mut self, point: Point2<f32>, radius: f32, r: f32, g: f32, b: f32, a: f32) {
-        todo!()
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error> {
+        // The canvas is an 8 bits texture, which would band in linear light
+        srgb_only(space)
     }
 
     #[inline]
-    unsafe fn rectangle(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    ) {
-        //todo!()
+    unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.renderer().clear(r, g, b, a);
+    }
+
+    #[inline]
+    unsafe fn set_transform(&mut self, transform: &Transform2D) {
+        self.renderer().set_transform(transform);
+    }
+
+    #[inline]
+    unsafe fn push_clip_rect(&mut self, rect: Rect) {
+        self.renderer().push_clip(&[rectangle_points(&rect)]);
+    }
+
+    #[inline]
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        let renderer = self.renderer();
+        let points = rounded_rectangle_points(&rect, radius, renderer.tolerance());
+        renderer.push_clip(&[points]);
+    }
+
+    #[inline]
+    unsafe fn push_clip_path(&mut self, path: &Path) {
+        let renderer = self.renderer();
+        let polygons = flatten(path, renderer.tolerance());
+        renderer.push_clip(&polygons);
+    }
+
+    #[inline]
+    unsafe fn pop_clip(&mut self) {
+        self.renderer().pop_clip();
+    }
+
+    #[inline]
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.renderer().set_blend_mode(mode);
+    }
+
+    #[inline]
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        self.renderer().push_layer(opacity, mode);
+    }
+
+    #[inline]
+    unsafe fn pop_layer(&mut self) {
+        self.renderer().pop_layer();
+    }
+
+    #[inline]
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint) {
+        let renderer = self.renderer();
+        let points = ellipse_points(&point, radius, renderer.tolerance());
+        renderer.fill(&[points], paint);
     }
 
     #[inline]
-    unsafe fn rounded_rectangle(
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint) {
+        self.renderer().fill(&[rectangle_points(&rect)], paint);
+    }
+
+    #[inline]
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint) {
+        let renderer = self.renderer();
+        let points = rounded_rectangle_points(&rect, radius, renderer.tolerance());
+        renderer.fill(&[points], paint);
+    }
+
+    #[inline]
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint) {
+        let polyline = Polyline {
+            points: vec![point1.into(), point2.into()],
+            closed: false,
+        };
+        self.renderer().stroke(&[polyline], style, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_ellipse(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
+        point: Point,
         radius: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
-        todo!()
+        let renderer = self.renderer();
+        let polyline = Polyline {
+            points: ellipse_points(&point, radius, renderer.tolerance()),
+            closed: true,
+        };
+        renderer.stroke(&[polyline], style, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint) {
+        let polyline = Polyline {
+            points: rectangle_points(&rect),
+            closed: true,
+        };
+        self.renderer().stroke(&[polyline], style, paint);
     }
 
     #[inline]
-    unsafe fn line(
+    unsafe fn stroke_rounded_rectangle(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        stroke: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        rect: Rect,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
-        todo!()
+        let renderer = self.renderer();
+        let polyline = Polyline {
+            points: rounded_rectangle_points(&rect, radius, renderer.tolerance()),
+            closed: true,
+        };
+        renderer.stroke(&[polyline], style, paint);
+    }
+
+    #[inline]
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint) {
+        let renderer = self.renderer();
+        let polygons = flatten(path, renderer.tolerance());
+        renderer.fill(&polygons, paint);
+    }
+
+    #[inline]
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint) {
+        let renderer = self.renderer();
+        let polylines = path.flatten(renderer.tolerance());
+        renderer.stroke(&polylines, style, paint);
     }
+
+    #[inline]
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        self.renderer().draw_image(image, dest, src, opacity);
+    }
+
+    #[inline]
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint) {
+        self.fill_path(&run.to_path(origin), paint);
+    }
+
+    #[inline]
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        self.egl
+            .make_current(
+                self.display,
+                Some(self.surface),
+                Some(self.surface),
+                Some(self.context),
+            )
+            .ok()?;
+        self.renderer.as_mut()?.read_pixels()
+    }
+}
+
+/// Polygons of the subpaths of a path, for filling
+fn flatten(path: &Path, tolerance: f32) -> Vec<Vec<(f32, f32)>> {
+    path.flatten(tolerance)
+        .into_iter()
+        .map(|polyline| polyline.points)
+        .collect()
 }
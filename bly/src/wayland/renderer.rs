@@ -0,0 +1,1111 @@
+//! OpenGL ES 2 renderer of the Wayland backend.
+//!
+//! Shapes are flattened into polygons on the CPU and mapped to pixels of the canvas.
+//! The winding numbers of the polygons are counted in the stencil buffer of a mask, by drawing them
+//! as triangle fans (stencil, then cover). This is repeated at 16 positions inside every pixel,
+//! adding up the coverage of the pixels in the mask, and the paint is then drawn through the mask.
+//!
+//! On the canvas and the layers, the highest stencil bit marks the pixels inside the clip
+//! and the lower 7 bits hold the winding numbers while a clip is pushed. Clips are not anti-aliased.
+//! The canvas is drawn into a framebuffer object, so that it can be read back and blended with
+//! at any time, and it is copied to the window surface when the frame is presented.
+
+use super::gl;
+use super::gl::types::{GLenum, GLint, GLsizei, GLuint};
+use crate::image::ImageData;
+use crate::path::Polyline;
+use crate::soft::shader::{interpolate, premultiply, Shader};
+use crate::soft::stroke::stroke_polyline;
+use crate::{
+    BlendMode, BlendSpace, Error, ExtendMode, Image, ImageFilter, Paint, Rect, StrokeStyle,
+    Transform2D,
+};
+use std::collections::HashMap;
+use std::ffi::{c_void, CString};
+use std::sync::{Arc, Weak};
+
+/// Maximum distance between a flattened curve and the real curve, in pixels of the canvas
+const TOLERANCE: f32 = 0.1;
+
+/// Stencil bit of the pixels inside the clip
+const CLIP_BIT: GLuint = 0x80;
+/// Stencil bits of the winding number
+const WINDING_BITS: GLuint = 0x7f;
+
+/// Number of colors in the lookup texture of a gradient
+const RAMP_SIZE: usize = 256;
+
+/// Number of positions inside a pixel at which shapes are sampled
+const SAMPLES: u32 = 16;
+
+const VERTEX_SHADER: &str = r#"
+attribute vec2 a_position;
+uniform vec2 u_viewport;
+// Moves the geometry so that another position inside the pixels is sampled
+uniform vec2 u_offset;
+
+void main() {
+    vec2 position = a_position + u_offset;
+    // The canvas has its origin at the top left and the y axis pointing down
+    gl_Position = vec4(
+        position.x / u_viewport.x * 2.0 - 1.0,
+        1.0 - position.y / u_viewport.y * 2.0,
+        0.0,
+        1.0
+    );
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+precision highp float;
+#else
+precision mediump float;
+#endif
+
+// Kinds of paint
+const int SOLID = 0;
+const int LINEAR = 1;
+const int RADIAL = 2;
+const int IMAGE = 3;
+const int TEXTURE = 4;
+
+uniform int u_kind;
+uniform vec4 u_color;
+// Map pixels of the canvas to the coordinates of the paint
+uniform vec3 u_inverse_x;
+uniform vec3 u_inverse_y;
+// Start and direction of a linear gradient, or center and radius of a radial one
+uniform vec4 u_gradient;
+// 0: pad, 1: repeat, 2: reflect
+uniform int u_extend;
+// Gradient ramp, image or layer
+uniform sampler2D u_texture;
+uniform vec2 u_texture_size;
+// Maps the paint coordinates to pixels of the image: scale, then offset
+uniform vec4 u_image;
+// Centers of the first and the last pixel of the image that may be sampled
+uniform vec4 u_bounds;
+uniform float u_opacity;
+// Blend mode done here instead of by the blending of OpenGL, 0 if none
+uniform int u_blend;
+// Copy of the canvas under the shape, for the blend modes
+uniform sampler2D u_backdrop;
+// Whether the coverage of the pixels is read from the mask
+uniform int u_masked;
+uniform sampler2D u_mask;
+uniform vec2 u_viewport;
+
+float extend(float t) {
+    if (u_extend == 1) {
+        return fract(t);
+    }
+    if (u_extend == 2) {
+        float r = mod(t, 2.0);
+        return r > 1.0 ? 2.0 - r : r;
+    }
+    return clamp(t, 0.0, 1.0);
+}
+
+vec4 ramp(float t) {
+    return texture2D(u_texture, vec2((extend(t) * 255.0 + 0.5) / 256.0, 0.5));
+}
+
+// Premultiplied color of the paint at the current pixel
+vec4 paint() {
+    vec3 pixel = vec3(gl_FragCoord.x, u_viewport.y - gl_FragCoord.y, 1.0);
+    vec2 point = vec2(dot(u_inverse_x, pixel), dot(u_inverse_y, pixel));
+    if (u_kind == LINEAR) {
+        return ramp(dot(point - u_gradient.xy, u_gradient.zw));
+    }
+    if (u_kind == RADIAL) {
+        return ramp(length(point - u_gradient.xy) / u_gradient.z);
+    }
+    if (u_kind == IMAGE) {
+        vec2 texel = clamp(point * u_image.xy + u_image.zw, u_bounds.xy, u_bounds.zw);
+        return texture2D(u_texture, texel / u_texture_size) * u_opacity;
+    }
+    if (u_kind == TEXTURE) {
+        return texture2D(u_texture, gl_FragCoord.xy / u_viewport) * u_opacity;
+    }
+    return u_color;
+}
+
+float screen(float cb, float cs) {
+    return cb + cs - cb * cs;
+}
+
+float hard_light(float cb, float cs) {
+    return cs <= 0.5 ? cb * 2.0 * cs : screen(cb, 2.0 * cs - 1.0);
+}
+
+// Blended straight color of the backdrop cb and the source cs
+float blend(float cb, float cs) {
+    if (u_blend == 1) {
+        return cb * cs;
+    }
+    if (u_blend == 2) {
+        return screen(cb, cs);
+    }
+    if (u_blend == 3) {
+        return hard_light(cs, cb);
+    }
+    if (u_blend == 4) {
+        return min(cb, cs);
+    }
+    if (u_blend == 5) {
+        return max(cb, cs);
+    }
+    if (u_blend == 6) {
+        if (cb <= 0.0) {
+            return 0.0;
+        }
+        return cs >= 1.0 ? 1.0 : min(cb / (1.0 - cs), 1.0);
+    }
+    if (u_blend == 7) {
+        if (cb >= 1.0) {
+            return 1.0;
+        }
+        return cs <= 0.0 ? 0.0 : 1.0 - min((1.0 - cb) / cs, 1.0);
+    }
+    if (u_blend == 8) {
+        return hard_light(cb, cs);
+    }
+    if (u_blend == 9) {
+        if (cs <= 0.5) {
+            return cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb);
+        }
+        float d = cb <= 0.25 ? ((16.0 * cb - 12.0) * cb + 4.0) * cb : sqrt(cb);
+        return cb + (2.0 * cs - 1.0) * (d - cb);
+    }
+    if (u_blend == 10) {
+        return abs(cb - cs);
+    }
+    if (u_blend == 11) {
+        return cb + cs - 2.0 * cb * cs;
+    }
+    return cs;
+}
+
+void main() {
+    vec4 src = paint();
+    if (u_masked != 0) {
+        src *= texture2D(u_mask, gl_FragCoord.xy / u_viewport).a;
+    }
+    if (u_blend != 0) {
+        vec4 dst = texture2D(u_backdrop, gl_FragCoord.xy / u_viewport);
+        vec3 cs = src.a > 0.0 ? src.rgb / src.a : vec3(0.0);
+        vec3 cb = dst.a > 0.0 ? dst.rgb / dst.a : vec3(0.0);
+        vec3 mixed = vec3(blend(cb.r, cs.r), blend(cb.g, cs.g), blend(cb.b, cs.b));
+        src = vec4(
+            src.rgb * (1.0 - dst.a) + dst.rgb * (1.0 - src.a) + src.a * dst.a * mixed,
+            src.a + dst.a - src.a * dst.a
+        );
+    }
+    gl_FragColor = src;
+}
+"#;
+
+/// Kinds of paint of the fragment shader
+const SOLID: GLint = 0;
+const LINEAR: GLint = 1;
+const RADIAL: GLint = 2;
+const IMAGE: GLint = 3;
+const TEXTURE: GLint = 4;
+
+/// Locations of the uniforms of the program
+struct Uniforms {
+    viewport: GLint,
+    offset: GLint,
+    kind: GLint,
+    color: GLint,
+    inverse_x: GLint,
+    inverse_y: GLint,
+    gradient: GLint,
+    extend: GLint,
+    texture: GLint,
+    texture_size: GLint,
+    image: GLint,
+    bounds: GLint,
+    opacity: GLint,
+    blend: GLint,
+    backdrop: GLint,
+    masked: GLint,
+    mask: GLint,
+}
+
+/// A framebuffer object that is drawn into: the canvas, a layer or the mask
+struct Target {
+    framebuffer: GLuint,
+    texture: GLuint,
+    stencil: GLuint,
+    /// Pushed clips in pixels of the canvas, each one is intersected with the previous ones
+    clips: Vec<Vec<Vec<(f32, f32)>>>,
+    opacity: f32,
+    blend: BlendMode,
+}
+
+impl Target {
+    unsafe fn new(width: u32, height: u32, opacity: f32, blend: BlendMode) -> Result<Self, Error> {
+        let texture = create_texture(width, height, std::ptr::null());
+        let mut stencil = 0;
+        gl::GenRenderbuffers(1, &mut stencil);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, stencil);
+        gl::RenderbufferStorage(
+            gl::RENDERBUFFER,
+            gl::STENCIL_INDEX8,
+            width as GLsizei,
+            height as GLsizei,
+        );
+        let mut framebuffer = 0;
+        gl::GenFramebuffers(1, &mut framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            texture,
+            0,
+        );
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::STENCIL_ATTACHMENT,
+            gl::RENDERBUFFER,
+            stencil,
+        );
+        let target = Self {
+            framebuffer,
+            texture,
+            stencil,
+            clips: Vec::new(),
+            opacity,
+            blend,
+        };
+        let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            target.delete();
+            return Err(Error::DrawingFailed(format!(
+                "incomplete framebuffer of {}x{} (status 0x{:x})",
+                width, height, status
+            )));
+        }
+        Ok(target)
+    }
+
+    unsafe fn delete(&self) {
+        gl::DeleteFramebuffers(1, &self.framebuffer);
+        gl::DeleteRenderbuffers(1, &self.stencil);
+        gl::DeleteTextures(1, &self.texture);
+    }
+}
+
+/// Draws with OpenGL ES 2 into the context current on the calling thread.
+/// The objects of the renderer belong to its context and are released with it
+pub(crate) struct Renderer {
+    program: GLuint,
+    uniforms: Uniforms,
+    buffer: GLuint,
+    /// Colors of the gradient being drawn
+    ramp: GLuint,
+    /// Copy of the canvas for the blend modes done by the fragment shader
+    backdrop: GLuint,
+    /// Coverage of the shape being filled, in the alpha of its texture
+    mask: Option<Target>,
+    width: u32,
+    height: u32,
+    transform: Transform2D,
+    /// TOLERANCE in user space, so that scaled up curves stay smooth
+    tolerance: f32,
+    blend: BlendMode,
+    /// The canvas, followed by the pushed layers. Drawing goes to the last one
+    targets: Vec<Target>,
+    /// Uploaded images, removed once every clone of the image is dropped
+    images: HashMap<u64, (Weak<ImageData>, GLuint)>,
+}
+
+impl Renderer {
+    /// Compiles the shaders in the current context
+    pub unsafe fn new() -> Result<Self, Error> {
+        let program = create_program()?;
+        let location = |name: &str| {
+            let name = CString::new(name).unwrap();
+            gl::GetUniformLocation(program, name.as_ptr())
+        };
+        let uniforms = Uniforms {
+            viewport: location("u_viewport"),
+            offset: location("u_offset"),
+            kind: location("u_kind"),
+            color: location("u_color"),
+            inverse_x: location("u_inverse_x"),
+            inverse_y: location("u_inverse_y"),
+            gradient: location("u_gradient"),
+            extend: location("u_extend"),
+            texture: location("u_texture"),
+            texture_size: location("u_texture_size"),
+            image: location("u_image"),
+            bounds: location("u_bounds"),
+            opacity: location("u_opacity"),
+            blend: location("u_blend"),
+            backdrop: location("u_backdrop"),
+            masked: location("u_masked"),
+            mask: location("u_mask"),
+        };
+        gl::UseProgram(program);
+        gl::Uniform1i(uniforms.texture, 0);
+        gl::Uniform1i(uniforms.backdrop, 1);
+        gl::Uniform1i(uniforms.mask, 2);
+
+        let mut buffer = 0;
+        gl::GenBuffers(1, &mut buffer);
+        gl::BindBuffer(gl::ARRAY_BUFFER, buffer);
+        gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+        gl::EnableVertexAttribArray(0);
+
+        Ok(Self {
+            program,
+            uniforms,
+            buffer,
+            ramp: create_texture(RAMP_SIZE as u32, 1, std::ptr::null()),
+            backdrop: 0,
+            mask: None,
+            width: 0,
+            height: 0,
+            transform: Transform2D::identity(),
+            tolerance: TOLERANCE,
+            blend: BlendMode::Normal,
+            targets: Vec::new(),
+            images: HashMap::new(),
+        })
+    }
+
+    /// Prepares a frame of the given size in pixels.
+    /// The canvas keeps its pixels from the last frame unless its size changed
+    pub unsafe fn begin(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        let (width, height) = (width.max(1), height.max(1));
+        // Layers left open by the last frame are dropped
+        for layer in self.targets.drain(1.min(self.targets.len())..) {
+            layer.delete();
+        }
+        if (width, height) != (self.width, self.height) || self.targets.is_empty() {
+            if let Some(canvas) = self.targets.pop() {
+                canvas.delete();
+            }
+            let canvas = Target::new(width, height, 1.0, BlendMode::Normal)?;
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            self.targets.push(canvas);
+            gl::DeleteTextures(1, &self.backdrop);
+            self.backdrop = create_texture(width, height, std::ptr::null());
+            if let Some(mask) = self.mask.take() {
+                mask.delete();
+            }
+            self.mask = Some(Target::new(width, height, 1.0, BlendMode::Normal)?);
+            self.width = width;
+            self.height = height;
+        }
+        self.images.retain(|_, (owner, texture)| {
+            let alive = owner.strong_count() > 0;
+            if !alive {
+                gl::DeleteTextures(1, texture);
+            }
+            alive
+        });
+
+        // Other canvases and toolkits may share the context
+        gl::UseProgram(self.program);
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.buffer);
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Disable(gl::SCISSOR_TEST);
+        gl::Disable(gl::CULL_FACE);
+        gl::Uniform2f(
+            self.uniforms.viewport,
+            self.width as f32,
+            self.height as f32,
+        );
+        gl::Uniform2f(self.uniforms.offset, 0.0, 0.0);
+        gl::Uniform1i(self.uniforms.masked, 0);
+        self.targets[0].clips.clear();
+        self.bind_target();
+        self.blend = BlendMode::Normal;
+        self.set_transform(&Transform2D::identity());
+        Ok(())
+    }
+
+    /// Copies the canvas to the window surface, which must be bound as the default framebuffer
+    pub unsafe fn present(&mut self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+        gl::Disable(gl::STENCIL_TEST);
+        gl::Disable(gl::BLEND);
+        self.set_texture_paint(self.targets[0].texture, 1.0);
+        gl::Uniform1i(self.uniforms.blend, 0);
+        self.draw_triangles(&quad(0.0, 0.0, self.width as f32, self.height as f32));
+    }
+
+    /// Maximum distance between a flattened curve and the real curve, in user space
+    #[inline]
+    pub fn tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    pub fn set_transform(&mut self, transform: &Transform2D) {
+        self.transform = *transform;
+        let scale = transform.max_scale();
+        self.tolerance = if scale > 0.0 && scale.is_finite() {
+            TOLERANCE / scale
+        } else {
+            TOLERANCE
+        };
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend = mode;
+    }
+
+    /// Fills the area inside the clip with a straight color, replacing what was drawn
+    pub unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        let [r, g, b, a] = premultiply(r, g, b, a);
+        if self.target().clips.is_empty() {
+            gl::ClearColor(r, g, b, a);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            return;
+        }
+        gl::Disable(gl::BLEND);
+        gl::Uniform1i(self.uniforms.blend, 0);
+        self.set_solid_paint([r, g, b, a]);
+        gl::StencilMask(0);
+        gl::StencilFunc(gl::EQUAL, CLIP_BIT as GLint, CLIP_BIT);
+        self.draw_triangles(&quad(0.0, 0.0, self.width as f32, self.height as f32));
+    }
+
+    /// Intersects the clip with polygons in user space
+    pub unsafe fn push_clip(&mut self, polygons: &[Vec<(f32, f32)>]) {
+        let polygons = self.to_canvas(polygons);
+        self.apply_clip(&polygons);
+        self.target_mut().clips.push(polygons);
+    }
+
+    pub unsafe fn pop_clip(&mut self) {
+        if self.target_mut().clips.pop().is_some() {
+            self.reset_clip();
+        }
+    }
+
+    /// Draws into a new layer until pop_layer(), which composites it over the canvas
+    pub unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        match Target::new(self.width, self.height, opacity, mode) {
+            Ok(mut layer) => {
+                layer.clips = self.target().clips.clone();
+                self.targets.push(layer);
+                self.bind_target();
+                gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+            // The layer is drawn directly on its parent instead
+            Err(e) => error!("Failed to create a layer: {}", e),
+        }
+    }
+
+    pub unsafe fn pop_layer(&mut self) {
+        if self.targets.len() < 2 {
+            return;
+        }
+        let layer = self.targets.pop().unwrap();
+        self.bind_target();
+        self.set_texture_paint(layer.texture, layer.opacity);
+        self.cover(
+            (0.0, 0.0, self.width as f32, self.height as f32),
+            layer.blend,
+            false,
+        );
+        layer.delete();
+    }
+
+    /// Fills polygons in user space with the non-zero rule
+    pub unsafe fn fill(&mut self, polygons: &[Vec<(f32, f32)>], paint: &Paint) {
+        let Some(bounds) = self.draw_mask(&self.to_canvas(polygons)) else {
+            return;
+        };
+        let shader = Shader::new(paint, BlendSpace::Srgb).transformed(&self.transform);
+        if self.set_paint(&shader) {
+            self.cover(bounds, self.blend, true);
+        }
+    }
+
+    /// Strokes polylines in user space, so that the transform also applies to the width
+    pub unsafe fn stroke(&mut self, polylines: &[Polyline], style: &StrokeStyle, paint: &Paint) {
+        let canvas = Rect::new(0.0, 0.0, self.width as f32, self.height as f32);
+        // A transform that cannot be inverted flattens everything, so nothing is visible
+        let Some(visible) = self.transform.inverse_bounds(&canvas) else {
+            return;
+        };
+        let mut polygons = Vec::new();
+        for polyline in polylines {
+            stroke_polyline(polyline, style, self.tolerance, &visible, &mut polygons);
+        }
+        self.fill(&polygons, paint);
+    }
+
+    /// Draws the src part of an image into dest, in user space
+    pub unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        if dest.width == 0.0 || dest.height == 0.0 {
+            return;
+        }
+        let polygons = self.to_canvas(&[rectangle(dest.x, dest.y, dest.width, dest.height)]);
+        let Some(bounds) = self.draw_mask(&polygons) else {
+            return;
+        };
+        let shader =
+            Shader::image(image, dest, src, opacity, BlendSpace::Srgb).transformed(&self.transform);
+        if self.set_paint(&shader) {
+            self.cover(bounds, self.blend, true);
+        }
+    }
+
+    /// Width, height and straight RGBA8 pixels of the canvas, row by row from the top left
+    pub unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        let canvas = self.targets.first()?;
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut pixels = vec![0u8; width * height * 4];
+        gl::BindFramebuffer(gl::FRAMEBUFFER, canvas.framebuffer);
+        gl::ReadPixels(
+            0,
+            0,
+            width as GLsizei,
+            height as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+        self.bind_target();
+
+        // OpenGL returns the bottom row first
+        let mut out = Vec::with_capacity(pixels.len());
+        for row in pixels.chunks_exact(width * 4).rev() {
+            for p in row.chunks_exact(4) {
+                let a = p[3] as u32;
+                if a == 0 {
+                    out.extend_from_slice(&[0, 0, 0, 0]);
+                    continue;
+                }
+                let unpremultiply = |c: u8| ((c as u32 * 255 + a / 2) / a).min(255) as u8;
+                out.extend_from_slice(&[
+                    unpremultiply(p[0]),
+                    unpremultiply(p[1]),
+                    unpremultiply(p[2]),
+                    p[3],
+                ]);
+            }
+        }
+        Some((self.width, self.height, out))
+    }
+
+    #[inline]
+    fn target(&self) -> &Target {
+        self.targets.last().expect("begin() creates the canvas")
+    }
+
+    #[inline]
+    fn target_mut(&mut self) -> &mut Target {
+        self.targets.last_mut().expect("begin() creates the canvas")
+    }
+
+    /// Binds the framebuffer of the last target and restores its clip
+    unsafe fn bind_target(&mut self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.target().framebuffer);
+        gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+        gl::Enable(gl::STENCIL_TEST);
+        self.reset_clip();
+    }
+
+    /// Rebuilds the clip of the target in the stencil buffer from its clip polygons
+    unsafe fn reset_clip(&mut self) {
+        gl::StencilMask(0xff);
+        gl::ClearStencil(CLIP_BIT as GLint);
+        gl::Clear(gl::STENCIL_BUFFER_BIT);
+        let clips = std::mem::take(&mut self.target_mut().clips);
+        for polygons in &clips {
+            self.apply_clip(polygons);
+        }
+        self.target_mut().clips = clips;
+    }
+
+    /// Removes the clip bit from the pixels outside polygons in pixels of the canvas
+    unsafe fn apply_clip(&mut self, polygons: &[Vec<(f32, f32)>]) {
+        gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        self.draw_winding(polygons);
+        // Clip bit and a winding number: 0x80, anything else: 0
+        gl::StencilMask(0xff);
+        gl::StencilFunc(gl::LESS, CLIP_BIT as GLint, 0xff);
+        gl::StencilOp(gl::ZERO, gl::ZERO, gl::REPLACE);
+        self.draw_triangles(&quad(0.0, 0.0, self.width as f32, self.height as f32));
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+    }
+
+    /// Adds the winding numbers of polygons in pixels of the canvas to the stencil buffer
+    unsafe fn draw_winding(&mut self, polygons: &[Vec<(f32, f32)>]) {
+        gl::StencilMask(WINDING_BITS);
+        set_winding_ops();
+        self.draw_triangles(&fans(polygons));
+    }
+
+    /// Draws the coverage of polygons in pixels of the canvas into the mask.
+    /// Returns the bounds of the polygons, None if there is nothing to fill
+    unsafe fn draw_mask(&mut self, polygons: &[Vec<(f32, f32)>]) -> Option<(f32, f32, f32, f32)> {
+        let (x0, y0, x1, y1) = bounds(polygons)?;
+        let (width, height) = (self.width as i32, self.height as i32);
+        let left = (x0.floor() as i32 - 1).clamp(0, width);
+        let right = (x1.ceil() as i32 + 1).clamp(0, width);
+        let top = (y0.floor() as i32 - 1).clamp(0, height);
+        let bottom = (y1.ceil() as i32 + 1).clamp(0, height);
+        if left >= right || top >= bottom {
+            return None;
+        }
+        let bounds = (left as f32, top as f32, right as f32, bottom as f32);
+
+        let mask = self.mask.as_ref().expect("begin() creates the mask");
+        gl::BindFramebuffer(gl::FRAMEBUFFER, mask.framebuffer);
+        gl::Enable(gl::SCISSOR_TEST);
+        gl::Scissor(left, height - bottom, right - left, bottom - top);
+        gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+        gl::ClearStencil(0);
+        gl::StencilMask(0xff);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+
+        // The fans are followed by a quad over the bounds
+        let mut vertices = fans(polygons);
+        let count = (vertices.len() / 2) as GLsizei;
+        vertices.extend(quad(bounds.0, bounds.1, bounds.2, bounds.3));
+        self.upload(&vertices);
+
+        let u = &self.uniforms;
+        gl::Uniform1i(u.kind, SOLID);
+        let weight = 1.0 / SAMPLES as f32;
+        gl::Uniform4f(u.color, weight, weight, weight, weight);
+        gl::Uniform1i(u.blend, 0);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::ONE, gl::ONE);
+        for i in 0..SAMPLES {
+            // Spread over the pixel like the queens of the eight queens puzzle
+            let x = (i as f32 + 0.5) / SAMPLES as f32 - 0.5;
+            let y = ((i.reverse_bits() >> (32 - SAMPLES.trailing_zeros())) as f32 + 0.5)
+                / SAMPLES as f32
+                - 0.5;
+            // Sampling at pixel center + (x, y) is the same as moving the polygons by (-x, -y)
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            gl::Uniform2f(u.offset, -x, -y);
+            set_winding_ops();
+            gl::DrawArrays(gl::TRIANGLES, 0, count);
+
+            // Counts the sample where the winding number is not zero, and resets it
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::Uniform2f(u.offset, 0.0, 0.0);
+            gl::StencilFunc(gl::NOTEQUAL, 0, 0xff);
+            gl::StencilOp(gl::ZERO, gl::ZERO, gl::ZERO);
+            gl::DrawArrays(gl::TRIANGLES, count, 6);
+        }
+        gl::Disable(gl::SCISSOR_TEST);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.target().framebuffer);
+        Some(bounds)
+    }
+
+    /// Draws the paint set up by set_paint() inside bounds in pixels of the canvas,
+    /// through the mask if masked
+    unsafe fn cover(&mut self, bounds: (f32, f32, f32, f32), blend: BlendMode, masked: bool) {
+        gl::StencilMask(0);
+        gl::StencilFunc(gl::EQUAL, CLIP_BIT as GLint, CLIP_BIT);
+        self.set_blend(blend, bounds);
+        if masked {
+            let mask = self.mask.as_ref().expect("begin() creates the mask");
+            gl::ActiveTexture(gl::TEXTURE2);
+            gl::BindTexture(gl::TEXTURE_2D, mask.texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::Uniform1i(self.uniforms.masked, 1);
+        }
+        self.draw_triangles(&quad(bounds.0, bounds.1, bounds.2, bounds.3));
+        gl::Uniform1i(self.uniforms.masked, 0);
+    }
+
+    /// Sets up how the paint is combined with the canvas inside bounds (in pixels of the canvas)
+    unsafe fn set_blend(&mut self, mode: BlendMode, bounds: (f32, f32, f32, f32)) {
+        match mode {
+            BlendMode::Normal => {
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                gl::Uniform1i(self.uniforms.blend, 0);
+            }
+            BlendMode::Add => {
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::ONE, gl::ONE);
+                gl::Uniform1i(self.uniforms.blend, 0);
+            }
+            _ => {
+                // The shader blends with a copy of the pixels under the shape
+                gl::Disable(gl::BLEND);
+                let (width, height) = (self.width as i32, self.height as i32);
+                let x0 = (bounds.0.floor() as i32).clamp(0, width);
+                let x1 = (bounds.2.ceil() as i32).clamp(0, width);
+                let y0 = (height - bounds.3.ceil() as i32).clamp(0, height);
+                let y1 = (height - bounds.1.floor() as i32).clamp(0, height);
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_2D, self.backdrop);
+                gl::CopyTexSubImage2D(gl::TEXTURE_2D, 0, x0, y0, x0, y0, x1 - x0, y1 - y0);
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::Uniform1i(self.uniforms.blend, blend_index(mode));
+            }
+        }
+    }
+
+    /// Sets up the uniforms of a shader. Returns false if nothing would be visible
+    unsafe fn set_paint(&mut self, shader: &Shader) -> bool {
+        let (inverse, shader) = match shader {
+            Shader::Transformed { inverse, shader } => (*inverse, &**shader),
+            shader => (Transform2D::identity(), shader),
+        };
+        let u = &self.uniforms;
+        gl::Uniform3f(u.inverse_x, inverse.m11, inverse.m21, inverse.m31);
+        gl::Uniform3f(u.inverse_y, inverse.m12, inverse.m22, inverse.m32);
+        match shader {
+            Shader::Solid(color) => {
+                self.set_solid_paint(*color);
+                color[3] > 0.0
+            }
+            Shader::Linear {
+                start,
+                direction,
+                stops,
+                extend,
+            } => {
+                gl::Uniform1i(u.kind, LINEAR);
+                gl::Uniform4f(u.gradient, start.0, start.1, direction.0, direction.1);
+                self.set_ramp(stops, extend_index(*extend));
+                true
+            }
+            Shader::Radial {
+                center,
+                radius,
+                stops,
+                extend,
+            } => {
+                gl::Uniform1i(u.kind, RADIAL);
+                gl::Uniform4f(u.gradient, center.0, center.1, *radius, 0.0);
+                self.set_ramp(stops, extend_index(*extend));
+                true
+            }
+            Shader::Image {
+                image,
+                scale,
+                offset,
+                min,
+                max,
+                opacity,
+                ..
+            } => {
+                let Some(texture) = self.image_texture(image) else {
+                    return false;
+                };
+                let u = &self.uniforms;
+                gl::Uniform1i(u.kind, IMAGE);
+                gl::Uniform4f(u.image, scale.0, scale.1, offset.0, offset.1);
+                gl::Uniform4f(
+                    u.bounds,
+                    min.0 as f32 + 0.5,
+                    min.1 as f32 + 0.5,
+                    max.0 as f32 + 0.5,
+                    max.1 as f32 + 0.5,
+                );
+                gl::Uniform2f(u.texture_size, image.width() as f32, image.height() as f32);
+                gl::Uniform1f(u.opacity, *opacity);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                let filter = match image.filter() {
+                    ImageFilter::Nearest => gl::NEAREST,
+                    ImageFilter::Bilinear => gl::LINEAR,
+                };
+                set_filter(filter);
+                true
+            }
+            // transformed() never nests shaders
+            Shader::Transformed { .. } => false,
+        }
+    }
+
+    unsafe fn set_solid_paint(&mut self, color: [f32; 4]) {
+        gl::Uniform1i(self.uniforms.kind, SOLID);
+        gl::Uniform4f(self.uniforms.color, color[0], color[1], color[2], color[3]);
+    }
+
+    /// Paints with a texture of the size of the canvas, such as a layer
+    unsafe fn set_texture_paint(&mut self, texture: GLuint, opacity: f32) {
+        gl::Uniform1i(self.uniforms.kind, TEXTURE);
+        gl::Uniform1f(self.uniforms.opacity, opacity);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+    }
+
+    /// Uploads the colors of a gradient into the ramp texture
+    unsafe fn set_ramp(&mut self, stops: &[(f32, [f32; 4])], extend: GLint) {
+        let mut colors = Vec::with_capacity(RAMP_SIZE * 4);
+        for i in 0..RAMP_SIZE {
+            let color = interpolate(stops, i as f32 / (RAMP_SIZE - 1) as f32);
+            colors.extend(
+                color
+                    .iter()
+                    .map(|c| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8),
+            );
+        }
+        gl::Uniform1i(self.uniforms.extend, extend);
+        gl::BindTexture(gl::TEXTURE_2D, self.ramp);
+        gl::TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            0,
+            0,
+            RAMP_SIZE as GLsizei,
+            1,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            colors.as_ptr() as *const c_void,
+        );
+    }
+
+    /// Texture of an image with premultiplied pixels, uploaded the first time it is drawn
+    unsafe fn image_texture(&mut self, image: &Image) -> Option<GLuint> {
+        if let Some((_, texture)) = self.images.get(&image.id()) {
+            return Some(*texture);
+        }
+        let mut max_size = 0;
+        gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size);
+        if image.width() > max_size as u32 || image.height() > max_size as u32 {
+            error!(
+                "Failed to upload an image of {}x{}, textures are limited to {}x{}",
+                image.width(),
+                image.height(),
+                max_size,
+                max_size
+            );
+            return None;
+        }
+        let pixels: Vec<u8> = image
+            .pixels()
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let premultiply = |c: u8| ((c as u32 * p[3] as u32 + 127) / 255) as u8;
+                [
+                    premultiply(p[0]),
+                    premultiply(p[1]),
+                    premultiply(p[2]),
+                    p[3],
+                ]
+            })
+            .collect();
+        let texture = create_texture(
+            image.width(),
+            image.height(),
+            pixels.as_ptr() as *const c_void,
+        );
+        self.images
+            .insert(image.id(), (Arc::downgrade(image.data()), texture));
+        Some(texture)
+    }
+
+    /// Maps polygons in user space to pixels of the canvas
+    fn to_canvas(&self, polygons: &[Vec<(f32, f32)>]) -> Vec<Vec<(f32, f32)>> {
+        polygons
+            .iter()
+            .map(|polygon| {
+                polygon
+                    .iter()
+                    .map(|&(x, y)| self.transform.apply(x, y))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Draws triangles of (x, y) vertices in pixels of the canvas
+    unsafe fn draw_triangles(&mut self, vertices: &[f32]) {
+        if vertices.is_empty() {
+            return;
+        }
+        self.upload(vertices);
+        gl::DrawArrays(gl::TRIANGLES, 0, (vertices.len() / 2) as GLsizei);
+    }
+
+    /// Replaces the vertices in the buffer
+    unsafe fn upload(&mut self, vertices: &[f32]) {
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            std::mem::size_of_val(vertices) as isize,
+            vertices.as_ptr() as *const c_void,
+            gl::STREAM_DRAW,
+        );
+    }
+}
+
+/// Value of u_extend
+fn extend_index(extend: ExtendMode) -> GLint {
+    match extend {
+        ExtendMode::Pad => 0,
+        ExtendMode::Repeat => 1,
+        ExtendMode::Reflect => 2,
+    }
+}
+
+/// Value of u_blend for the blend modes done by the fragment shader
+fn blend_index(mode: BlendMode) -> GLint {
+    match mode {
+        BlendMode::Normal | BlendMode::Add => 0,
+        BlendMode::Multiply => 1,
+        BlendMode::Screen => 2,
+        BlendMode::Overlay => 3,
+        BlendMode::Darken => 4,
+        BlendMode::Lighten => 5,
+        BlendMode::ColorDodge => 6,
+        BlendMode::ColorBurn => 7,
+        BlendMode::HardLight => 8,
+        BlendMode::SoftLight => 9,
+        BlendMode::Difference => 10,
+        BlendMode::Exclusion => 11,
+    }
+}
+
+/// Outline of the rectangle at (x, y) with the size (w, h)
+fn rectangle(x: f32, y: f32, w: f32, h: f32) -> Vec<(f32, f32)> {
+    vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)]
+}
+
+/// Two triangles covering the rectangle from (x0, y0) to (x1, y1)
+fn quad(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<f32> {
+    vec![x0, y0, x1, y0, x1, y1, x0, y0, x1, y1, x0, y1]
+}
+
+/// Triangle fans of polygons. Together their winding numbers are the ones of the polygons
+fn fans(polygons: &[Vec<(f32, f32)>]) -> Vec<f32> {
+    let mut vertices = Vec::new();
+    for polygon in polygons.iter().filter(|polygon| polygon.len() >= 3) {
+        let first = polygon[0];
+        for pair in polygon[1..].windows(2) {
+            for point in [first, pair[0], pair[1]] {
+                vertices.extend_from_slice(&[point.0, point.1]);
+            }
+        }
+    }
+    vertices
+}
+
+/// Stencil operations that count winding numbers: front faces increment and back faces decrement
+unsafe fn set_winding_ops() {
+    gl::StencilFunc(gl::ALWAYS, 0, 0xff);
+    gl::StencilOpSeparate(gl::FRONT, gl::KEEP, gl::KEEP, gl::INCR_WRAP);
+    gl::StencilOpSeparate(gl::BACK, gl::KEEP, gl::KEEP, gl::DECR_WRAP);
+}
+
+/// Bounding box of polygons, None if they are empty or not finite
+fn bounds(polygons: &[Vec<(f32, f32)>]) -> Option<(f32, f32, f32, f32)> {
+    let mut points = polygons.iter().filter(|p| p.len() >= 3).flatten();
+    let first = points.next()?;
+    let (x0, y0, x1, y1) = points.fold((first.0, first.1, first.0, first.1), |b, p| {
+        (b.0.min(p.0), b.1.min(p.1), b.2.max(p.0), b.3.max(p.1))
+    });
+    (x0.is_finite() && y0.is_finite() && x1.is_finite() && y1.is_finite())
+        .then_some((x0, y0, x1, y1))
+}
+
+/// Creates an RGBA texture sampled with bilinear filtering, pixels may be null
+unsafe fn create_texture(width: u32, height: u32, pixels: *const c_void) -> GLuint {
+    let mut texture = 0;
+    gl::GenTextures(1, &mut texture);
+    gl::BindTexture(gl::TEXTURE_2D, texture);
+    set_filter(gl::LINEAR);
+    gl::TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_WRAP_S,
+        gl::CLAMP_TO_EDGE as GLint,
+    );
+    gl::TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_WRAP_T,
+        gl::CLAMP_TO_EDGE as GLint,
+    );
+    gl::TexImage2D(
+        gl::TEXTURE_2D,
+        0,
+        gl::RGBA as GLint,
+        width as GLsizei,
+        height as GLsizei,
+        0,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels,
+    );
+    texture
+}
+
+/// Sets the filter of the bound texture
+unsafe fn set_filter(filter: GLenum) {
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as GLint);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as GLint);
+}
+
+unsafe fn compile_shader(kind: GLenum, source: &str) -> Result<GLuint, Error> {
+    let shader = gl::CreateShader(kind);
+    let source = CString::new(source).unwrap();
+    gl::ShaderSource(shader, 1, &source.as_ptr(), std::ptr::null());
+    gl::CompileShader(shader);
+    let mut status = 0;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+    if status == 0 {
+        let mut log = vec![0u8; 1024];
+        let mut length = 0;
+        gl::GetShaderInfoLog(
+            shader,
+            log.len() as GLsizei,
+            &mut length,
+            log.as_mut_ptr() as *mut _,
+        );
+        gl::DeleteShader(shader);
+        log.truncate(length.max(0) as usize);
+        return Err(Error::ContextCreationFailed(format!(
+            "shader compilation failed: {}",
+            String::from_utf8_lossy(&log)
+        )));
+    }
+    Ok(shader)
+}
+
+unsafe fn create_program() -> Result<GLuint, Error> {
+    let vertex = compile_shader(gl::VERTEX_SHADER, VERTEX_SHADER)?;
+    let fragment = match compile_shader(gl::FRAGMENT_SHADER, FRAGMENT_SHADER) {
+        Ok(fragment) => fragment,
+        Err(e) => {
+            gl::DeleteShader(vertex);
+            return Err(e);
+        }
+    };
+    let program = gl::CreateProgram();
+    gl::AttachShader(program, vertex);
+    gl::AttachShader(program, fragment);
+    let position = CString::new("a_position").unwrap();
+    gl::BindAttribLocation(program, 0, position.as_ptr());
+    gl::LinkProgram(program);
+    // The program keeps the shaders alive for as long as it needs them
+    gl::DeleteShader(vertex);
+    gl::DeleteShader(fragment);
+    let mut status = 0;
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+    if status == 0 {
+        let mut log = vec![0u8; 1024];
+        let mut length = 0;
+        gl::GetProgramInfoLog(
+            program,
+            log.len() as GLsizei,
+            &mut length,
+            log.as_mut_ptr() as *mut _,
+        );
+        gl::DeleteProgram(program);
+        log.truncate(length.max(0) as usize);
+        return Err(Error::ContextCreationFailed(format!(
+            "shader linking failed: {}",
+            String::from_utf8_lossy(&log)
+        )));
+    }
+    Ok(program)
+}
@@ -0,0 +1,809 @@
+//! Software presentation on Wayland, for systems without a working EGL.
+//! Frames are drawn by the software backend and copied into wl_shm buffers,
+//! which are attached to the surface of the window in turn
+
+use crate::soft::{self, SoftBackend};
+use crate::{
+    Backend, BlendMode, BlendSpace, Error, GlyphRun, Image, Paint, Path, Point, Rect, StrokeStyle,
+    Transform2D,
+};
+use std::cell::Cell;
+use std::env;
+use std::ffi::{c_char, c_void, CStr};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wayland_sys::client::*;
+use wayland_sys::common::{wl_argument, wl_interface, wl_message};
+
+/// wl_shm_format::argb8888, premultiplied and supported by every compositor
+const FORMAT_ARGB8888: u32 = 0;
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Creates a backend presenting on wl_surface through wl_shm buffers
+#[doc(hidden)]
+pub fn create_backend(
+    wl_display: *mut wl_display,
+    wl_surface: *mut wl_proxy,
+) -> Result<ShmBackend, Error> {
+    info!("ShmBackend is being created. {:?}", wl_surface);
+    unsafe {
+        // Our objects get their own queue, the application dispatches the default one
+        let queue =
+            wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_create_queue, wl_display);
+        if queue.is_null() {
+            return Err(Error::DisplayConnectionFailed);
+        }
+        let mut backend = ShmBackend {
+            soft: soft::create_backend(1, 1),
+            wl_display,
+            wl_surface,
+            queue,
+            shm: std::ptr::null_mut(),
+            buffers: Vec::new(),
+            frame: 0,
+            last: Vec::new(),
+            last_size: (0, 0),
+            last_damage: None,
+        };
+        backend.shm = backend.bind_shm()?;
+        Ok(backend)
+    }
+}
+
+#[doc(hidden)]
+pub struct ShmBackend {
+    /// Draws the frames on the CPU
+    soft: SoftBackend,
+    wl_display: *mut wl_display,
+    wl_surface: *mut wl_proxy,
+    queue: *mut wl_event_queue,
+    shm: *mut wl_proxy,
+    /// At most two buffers: the one the compositor shows and the one drawn next
+    buffers: Vec<Buffer>,
+    /// Number of the last presented frame
+    frame: u64,
+    /// Pixels of the last presented frame, compared with the next one to find its damage
+    last: Vec<u8>,
+    last_size: (usize, usize),
+    /// Area changed by the last presented frame, None if nothing changed
+    last_damage: Option<Damage>,
+}
+
+/// Changed rows and columns of a frame, ends excluded
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Damage {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+impl Damage {
+    fn union(self, other: Damage) -> Damage {
+        Damage {
+            x0: self.x0.min(other.x0),
+            y0: self.y0.min(other.y0),
+            x1: self.x1.max(other.x1),
+            y1: self.y1.max(other.y1),
+        }
+    }
+}
+
+/// A wl_buffer with its own pool, backed by an unlinked file
+struct Buffer {
+    file: File,
+    pool: *mut wl_proxy,
+    buffer: *mut wl_proxy,
+    width: usize,
+    height: usize,
+    /// False from the moment the buffer is attached until the compositor releases it
+    released: Box<Cell<bool>>,
+    /// Number of the frame the buffer holds, 0 if it was never drawn into
+    frame: u64,
+}
+
+impl Buffer {
+    /// Creates a buffer on the queue of shm
+    unsafe fn new(shm: *mut wl_proxy, width: usize, height: usize) -> Result<Buffer, Error> {
+        let stride = width * BYTES_PER_PIXEL;
+        let size = stride * height;
+        let file = create_file(size as u64).map_err(|e| {
+            Error::SurfaceCreationFailed(format!("failed to create the shm buffer: {}", e))
+        })?;
+
+        let mut args = [
+            wl_argument { n: 0 },
+            wl_argument {
+                h: file.as_raw_fd(),
+            },
+            wl_argument { i: size as i32 },
+        ];
+        let pool = wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_marshal_array_constructor,
+            shm,
+            WL_SHM_CREATE_POOL,
+            args.as_mut_ptr(),
+            &WL_SHM_POOL_INTERFACE
+        );
+        if pool.is_null() {
+            return Err(Error::SurfaceCreationFailed(
+                "wl_shm.create_pool failed".to_string(),
+            ));
+        }
+
+        let mut args = [
+            wl_argument { n: 0 },
+            wl_argument { i: 0 },
+            wl_argument { i: width as i32 },
+            wl_argument { i: height as i32 },
+            wl_argument { i: stride as i32 },
+            wl_argument { u: FORMAT_ARGB8888 },
+        ];
+        let buffer = wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_marshal_array_constructor,
+            pool,
+            WL_SHM_POOL_CREATE_BUFFER,
+            args.as_mut_ptr(),
+            &WL_BUFFER_INTERFACE
+        );
+        if buffer.is_null() {
+            destroy_proxy(pool, WL_SHM_POOL_DESTROY);
+            return Err(Error::SurfaceCreationFailed(
+                "wl_shm_pool.create_buffer failed".to_string(),
+            ));
+        }
+
+        let released = Box::new(Cell::new(true));
+        wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_add_listener,
+            buffer,
+            &BUFFER_LISTENER as *const BufferListener as *mut extern "C" fn(),
+            &*released as *const Cell<bool> as *mut c_void
+        );
+
+        Ok(Buffer {
+            file,
+            pool,
+            buffer,
+            width,
+            height,
+            released,
+            frame: 0,
+        })
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            destroy_proxy(self.buffer, WL_BUFFER_DESTROY);
+            destroy_proxy(self.pool, WL_SHM_POOL_DESTROY);
+        }
+    }
+}
+
+impl Drop for ShmBackend {
+    fn drop(&mut self) {
+        self.buffers.clear();
+        unsafe {
+            if !self.shm.is_null() {
+                wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, self.shm);
+            }
+            wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_flush, self.wl_display);
+            wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_event_queue_destroy, self.queue);
+        }
+    }
+}
+
+impl ShmBackend {
+    /// Binds wl_shm through a registry on our queue
+    unsafe fn bind_shm(&mut self) -> Result<*mut wl_proxy, Error> {
+        let wrapper = wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_create_wrapper,
+            self.wl_display as *mut wl_proxy
+        );
+        if wrapper.is_null() {
+            return Err(Error::DisplayConnectionFailed);
+        }
+        wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_set_queue,
+            wrapper,
+            self.queue
+        );
+        let mut args = [wl_argument { n: 0 }];
+        let registry = wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_marshal_array_constructor,
+            wrapper,
+            WL_DISPLAY_GET_REGISTRY,
+            args.as_mut_ptr(),
+            &WL_REGISTRY_INTERFACE
+        );
+        wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_wrapper_destroy, wrapper);
+        if registry.is_null() {
+            return Err(Error::DisplayConnectionFailed);
+        }
+
+        let mut name: Option<u32> = None;
+        wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_add_listener,
+            registry,
+            &REGISTRY_LISTENER as *const RegistryListener as *mut extern "C" fn(),
+            &mut name as *mut Option<u32> as *mut c_void
+        );
+        let result = self.roundtrip();
+        let shm = match (&result, name) {
+            (Ok(()), Some(name)) => {
+                let mut args = [
+                    wl_argument { u: name },
+                    wl_argument {
+                        s: WL_SHM_INTERFACE.name,
+                    },
+                    wl_argument { u: 1 },
+                    wl_argument { n: 0 },
+                ];
+                wayland_sys::ffi_dispatch!(
+                    WAYLAND_CLIENT_HANDLE,
+                    wl_proxy_marshal_array_constructor_versioned,
+                    registry,
+                    WL_REGISTRY_BIND,
+                    args.as_mut_ptr(),
+                    &WL_SHM_INTERFACE,
+                    1
+                )
+            }
+            _ => std::ptr::null_mut(),
+        };
+        // The registry is only needed to bind wl_shm, and name must not be used anymore
+        wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, registry);
+        result?;
+        if shm.is_null() {
+            return Err(Error::SurfaceCreationFailed(
+                "the compositor has no wl_shm".to_string(),
+            ));
+        }
+        Ok(shm)
+    }
+
+    unsafe fn roundtrip(&self) -> Result<(), Error> {
+        let result = wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_display_roundtrip_queue,
+            self.wl_display,
+            self.queue
+        );
+        if result < 0 {
+            return Err(Error::DisplayConnectionFailed);
+        }
+        Ok(())
+    }
+
+    /// Returns the index of a released buffer of the given size,
+    /// waiting for the compositor if both buffers are in use
+    unsafe fn acquire_buffer(&mut self, width: usize, height: usize) -> Result<usize, Error> {
+        self.buffers
+            .retain(|buffer| buffer.width == width && buffer.height == height);
+        loop {
+            let result = wayland_sys::ffi_dispatch!(
+                WAYLAND_CLIENT_HANDLE,
+                wl_display_dispatch_queue_pending,
+                self.wl_display,
+                self.queue
+            );
+            if result < 0 {
+                return Err(Error::DisplayConnectionFailed);
+            }
+            // The most recent frame needs the least copying
+            if let Some((index, _)) = self
+                .buffers
+                .iter()
+                .enumerate()
+                .filter(|(_, buffer)| buffer.released.get())
+                .max_by_key(|(_, buffer)| buffer.frame)
+            {
+                return Ok(index);
+            }
+            if self.buffers.len() < 2 {
+                self.buffers.push(Buffer::new(self.shm, width, height)?);
+                return Ok(self.buffers.len() - 1);
+            }
+            let result = wayland_sys::ffi_dispatch!(
+                WAYLAND_CLIENT_HANDLE,
+                wl_display_dispatch_queue,
+                self.wl_display,
+                self.queue
+            );
+            if result < 0 {
+                return Err(Error::DisplayConnectionFailed);
+            }
+        }
+    }
+
+    /// Copies the drawn frame into a buffer and attaches it to the surface
+    unsafe fn present(&mut self) -> Result<(), Error> {
+        let (width, height) = self.soft.get_display_size();
+        let (width, height) = (width as usize, height as usize);
+        let pixels = self.soft.to_argb8888();
+
+        let full = Damage {
+            x0: 0,
+            y0: 0,
+            x1: width,
+            y1: height,
+        };
+        let damage = if self.frame > 0 && self.last_size == (width, height) {
+            find_damage(&self.last, &pixels, width)
+        } else {
+            Some(full)
+        };
+
+        let index = self.acquire_buffer(width, height)?;
+        let buffer = &mut self.buffers[index];
+        // The buffer still holds an older frame, only the rows changed since then are copied
+        let stale = if buffer.frame == 0 {
+            Some(full)
+        } else if buffer.frame == self.frame {
+            damage
+        } else if buffer.frame + 1 == self.frame {
+            union(damage, self.last_damage)
+        } else {
+            Some(full)
+        };
+        if let Some(stale) = stale {
+            let stride = width * BYTES_PER_PIXEL;
+            let rows = &pixels[stale.y0 * stride..stale.y1 * stride];
+            buffer
+                .file
+                .write_all_at(rows, (stale.y0 * stride) as u64)
+                .map_err(|e| {
+                    Error::DrawingFailed(format!("failed to fill the shm buffer: {}", e))
+                })?;
+        }
+
+        let mut args = [
+            wl_argument {
+                o: buffer.buffer as *const c_void,
+            },
+            wl_argument { i: 0 },
+            wl_argument { i: 0 },
+        ];
+        wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_marshal_array,
+            self.wl_surface,
+            WL_SURFACE_ATTACH,
+            args.as_mut_ptr()
+        );
+        // A frame without changes is still committed, with the state of the application
+        if let Some(damage) = damage {
+            let mut args = [
+                wl_argument {
+                    i: damage.x0 as i32,
+                },
+                wl_argument {
+                    i: damage.y0 as i32,
+                },
+                wl_argument {
+                    i: (damage.x1 - damage.x0) as i32,
+                },
+                wl_argument {
+                    i: (damage.y1 - damage.y0) as i32,
+                },
+            ];
+            // Before damage_buffer() appeared in version 4, damage() used the same coordinates
+            let version = wayland_sys::ffi_dispatch!(
+                WAYLAND_CLIENT_HANDLE,
+                wl_proxy_get_version,
+                self.wl_surface
+            );
+            let opcode = if version >= 4 {
+                WL_SURFACE_DAMAGE_BUFFER
+            } else {
+                WL_SURFACE_DAMAGE
+            };
+            wayland_sys::ffi_dispatch!(
+                WAYLAND_CLIENT_HANDLE,
+                wl_proxy_marshal_array,
+                self.wl_surface,
+                opcode,
+                args.as_mut_ptr()
+            );
+        }
+        wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_proxy_marshal_array,
+            self.wl_surface,
+            WL_SURFACE_COMMIT,
+            std::ptr::null_mut()
+        );
+
+        self.frame += 1;
+        buffer.frame = self.frame;
+        buffer.released.set(false);
+        self.last = pixels;
+        self.last_size = (width, height);
+        self.last_damage = damage;
+
+        if wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_display_flush, self.wl_display) < 0
+        {
+            return Err(Error::DisplayConnectionFailed);
+        }
+        Ok(())
+    }
+}
+
+fn union(a: Option<Damage>, b: Option<Damage>) -> Option<Damage> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Bounding box of the pixels that differ between two frames of the same size
+fn find_damage(last: &[u8], pixels: &[u8], width: usize) -> Option<Damage> {
+    let stride = width * BYTES_PER_PIXEL;
+    let mut damage: Option<Damage> = None;
+    for (y, (a, b)) in last.chunks(stride).zip(pixels.chunks(stride)).enumerate() {
+        if a == b {
+            continue;
+        }
+        let differs = |x: &usize| {
+            let i = x * BYTES_PER_PIXEL;
+            a[i..i + BYTES_PER_PIXEL] != b[i..i + BYTES_PER_PIXEL]
+        };
+        let x0 = (0..width).find(differs).unwrap_or(0);
+        let x1 = (0..width).rev().find(differs).map_or(width, |x| x + 1);
+        let row = Damage {
+            x0,
+            y0: y,
+            x1,
+            y1: y + 1,
+        };
+        damage = Some(damage.map_or(row, |damage| damage.union(row)));
+    }
+    damage
+}
+
+/// Creates a file of the given size that only lives as long as its descriptors
+fn create_file(size: u64) -> io::Result<File> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    loop {
+        let path = dir.join(format!(
+            "bly-shm-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        fs::remove_file(&path)?;
+        file.set_len(size)?;
+        return Ok(file);
+    }
+}
+
+/// Sends the destructor request of a proxy and destroys it
+unsafe fn destroy_proxy(proxy: *mut wl_proxy, opcode: u32) {
+    wayland_sys::ffi_dispatch!(
+        WAYLAND_CLIENT_HANDLE,
+        wl_proxy_marshal_array,
+        proxy,
+        opcode,
+        std::ptr::null_mut()
+    );
+    wayland_sys::ffi_dispatch!(WAYLAND_CLIENT_HANDLE, wl_proxy_destroy, proxy);
+}
+
+impl Backend for ShmBackend {
+    #[inline]
+    unsafe fn begin_draw(&mut self) -> Result<(), Error> {
+        let result = wayland_sys::ffi_dispatch!(
+            WAYLAND_CLIENT_HANDLE,
+            wl_display_get_error,
+            self.wl_display
+        );
+        if result != 0 {
+            return Err(Error::DisplayConnectionFailed);
+        }
+        self.soft.begin_draw()
+    }
+
+    #[inline]
+    unsafe fn flush(&mut self) -> Result<(), Error> {
+        self.soft.flush()?;
+        self.present()
+    }
+
+    #[inline]
+    unsafe fn get_display_size(&mut self) -> (u32, u32) {
+        self.soft.get_display_size()
+    }
+
+    #[inline]
+    unsafe fn resize(&mut self, width: u32, height: u32) {
+        // The next frame is drawn from scratch, into buffers of the new size
+        self.soft.resize(width.max(1), height.max(1))
+    }
+
+    #[inline]
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error> {
+        self.soft.set_blend_space(space)
+    }
+
+    #[inline]
+    unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.soft.clear(r, g, b, a);
+    }
+
+    #[inline]
+    unsafe fn set_transform(&mut self, transform: &Transform2D) {
+        self.soft.set_transform(transform)
+    }
+
+    #[inline]
+    unsafe fn push_clip_rect(&mut self, rect: Rect) {
+        self.soft.push_clip_rect(rect)
+    }
+
+    #[inline]
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        self.soft.push_clip_rounded_rect(rect, radius)
+    }
+
+    #[inline]
+    unsafe fn push_clip_path(&mut self, path: &Path) {
+        self.soft.push_clip_path(path)
+    }
+
+    #[inline]
+    unsafe fn pop_clip(&mut self) {
+        self.soft.pop_clip()
+    }
+
+    #[inline]
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.soft.set_blend_mode(mode)
+    }
+
+    #[inline]
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        self.soft.push_layer(opacity, mode)
+    }
+
+    #[inline]
+    unsafe fn pop_layer(&mut self) {
+        self.soft.pop_layer()
+    }
+
+    #[inline]
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint) {
+        self.soft.ellipse(point, radius, paint)
+    }
+
+    #[inline]
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint) {
+        self.soft.rectangle(rect, paint)
+    }
+
+    #[inline]
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint) {
+        self.soft.rounded_rectangle(rect, radius, paint)
+    }
+
+    #[inline]
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint) {
+        self.soft.line(point1, point2, style, paint)
+    }
+
+    #[inline]
+    unsafe fn stroke_ellipse(
+        &mut self,
+        point: Point,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
+    ) {
+        self.soft.stroke_ellipse(point, radius, style, paint)
+    }
+
+    #[inline]
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint) {
+        self.soft.stroke_rectangle(rect, style, paint)
+    }
+
+    #[inline]
+    unsafe fn stroke_rounded_rectangle(
+        &mut self,
+        rect: Rect,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
+    ) {
+        self.soft
+            .stroke_rounded_rectangle(rect, radius, style, paint)
+    }
+
+    #[inline]
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint) {
+        self.soft.fill_path(path, paint)
+    }
+
+    #[inline]
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint) {
+        self.soft.stroke_path(path, style, paint)
+    }
+
+    #[inline]
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        self.soft.draw_image(image, dest, src, opacity)
+    }
+
+    #[inline]
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint) {
+        self.soft.draw_glyphs(run, origin, paint)
+    }
+
+    #[inline]
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        self.soft.read_pixels()
+    }
+}
+
+// Protocol objects used by the backend, as described by wayland.xml.
+// The surface is created by the application, its requests use the definitions of libwayland
+
+const WL_DISPLAY_GET_REGISTRY: u32 = 1;
+const WL_REGISTRY_BIND: u32 = 0;
+const WL_SHM_CREATE_POOL: u32 = 0;
+const WL_SHM_POOL_CREATE_BUFFER: u32 = 0;
+const WL_SHM_POOL_DESTROY: u32 = 1;
+const WL_BUFFER_DESTROY: u32 = 0;
+const WL_SURFACE_ATTACH: u32 = 1;
+const WL_SURFACE_DAMAGE: u32 = 2;
+const WL_SURFACE_COMMIT: u32 = 6;
+const WL_SURFACE_DAMAGE_BUFFER: u32 = 9;
+
+/// Static protocol tables, which only hold pointers to other static data
+#[repr(transparent)]
+struct Table<T>(T);
+
+unsafe impl<T> Sync for Table<T> {}
+
+/// A request or an event
+const fn message(
+    name: &'static CStr,
+    signature: &'static CStr,
+    types: &'static Table<[*const wl_interface; 6]>,
+) -> wl_message {
+    wl_message {
+        name: name.as_ptr(),
+        signature: signature.as_ptr(),
+        types: types.0.as_ptr(),
+    }
+}
+
+const NONE: *const wl_interface = std::ptr::null();
+
+/// Types of messages without object arguments
+static NO_TYPES: Table<[*const wl_interface; 6]> = Table([NONE; 6]);
+static CREATE_POOL_TYPES: Table<[*const wl_interface; 6]> =
+    Table([&WL_SHM_POOL_INTERFACE, NONE, NONE, NONE, NONE, NONE]);
+static CREATE_BUFFER_TYPES: Table<[*const wl_interface; 6]> =
+    Table([&WL_BUFFER_INTERFACE, NONE, NONE, NONE, NONE, NONE]);
+
+static WL_REGISTRY_REQUESTS: Table<[wl_message; 1]> = Table([message(c"bind", c"usun", &NO_TYPES)]);
+static WL_REGISTRY_EVENTS: Table<[wl_message; 2]> = Table([
+    message(c"global", c"usu", &NO_TYPES),
+    message(c"global_remove", c"u", &NO_TYPES),
+]);
+static WL_REGISTRY_INTERFACE: wl_interface = wl_interface {
+    name: c"wl_registry".as_ptr(),
+    version: 1,
+    request_count: 1,
+    requests: WL_REGISTRY_REQUESTS.0.as_ptr(),
+    event_count: 2,
+    events: WL_REGISTRY_EVENTS.0.as_ptr(),
+};
+
+static WL_SHM_REQUESTS: Table<[wl_message; 1]> =
+    Table([message(c"create_pool", c"nhi", &CREATE_POOL_TYPES)]);
+static WL_SHM_EVENTS: Table<[wl_message; 1]> = Table([message(c"format", c"u", &NO_TYPES)]);
+static WL_SHM_INTERFACE: wl_interface = wl_interface {
+    name: c"wl_shm".as_ptr(),
+    version: 1,
+    request_count: 1,
+    requests: WL_SHM_REQUESTS.0.as_ptr(),
+    event_count: 1,
+    events: WL_SHM_EVENTS.0.as_ptr(),
+};
+
+static WL_SHM_POOL_REQUESTS: Table<[wl_message; 3]> = Table([
+    message(c"create_buffer", c"niiiiu", &CREATE_BUFFER_TYPES),
+    message(c"destroy", c"", &NO_TYPES),
+    message(c"resize", c"i", &NO_TYPES),
+]);
+static WL_SHM_POOL_INTERFACE: wl_interface = wl_interface {
+    name: c"wl_shm_pool".as_ptr(),
+    version: 1,
+    request_count: 3,
+    requests: WL_SHM_POOL_REQUESTS.0.as_ptr(),
+    event_count: 0,
+    events: std::ptr::null(),
+};
+
+static WL_BUFFER_REQUESTS: Table<[wl_message; 1]> = Table([message(c"destroy", c"", &NO_TYPES)]);
+static WL_BUFFER_EVENTS: Table<[wl_message; 1]> = Table([message(c"release", c"", &NO_TYPES)]);
+static WL_BUFFER_INTERFACE: wl_interface = wl_interface {
+    name: c"wl_buffer".as_ptr(),
+    version: 1,
+    request_count: 1,
+    requests: WL_BUFFER_REQUESTS.0.as_ptr(),
+    event_count: 1,
+    events: WL_BUFFER_EVENTS.0.as_ptr(),
+};
+
+#[repr(C)]
+struct RegistryListener {
+    global: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32, *const c_char, u32),
+    global_remove: unsafe extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+static REGISTRY_LISTENER: RegistryListener = RegistryListener {
+    global: registry_global,
+    global_remove: registry_global_remove,
+};
+
+/// Remembers the name of wl_shm in the Option<u32> of the listener
+unsafe extern "C" fn registry_global(
+    data: *mut c_void,
+    _registry: *mut wl_proxy,
+    name: u32,
+    interface: *const c_char,
+    _version: u32,
+) {
+    if CStr::from_ptr(interface).to_bytes() == b"wl_shm" {
+        *(data as *mut Option<u32>) = Some(name);
+    }
+}
+
+unsafe extern "C" fn registry_global_remove(
+    _data: *mut c_void,
+    _registry: *mut wl_proxy,
+    _name: u32,
+) {
+}
+
+#[repr(C)]
+struct BufferListener {
+    release: unsafe extern "C" fn(*mut c_void, *mut wl_proxy),
+}
+
+static BUFFER_LISTENER: BufferListener = BufferListener {
+    release: buffer_release,
+};
+
+/// Marks the Cell<bool> of the listener, the compositor does not read the buffer anymore
+unsafe extern "C" fn buffer_release(data: *mut c_void, _buffer: *mut wl_proxy) {
+    (*(data as *const Cell<bool>)).set(true);
+}
@@ -1,50 +1,85 @@
 //! Drawing backend for the Web
 
-use crate::{Backend, Point2};
+use crate::blend::srgb_only;
+use crate::image::ImageData;
+use crate::paint::premultiplied_stops;
+use crate::{
+    Backend, BlendMode, BlendSpace, Color, Error, ExtendMode, GlyphRun, GradientStop, Image,
+    ImageFilter, LineCap, LineJoin, Paint, Path, PathElement, Point, Rect, StrokeStyle,
+    Transform2D,
+};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::{Arc, Weak};
+use wasm_bindgen::{Clamped, JsCast, JsValue};
+
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Self {
+        Error::PlatformError(format!("{:?}", value))
+    }
+}
 
 #[doc(hidden)]
-pub fn create_backend(id: u32) -> WebBackend {
-    use wasm_bindgen::JsCast;
+pub fn create_backend(id: u32) -> Result<WebBackend, Error> {
     info!("WebBackend is being created. ID:{}", id);
 
-    let window = web_sys::window().unwrap();
+    let window = web_sys::window().ok_or_else(|| Error::PlatformError("no window".to_string()))?;
 
-    let width = window.inner_width().unwrap().as_f64().unwrap();
-    let height = window.inner_height().unwrap().as_f64().unwrap();
+    let (width, height) = window_size(&window)?;
 
-    let document = window.document().unwrap();
-    let canvas = document.get_element_by_id("bly_canvas").unwrap();
+    let document = window
+        .document()
+        .ok_or_else(|| Error::PlatformError("no document".to_string()))?;
+    let canvas = document.get_element_by_id("bly_canvas").ok_or_else(|| {
+        Error::SurfaceCreationFailed("no element with the id bly_canvas".to_string())
+    })?;
 
     let canvas: web_sys::HtmlCanvasElement = canvas
         .dyn_into::<web_sys::HtmlCanvasElement>()
-        .map_err(|_| ())
-        .unwrap();
+        .map_err(|_| Error::SurfaceCreationFailed("bly_canvas is not a canvas".to_string()))?;
 
     let context = canvas
-        .get_context("2d")
-        .unwrap()
-        .unwrap()
+        .get_context("2d")?
+        .ok_or_else(|| Error::ContextCreationFailed("2d context is unavailable".to_string()))?
         .dyn_into::<web_sys::CanvasRenderingContext2d>()
-        .unwrap();
+        .map_err(|_| Error::ContextCreationFailed("unexpected context type".to_string()))?;
 
-    let body = document.body().unwrap();
+    let body = document
+        .body()
+        .ok_or_else(|| Error::PlatformError("no body".to_string()))?;
 
     canvas
         .style()
         .set_css_text(&format!("width: {}px; height: {}px;", width, height));
 
-    body.append_child(&canvas).unwrap();
-    WebBackend {
+    body.append_child(&canvas)?;
+    Ok(WebBackend {
         canvas,
         context,
         window,
 
         width,
         height,
+        images: HashMap::new(),
+        transform: Transform2D::identity(),
+        blend: BlendMode::Normal,
+        layers: Vec::new(),
         r: 1.0,
         g: 1.0,
         b: 1.0,
         a: 1.0,
+    })
+}
+
+/// Get the inner size of the browser window
+fn window_size(window: &web_sys::Window) -> Result<(f64, f64), Error> {
+    let width = window.inner_width()?.as_f64();
+    let height = window.inner_height()?.as_f64();
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(Error::PlatformError(
+            "window size is not a number".to_string(),
+        )),
     }
 }
 
@@ -57,6 +92,14 @@ pub struct WebBackend {
     width: f64,
     height: f64,
 
+    /// Images uploaded to offscreen canvases by draw_image(), until the last clone of the image is dropped
+    images: HashMap<u64, (Weak<ImageData>, web_sys::HtmlCanvasElement)>,
+    /// Transform set last, applied again when a clip is popped
+    transform: Transform2D,
+    blend: BlendMode,
+    /// Pushed layers, drawn into offscreen canvases until they are popped
+    layers: Vec<Layer>,
+
     r: f32,
     g: f32,
     b: f32,
@@ -65,119 +108,518 @@ pub struct WebBackend {
 
 impl Backend for WebBackend {
     #[inline]
-    unsafe fn begin_draw(&mut self) {
-        let (width, height) = self.get_window_size();
-        self.scale(width, height);
+    unsafe fn begin_draw(&mut self) -> Result<(), Error> {
+        let (width, height) = window_size(&self.window)?;
+        self.scale(width as f32, height as f32);
+        self.images.retain(|_, (owner, _)| owner.strong_count() > 0);
         self.context.begin_path();
+        Ok(())
     }
 
     #[inline]
-    unsafe fn flush(&mut self) {
-        self.context.fill();
+    unsafe fn flush(&mut self) -> Result<(), Error> {
+        // Every shape is drawn by its own call, the browser presents the canvas by itself
+        Ok(())
     }
 
     #[inline]
     unsafe fn get_display_size(&mut self) -> (u32, u32) {
-        let (width, height) = self.get_window_size();
-        self.scale(width, height);
-        (width as u32, height as u32)
+        if let Ok((width, height)) = window_size(&self.window) {
+            self.scale(width as f32, height as f32);
+        }
+        (self.width as u32, self.height as u32)
+    }
+
+    #[inline]
+    unsafe fn resize(&mut self, _width: u32, _height: u32) {
+        // The canvas element follows the size of the window in begin_draw
+    }
+
+    #[inline]
+    unsafe fn set_blend_space(&mut self, space: BlendSpace) -> Result<(), Error> {
+        // The 2D context composites the sRGB values of the canvas
+        srgb_only(space)
     }
 
     #[inline]
     unsafe fn clear(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        // The whole canvas is replaced whatever the transform is, like on the other backends
+        self.context.save();
+        if let Err(e) = self.context.reset_transform() {
+            error!("resetTransform failed: {:?}", e);
+        }
+        if let Err(e) = self.context.set_global_composite_operation("copy") {
+            error!("globalCompositeOperation failed: {:?}", e);
+        }
         self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(&format!(
-                "rgba({},{},{},{})",
-                (r * 255.0) as u32,
-                (g * 255.0) as u32,
-                (b * 255.0) as u32,
-                (a * 255.0) as u32
-            )));
-        self.context
-            .fill_rect(0.0, 0.0, self.width, self.height + 300.0);
+            .set_fill_style(&wasm_bindgen::JsValue::from_str(&css_color(Color::rgba(
+                r, g, b, a,
+            ))));
+        self.context.fill_rect(
+            0.0,
+            0.0,
+            self.canvas.width().into(),
+            self.canvas.height().into(),
+        );
+        self.context.restore();
     }
 
     #[inline]
-    unsafe fn ellipse(&mut self, point: Point2<f32>, radius: f32, r: f32, g: f32, b: f32, a: f32) {
-        todo!()
+    unsafe fn set_transform(&mut self, transform: &Transform2D) {
+        self.transform = *transform;
+        if let Err(e) = self.context.set_transform(
+            transform.m11.into(),
+            transform.m12.into(),
+            transform.m21.into(),
+            transform.m22.into(),
+            transform.m31.into(),
+            transform.m32.into(),
+        ) {
+            error!("setTransform failed: {:?}", e);
+        }
     }
 
     #[inline]
-    unsafe fn rectangle(
-        &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
-    ) {
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(&format!(
-                "rgba({},{},{},{})",
-                (r * 255.0) as u32,
-                (g * 255.0) as u32,
-                (b * 255.0) as u32,
-                (a * 255.0) as u32
-            )));
+    unsafe fn push_clip_rect(&mut self, rect: Rect) {
+        self.context.save();
+        self.context.begin_path();
+        self.context.rect(
+            rect.x.into(),
+            rect.y.into(),
+            rect.width.into(),
+            rect.height.into(),
+        );
+        self.context.clip();
+    }
+
+    #[inline]
+    unsafe fn push_clip_rounded_rect(&mut self, rect: Rect, radius: f32) {
+        self.context.save();
+        self.set_path(&rounded_rectangle_path(rect, radius));
+        self.context.clip();
+    }
+
+    #[inline]
+    unsafe fn push_clip_path(&mut self, path: &Path) {
+        self.context.save();
+        self.set_path(path);
+        self.context.clip();
+    }
+
+    #[inline]
+    unsafe fn pop_clip(&mut self) {
+        // Restoring the state also brings back the transform and the composite operation,
+        // which may have changed since the push
+        self.context.restore();
+        let (transform, blend) = (self.transform, self.blend);
+        self.set_transform(&transform);
+        self.set_blend_mode(blend);
+    }
+
+    #[inline]
+    unsafe fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend = mode;
+        if let Err(e) = self
+            .context
+            .set_global_composite_operation(composite_operation(mode))
+        {
+            error!("globalCompositeOperation failed: {:?}", e);
+        }
+    }
+
+    #[inline]
+    unsafe fn push_layer(&mut self, opacity: f32, mode: BlendMode) {
+        let canvas = match self.create_canvas(self.canvas.width(), self.canvas.height()) {
+            Ok((canvas, context)) => {
+                let parent = std::mem::replace(&mut self.context, context);
+                let (transform, blend) = (self.transform, self.blend);
+                self.set_transform(&transform);
+                self.set_blend_mode(blend);
+                Some((canvas, parent))
+            }
+            Err(e) => {
+                // The content of the layer is drawn directly instead
+                error!("creating a layer failed: {:?}", e);
+                None
+            }
+        };
+        self.layers.push(Layer {
+            canvas,
+            opacity,
+            blend: mode,
+        });
+    }
+
+    #[inline]
+    unsafe fn pop_layer(&mut self) {
+        let (canvas, parent, opacity, blend) = match self.layers.pop() {
+            Some(Layer {
+                canvas: Some((canvas, parent)),
+                opacity,
+                blend,
+            }) => (canvas, parent, opacity, blend),
+            _ => return,
+        };
+        self.context = parent;
+        self.context.save();
+        if let Err(e) = self.context.reset_transform() {
+            error!("resetTransform failed: {:?}", e);
+        }
+        self.context.set_global_alpha(opacity.into());
+        if let Err(e) = self
+            .context
+            .set_global_composite_operation(composite_operation(blend))
+        {
+            error!("globalCompositeOperation failed: {:?}", e);
+        }
+        if let Err(e) = self
+            .context
+            .draw_image_with_html_canvas_element(&canvas, 0.0, 0.0)
+        {
+            error!("drawImage failed: {:?}", e);
+        }
+        self.context.restore();
+        // The parent still has the transform and the blend mode of the time of the push
+        let (transform, blend) = (self.transform, self.blend);
+        self.set_transform(&transform);
+        self.set_blend_mode(blend);
+    }
+
+    #[inline]
+    unsafe fn ellipse(&mut self, point: Point, radius: f32, paint: &Paint) {
+        let paint_style = self.paint_style(paint);
+        self.context.set_fill_style(&paint_style);
+
+        self.context.begin_path();
+        if let Err(e) = self.context.arc(
+            (point.x + radius).into(),
+            (point.y + radius).into(),
+            radius.into(),
+            0.0,
+            2.0 * PI,
+        ) {
+            error!("arc failed: {:?}", e);
+            return;
+        }
+        self.context.fill();
+    }
+
+    #[inline]
+    unsafe fn rectangle(&mut self, rect: Rect, paint: &Paint) {
+        let paint_style = self.paint_style(paint);
+        self.context.set_fill_style(&paint_style);
         self.context.fill_rect(
-            point1.0.into(),
-            point1.1.into(),
-            point2.0.into(),
-            point2.1.into(),
+            rect.x.into(),
+            rect.y.into(),
+            rect.width.into(),
+            rect.height.into(),
         );
     }
 
     #[inline]
-    unsafe fn rounded_rectangle(
+    unsafe fn rounded_rectangle(&mut self, rect: Rect, radius: f32, paint: &Paint) {
+        let paint_style = self.paint_style(paint);
+        self.context.set_fill_style(&paint_style);
+
+        self.set_path(&rounded_rectangle_path(rect, radius));
+        self.context.fill();
+    }
+
+    #[inline]
+    unsafe fn line(&mut self, point1: Point, point2: Point, style: &StrokeStyle, paint: &Paint) {
+        let paint_style = self.paint_style(paint);
+        self.context.set_fill_style(&paint_style);
+        self.context.set_stroke_style(&paint_style);
+        self.set_stroke_style(style);
+
+        self.context.begin_path();
+        self.context.move_to(point1.x.into(), point1.y.into());
+        self.context.line_to(point2.x.into(), point2.y.into());
+
+        self.context.stroke();
+    }
+
+    #[inline]
+    unsafe fn stroke_ellipse(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
+        point: Point,
         radius: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
-        todo!()
+        let paint_style = self.paint_style(paint);
+        self.context.set_stroke_style(&paint_style);
+        self.set_stroke_style(style);
+
+        self.context.begin_path();
+        if let Err(e) = self.context.arc(
+            (point.x + radius).into(),
+            (point.y + radius).into(),
+            radius.into(),
+            0.0,
+            2.0 * PI,
+        ) {
+            error!("arc failed: {:?}", e);
+            return;
+        }
+        self.context.stroke();
     }
 
     #[inline]
-    unsafe fn line(
+    unsafe fn stroke_rectangle(&mut self, rect: Rect, style: &StrokeStyle, paint: &Paint) {
+        let paint_style = self.paint_style(paint);
+        self.context.set_stroke_style(&paint_style);
+        self.set_stroke_style(style);
+
+        self.context.stroke_rect(
+            rect.x.into(),
+            rect.y.into(),
+            rect.width.into(),
+            rect.height.into(),
+        );
+    }
+
+    #[inline]
+    unsafe fn stroke_rounded_rectangle(
         &mut self,
-        point1: Point2<f32>,
-        point2: Point2<f32>,
-        stroke: f32,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        rect: Rect,
+        radius: f32,
+        style: &StrokeStyle,
+        paint: &Paint,
     ) {
-        self.context
-            .set_fill_style(&wasm_bindgen::JsValue::from_str(&format!(
-                "rgba({},{},{},{})",
-                (r * 255.0) as u32,
-                (g * 255.0) as u32,
-                (b * 255.0) as u32,
-                (a * 255.0) as u32
-            )));
-        self.context
-            .set_stroke_style(&wasm_bindgen::JsValue::from_str(&format!(
-                "rgba({},{},{},{});",
-                (r * 255.0) as u32,
-                (g * 255.0) as u32,
-                (b * 255.0) as u32,
-                (a * 255.0) as u32
-            )));
+        let paint_style = self.paint_style(paint);
+        self.context.set_stroke_style(&paint_style);
+        self.set_stroke_style(style);
 
-        self.context.move_to(point1.0.into(), point1.1.into());
-        self.context.line_to(point2.0.into(), point2.1.into());
+        self.set_path(&rounded_rectangle_path(rect, radius));
+        self.context.stroke();
+    }
+
+    #[inline]
+    unsafe fn fill_path(&mut self, path: &Path, paint: &Paint) {
+        let paint_style = self.paint_style(paint);
+        self.context.set_fill_style(&paint_style);
+        self.set_path(path);
+        self.context.fill();
+    }
 
+    #[inline]
+    unsafe fn stroke_path(&mut self, path: &Path, style: &StrokeStyle, paint: &Paint) {
+        let paint_style = self.paint_style(paint);
+        self.context.set_stroke_style(&paint_style);
+        self.set_stroke_style(style);
+        self.set_path(path);
         self.context.stroke();
     }
+
+    #[inline]
+    unsafe fn draw_image(&mut self, image: &Image, dest: Rect, src: Rect, opacity: f32) {
+        let canvas = match self.images.get(&image.id()) {
+            Some((_, canvas)) => canvas.clone(),
+            None => match self.upload_image(image) {
+                Ok(canvas) => {
+                    self.images
+                        .insert(image.id(), (Arc::downgrade(image.data()), canvas.clone()));
+                    canvas
+                }
+                Err(e) => {
+                    error!("Failed to upload an image: {}", e);
+                    return;
+                }
+            },
+        };
+
+        self.context.save();
+        self.context.set_global_alpha(opacity.into());
+        self.context
+            .set_image_smoothing_enabled(image.filter() == ImageFilter::Bilinear);
+        if let Err(e) = self
+            .context
+            .draw_image_with_html_canvas_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                &canvas,
+                src.x.into(),
+                src.y.into(),
+                src.width.into(),
+                src.height.into(),
+                dest.x.into(),
+                dest.y.into(),
+                dest.width.into(),
+                dest.height.into(),
+            )
+        {
+            error!("drawImage failed: {:?}", e);
+        }
+        self.context.restore();
+    }
+
+    #[inline]
+    unsafe fn draw_glyphs(&mut self, run: &GlyphRun, origin: Point, paint: &Paint) {
+        self.fill_path(&run.to_path(origin), paint);
+    }
+
+    #[inline]
+    unsafe fn read_pixels(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        None
+    }
 }
 
 impl WebBackend {
+    /// Copies the pixels of the image to an offscreen canvas that drawImage() can take
+    fn upload_image(&self, image: &Image) -> Result<web_sys::HtmlCanvasElement, Error> {
+        let (canvas, context) = self.create_canvas(image.width(), image.height())?;
+        let data = web_sys::ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(image.pixels()),
+            image.width(),
+            image.height(),
+        )?;
+        context.put_image_data(&data, 0.0, 0.0)?;
+        Ok(canvas)
+    }
+
+    /// Creates a transparent canvas that is not part of the page
+    fn create_canvas(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<
+        (
+            web_sys::HtmlCanvasElement,
+            web_sys::CanvasRenderingContext2d,
+        ),
+        Error,
+    > {
+        let document = self
+            .window
+            .document()
+            .ok_or_else(|| Error::PlatformError("no document".to_string()))?;
+        let canvas = document
+            .create_element("canvas")?
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .map_err(|_| Error::PlatformError("unexpected element type".to_string()))?;
+        canvas.set_width(width);
+        canvas.set_height(height);
+
+        let context = canvas
+            .get_context("2d")?
+            .ok_or_else(|| Error::ContextCreationFailed("2d context is unavailable".to_string()))?
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()
+            .map_err(|_| Error::ContextCreationFailed("unexpected context type".to_string()))?;
+        Ok((canvas, context))
+    }
+
+    /// Converts the paint into a value for fillStyle or strokeStyle.
+    /// The canvas only pads gradients, so the other extend modes are drawn by repeating the stops
+    /// until the gradient covers the canvas
+    fn paint_style(&self, paint: &Paint) -> JsValue {
+        let diagonal = (self.width * self.width + self.height * self.height).sqrt() as f32;
+        let result = match paint {
+            Paint::Solid(color) => return JsValue::from_str(&css_color(*color)),
+            Paint::LinearGradient(gradient) => {
+                let (dx, dy) = (
+                    gradient.end.x - gradient.start.x,
+                    gradient.end.y - gradient.start.y,
+                );
+                let length = (dx * dx + dy * dy).sqrt();
+                if gradient.stops.is_empty() || length == 0.0 {
+                    return last_stop_style(&gradient.stops);
+                }
+                let periods = repeated_periods(gradient.extend, diagonal, length);
+                let n = periods as f32;
+                let canvas_gradient = self.context.create_linear_gradient(
+                    (gradient.start.x - dx * n).into(),
+                    (gradient.start.y - dy * n).into(),
+                    (gradient.end.x + dx * n).into(),
+                    (gradient.end.y + dy * n).into(),
+                );
+                add_color_stops(
+                    &canvas_gradient,
+                    &unrolled_stops(&gradient.stops, gradient.extend, periods, periods),
+                )
+                .map(|_| canvas_gradient)
+            }
+            Paint::RadialGradient(gradient) => {
+                if gradient.stops.is_empty() || gradient.radius <= 0.0 {
+                    return last_stop_style(&gradient.stops);
+                }
+                let periods = repeated_periods(gradient.extend, diagonal, gradient.radius);
+                self.context
+                    .create_radial_gradient(
+                        gradient.center.x.into(),
+                        gradient.center.y.into(),
+                        0.0,
+                        gradient.center.x.into(),
+                        gradient.center.y.into(),
+                        (gradient.radius * (periods + 1) as f32).into(),
+                    )
+                    .and_then(|canvas_gradient| {
+                        add_color_stops(
+                            &canvas_gradient,
+                            &unrolled_stops(&gradient.stops, gradient.extend, 0, periods),
+                        )
+                        .map(|_| canvas_gradient)
+                    })
+            }
+        };
+        match result {
+            Ok(canvas_gradient) => canvas_gradient.into(),
+            Err(e) => {
+                error!("Failed to create a gradient: {:?}", e);
+                JsValue::from_str("transparent")
+            }
+        }
+    }
+
+    /// Applies the width, joins, caps and dashes used by the next stroke()
+    fn set_stroke_style(&mut self, style: &StrokeStyle) {
+        self.context.set_line_width(style.width.into());
+        self.context.set_line_join(match style.line_join {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        });
+        self.context.set_line_cap(match style.line_cap {
+            LineCap::Butt => "butt",
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        });
+        self.context.set_miter_limit(style.miter_limit.into());
+
+        let dash = js_sys::Array::new();
+        if style.is_dashed() {
+            for length in &style.dash {
+                dash.push(&JsValue::from_f64((*length).into()));
+            }
+        }
+        if let Err(e) = self.context.set_line_dash(&dash) {
+            error!("setLineDash failed: {:?}", e);
+        }
+        self.context.set_line_dash_offset(style.dash_offset.into());
+    }
+
+    /// Replaces the current path of the context with the given path
+    fn set_path(&mut self, path: &Path) {
+        self.context.begin_path();
+        for element in path.elements() {
+            match *element {
+                PathElement::MoveTo(x, y) => self.context.move_to(x.into(), y.into()),
+                PathElement::LineTo(x, y) => self.context.line_to(x.into(), y.into()),
+                PathElement::QuadTo(x1, y1, x, y) => {
+                    self.context
+                        .quadratic_curve_to(x1.into(), y1.into(), x.into(), y.into())
+                }
+                PathElement::CubicTo(x1, y1, x2, y2, x, y) => self.context.bezier_curve_to(
+                    x1.into(),
+                    y1.into(),
+                    x2.into(),
+                    y2.into(),
+                    x.into(),
+                    y.into(),
+                ),
+                PathElement::Close => self.context.close_path(),
+            }
+        }
+    }
+
     pub fn set_rgba(&mut self, r: f32, g: f32, b: f32, a: f32) {
         self.r = r;
         self.g = g;
@@ -192,10 +634,139 @@ impl WebBackend {
             self.width as f64, self.height as f64
         ));
     }
+}
+
+/// A layer pushed by push_layer()
+struct Layer {
+    /// Canvas that the layer is drawn into and the context it is composited into,
+    /// None if the canvas could not be created
+    canvas: Option<(
+        web_sys::HtmlCanvasElement,
+        web_sys::CanvasRenderingContext2d,
+    )>,
+    opacity: f32,
+    blend: BlendMode,
+}
+
+/// Value of globalCompositeOperation for a blend mode
+fn composite_operation(mode: BlendMode) -> &'static str {
+    match mode {
+        BlendMode::Normal => "source-over",
+        BlendMode::Multiply => "multiply",
+        BlendMode::Screen => "screen",
+        BlendMode::Overlay => "overlay",
+        BlendMode::Darken => "darken",
+        BlendMode::Lighten => "lighten",
+        BlendMode::ColorDodge => "color-dodge",
+        BlendMode::ColorBurn => "color-burn",
+        BlendMode::HardLight => "hard-light",
+        BlendMode::SoftLight => "soft-light",
+        BlendMode::Difference => "difference",
+        BlendMode::Exclusion => "exclusion",
+        BlendMode::Add => "lighter",
+    }
+}
+
+/// Outline of a rounded rectangle
+fn rounded_rectangle_path(rect: Rect, radius: f32) -> Path {
+    let (x, y, w, h) = (rect.x, rect.y, rect.width, rect.height);
+    let radius = radius.min(w.abs() / 2.0).min(h.abs() / 2.0).max(0.0);
+    let mut path = Path::new();
+    path.move_to(x + radius, y)
+        .arc_to(x + w, y, x + w, y + h, radius)
+        .arc_to(x + w, y + h, x, y + h, radius)
+        .arc_to(x, y + h, x, y, radius)
+        .arc_to(x, y, x + w, y, radius)
+        .close();
+    path
+}
 
-    fn get_window_size(&mut self) -> (f32, f32) {
-        let width = self.window.inner_width().unwrap().as_f64().unwrap() as f32;
-        let height = self.window.inner_height().unwrap().as_f64().unwrap() as f32;
-        (width, height)
+/// CSS color string of a color.
+/// CSS takes the components from 0 to 255 and the alpha from 0.0 to 1.0
+fn css_color(color: Color) -> String {
+    let [r, g, b, _] = color.to_rgba8();
+    format!("rgba({},{},{},{})", r, g, b, color.a.clamp(0.0, 1.0))
+}
+
+/// Style of a gradient that cannot be drawn as a gradient, transparent if it has no stops
+fn last_stop_style(stops: &[GradientStop]) -> JsValue {
+    match stops.iter().max_by(|a, b| a.offset.total_cmp(&b.offset)) {
+        Some(stop) => JsValue::from_str(&css_color(stop.color)),
+        None => JsValue::from_str("transparent"),
+    }
+}
+
+/// Number of extra periods needed on each side of a gradient to cover the canvas
+fn repeated_periods(extend: ExtendMode, diagonal: f32, length: f32) -> usize {
+    if extend == ExtendMode::Pad {
+        0
+    } else {
+        ((diagonal / length).ceil() as usize).min(256)
+    }
+}
+
+fn add_color_stops(
+    gradient: &web_sys::CanvasGradient,
+    stops: &[GradientStop],
+) -> Result<(), JsValue> {
+    for stop in stops {
+        gradient.add_color_stop(stop.offset, &css_color(stop.color))?;
+    }
+    Ok(())
+}
+
+/// Stops that draw the extend mode of a gradient within a pad-only gradient.
+/// The returned stops cover `before + 1 + after` periods of the gradient,
+/// the original one being the one after the first `before` periods.
+fn unrolled_stops(
+    stops: &[GradientStop],
+    extend: ExtendMode,
+    before: usize,
+    after: usize,
+) -> Vec<GradientStop> {
+    // Browsers do not all interpolate with premultiplied alpha
+    let mut stops = premultiplied_stops(stops);
+    if extend == ExtendMode::Pad || stops.is_empty() {
+        return stops;
+    }
+    // Every period has to end with the colors it pads with, or neighbouring periods would blend
+    if stops[0].offset > 0.0 {
+        stops.insert(
+            0,
+            GradientStop {
+                offset: 0.0,
+                color: stops[0].color,
+            },
+        );
+    }
+    if let Some(&last) = stops.last() {
+        if last.offset < 1.0 {
+            stops.push(GradientStop {
+                offset: 1.0,
+                color: last.color,
+            });
+        }
+    }
+    let periods = before + 1 + after;
+    let mut unrolled = Vec::with_capacity(stops.len() * periods);
+    for period in 0..periods {
+        let reversed = extend == ExtendMode::Reflect && (period + before) % 2 == 1;
+        let mut push = |stop: &GradientStop| {
+            let offset = if reversed {
+                1.0 - stop.offset
+            } else {
+                stop.offset
+            };
+            unrolled.push(GradientStop {
+                offset: (period as f32 + offset) / periods as f32,
+                color: stop.color,
+            });
+        };
+        if reversed {
+            stops.iter().rev().for_each(&mut push);
+        } else {
+            stops.iter().for_each(&mut push);
+        }
     }
+    unrolled
 }
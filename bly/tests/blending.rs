@@ -0,0 +1,117 @@
+//! Checks the pixels that colors are drawn with, in both blend spaces
+
+use bly::{BlendSpace, Color, Image, LinearGradient, Point, Rect, Size};
+
+/// Pixel (x, 0) read back from the canvas
+fn pixel(canvas: &mut bly::Canvas, x: usize) -> [u8; 4] {
+    let pixels = canvas.read_pixels().unwrap();
+    pixels[x * 4..x * 4 + 4].try_into().unwrap()
+}
+
+fn assert_pixel(actual: [u8; 4], expected: [u8; 4]) {
+    assert!(
+        actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) <= 1),
+        "{:?} should be {:?}",
+        actual,
+        expected
+    );
+}
+
+/// Fills a canvas of 3x1 pixels with a gradient whose middle is at the center of the second pixel
+fn draw_gradient(space: BlendSpace, from: Color, to: Color) -> bly::Canvas {
+    let mut canvas = bly::create_offscreen_canvas(3, 1);
+    canvas.set_blend_space(space).unwrap();
+    let gradient = LinearGradient::new(Point::new(0.5, 0.0), Point::new(2.5, 0.0))
+        .with_stop(0.0, from)
+        .with_stop(1.0, to);
+    canvas
+        .draw(|painter| {
+            painter.rectangle(Point::new(0.0, 0.0), Size::new(3.0, 1.0), gradient.clone())
+        })
+        .unwrap();
+    canvas
+}
+
+#[test]
+fn colors_are_read_back_as_straight_srgb() {
+    for space in [BlendSpace::Srgb, BlendSpace::Linear] {
+        let mut canvas = bly::create_offscreen_canvas(1, 1);
+        canvas.set_blend_space(space).unwrap();
+        canvas
+            .draw(|painter| painter.clear(Color::rgba8(255, 128, 0, 128)))
+            .unwrap();
+        assert_pixel(pixel(&mut canvas, 0), [255, 128, 0, 128]);
+    }
+}
+
+#[test]
+fn translucent_colors_are_blended_in_the_blend_space() {
+    for (space, expected) in [
+        (BlendSpace::Srgb, [255, 128, 128, 255]),
+        // Half of the light of white, encoded in sRGB
+        (BlendSpace::Linear, [255, 188, 188, 255]),
+    ] {
+        let mut canvas = bly::create_offscreen_canvas(1, 1);
+        canvas.set_blend_space(space).unwrap();
+        canvas
+            .draw(|painter| {
+                painter.clear(Color::WHITE);
+                painter.rectangle(
+                    Point::new(0.0, 0.0),
+                    Size::new(1.0, 1.0),
+                    Color::RED.with_alpha(0.5),
+                );
+            })
+            .unwrap();
+        assert_pixel(pixel(&mut canvas, 0), expected);
+    }
+}
+
+#[test]
+fn gradients_are_interpolated_in_the_blend_space() {
+    let mut canvas = draw_gradient(BlendSpace::Srgb, Color::BLACK, Color::WHITE);
+    assert_pixel(pixel(&mut canvas, 1), [128, 128, 128, 255]);
+    let mut canvas = draw_gradient(BlendSpace::Linear, Color::BLACK, Color::WHITE);
+    assert_pixel(pixel(&mut canvas, 1), [188, 188, 188, 255]);
+}
+
+#[test]
+fn gradients_are_interpolated_with_premultiplied_alpha() {
+    // The transparent blue stop only fades the red out, without making it purple
+    let mut canvas = draw_gradient(BlendSpace::Srgb, Color::RED, Color::BLUE.with_alpha(0.0));
+    assert_pixel(pixel(&mut canvas, 1), [255, 0, 0, 128]);
+}
+
+#[test]
+fn images_are_straight_srgb() {
+    let image = Image::from_rgba(1, 1, vec![200, 100, 50, 128]).unwrap();
+    for space in [BlendSpace::Srgb, BlendSpace::Linear] {
+        let mut canvas = bly::create_offscreen_canvas(1, 1);
+        canvas.set_blend_space(space).unwrap();
+        canvas
+            .draw(|painter| painter.draw_image(&image, Rect::new(0.0, 0.0, 1.0, 1.0), None, 1.0))
+            .unwrap();
+        assert_pixel(pixel(&mut canvas, 0), [200, 100, 50, 128]);
+    }
+}
+
+#[test]
+fn changing_the_blend_space_keeps_the_pixels() {
+    let mut canvas = bly::create_offscreen_canvas(2, 1);
+    canvas
+        .draw(|painter| {
+            painter.clear(Color::rgb8(12, 200, 140));
+            painter.rectangle(
+                Point::new(1.0, 0.0),
+                Size::new(1.0, 1.0),
+                Color::rgba8(255, 128, 0, 64),
+            );
+        })
+        .unwrap();
+    let before = canvas.read_pixels().unwrap();
+    canvas.set_blend_space(BlendSpace::Linear).unwrap();
+    assert_pixel(pixel(&mut canvas, 0), before[0..4].try_into().unwrap());
+    assert_pixel(pixel(&mut canvas, 1), before[4..8].try_into().unwrap());
+    canvas.set_blend_space(BlendSpace::Srgb).unwrap();
+    assert_pixel(pixel(&mut canvas, 1), before[4..8].try_into().unwrap());
+}
@@ -0,0 +1,172 @@
+//! Color parsing and conversion tests
+
+use bly::{Color, Error};
+
+fn assert_close(a: Color, b: Color) {
+    let (a8, b8) = (a.to_rgba8(), b.to_rgba8());
+    assert!(
+        a8.iter().zip(b8.iter()).all(|(x, y)| x.abs_diff(*y) <= 1),
+        "{:?} should be close to {:?}",
+        a,
+        b
+    );
+}
+
+#[test]
+fn hex_colors_are_parsed() {
+    assert_eq!(
+        Color::from_hex("#ff8000").unwrap(),
+        Color::rgb8(255, 128, 0)
+    );
+    assert_eq!(Color::from_hex("ff8000").unwrap(), Color::rgb8(255, 128, 0));
+    assert_eq!(Color::from_hex("#f80").unwrap(), Color::rgb8(255, 136, 0));
+    assert_eq!(
+        Color::from_hex("#f808").unwrap(),
+        Color::rgba8(255, 136, 0, 136)
+    );
+    assert_eq!(
+        Color::from_hex("#FF800080").unwrap(),
+        Color::rgba8(255, 128, 0, 128)
+    );
+}
+
+#[test]
+fn invalid_hex_colors_are_rejected() {
+    for hex in [
+        "",
+        "#",
+        "#12",
+        "#12345",
+        "#1234567",
+        "#ggg",
+        "#+12",
+        "#123456789",
+    ] {
+        assert!(
+            matches!(Color::from_hex(hex), Err(Error::ColorParsingFailed(_))),
+            "{:?} should be rejected",
+            hex
+        );
+    }
+}
+
+#[test]
+fn named_colors_ignore_case() {
+    assert_eq!(
+        Color::named("rebeccapurple"),
+        Some(Color::rgb8(102, 51, 153))
+    );
+    assert_eq!(Color::named("AliceBlue"), Some(Color::rgb8(240, 248, 255)));
+    assert_eq!(Color::named("yellowgreen"), Some(Color::rgb8(154, 205, 50)));
+    assert_eq!(Color::named("lime"), Some(Color::GREEN));
+    assert_eq!(Color::named("gray"), Some(Color::GRAY));
+    assert_eq!(Color::named("grey"), Some(Color::GRAY));
+    assert_eq!(Color::named("notacolor"), None);
+}
+
+#[test]
+fn css_functions_are_parsed() {
+    let orange = Color::rgb8(255, 128, 0);
+    assert_eq!(Color::parse("rgb(255, 128, 0)").unwrap(), orange);
+    assert_eq!(Color::parse("rgb(255 128 0)").unwrap(), orange);
+    assert_eq!(
+        Color::parse("rgba(255, 128, 0, 0.5)").unwrap(),
+        orange.with_alpha(0.5)
+    );
+    assert_eq!(
+        Color::parse("rgb(255 128 0 / 50%)").unwrap(),
+        orange.with_alpha(0.5)
+    );
+    assert_eq!(Color::parse("rgb(100% 0% 0%)").unwrap(), Color::RED);
+    assert_eq!(Color::parse("hsl(120, 100%, 50%)").unwrap(), Color::GREEN);
+    assert_eq!(Color::parse("hsl(240deg 100% 50%)").unwrap(), Color::BLUE);
+    assert_close(
+        Color::parse("hsla(0.5turn 100% 50% / 0.25)").unwrap(),
+        Color::rgba(0.0, 1.0, 1.0, 0.25),
+    );
+    assert_close(
+        Color::parse("oklch(62.8% 0.2577 29.23)").unwrap(),
+        Color::RED,
+    );
+    assert_eq!(Color::parse("  Transparent ").unwrap(), Color::TRANSPARENT);
+    assert_eq!("#0000ff".parse::<Color>().unwrap(), Color::BLUE);
+}
+
+#[test]
+fn invalid_css_is_rejected() {
+    for css in [
+        "rgb(1, 2)",
+        "rgb(1 2 3 4)",
+        "rgb(1, 2, 3",
+        "cmyk(1, 2, 3)",
+        "hsl(red 100% 50%)",
+        "rgb(nan, 0, 0)",
+        "blurple",
+    ] {
+        assert!(Color::parse(css).is_err(), "{:?} should be rejected", css);
+    }
+}
+
+#[test]
+fn hsl_and_hsv_round_trip() {
+    let colors = [
+        Color::rgb8(255, 128, 0),
+        Color::rgb8(12, 200, 140),
+        Color::rgb8(90, 20, 210),
+        Color::rgb8(128, 128, 128),
+        Color::BLACK,
+        Color::WHITE,
+    ];
+    for color in colors {
+        let (h, s, l) = color.to_hsl();
+        assert_close(Color::from_hsl(h, s, l), color);
+        let (h, s, v) = color.to_hsv();
+        assert_close(Color::from_hsv(h, s, v), color);
+    }
+    assert_eq!(Color::BLUE.to_hsv(), (240.0, 1.0, 1.0));
+}
+
+#[test]
+fn oklch_round_trips() {
+    let (l, c, h) = Color::WHITE.to_oklch();
+    assert!((l - 1.0).abs() < 1e-3 && c < 1e-3, "{} {} {}", l, c, h);
+    for color in [
+        Color::RED,
+        Color::rgb8(255, 128, 0),
+        Color::rgb8(30, 60, 200),
+    ] {
+        let (l, c, h) = color.to_oklch();
+        assert_close(Color::from_oklch(l, c, h), color);
+    }
+}
+
+#[test]
+fn helpers_keep_alpha() {
+    let red = Color::RED.with_alpha(0.5);
+    assert_close(red.lighten(0.25), Color::rgba(1.0, 0.5, 0.5, 0.5));
+    assert_close(red.darken(0.25), Color::rgba(0.5, 0.0, 0.0, 0.5));
+    assert_eq!(Color::WHITE.lighten(0.5), Color::WHITE);
+}
+
+#[test]
+fn mix_uses_premultiplied_alpha() {
+    assert_close(Color::RED.mix(Color::BLUE, 0.5), Color::rgb(0.5, 0.0, 0.5));
+    // Transparent black does not darken red, it only makes it more transparent
+    assert_close(
+        Color::RED.mix(Color::TRANSPARENT, 0.5),
+        Color::rgba(1.0, 0.0, 0.0, 0.5),
+    );
+    assert_eq!(Color::RED.mix(Color::BLUE, 0.0), Color::RED);
+}
+
+#[test]
+fn conversions() {
+    let color = Color::rgba8(255, 128, 0, 64);
+    assert_eq!(Color::from([255u8, 128, 0, 64]), color);
+    assert_eq!(Color::from(0xff800040u32), color);
+    assert_eq!(
+        Color::from([1.0f32, 0.5, 0.0, 0.25]),
+        Color::rgba(1.0, 0.5, 0.0, 0.25)
+    );
+    assert_eq!(color.to_rgba8(), [255, 128, 0, 64]);
+}
@@ -0,0 +1,151 @@
+//! Points, vectors, sizes and rectangles
+
+use bly::{Point, Rect, Size, Transform2D, Vector};
+
+#[test]
+fn points_move_by_vectors() {
+    let mut point = Point::new(10.0, 20.0);
+    assert_eq!(point + Vector::new(1.0, -2.0), Point::new(11.0, 18.0));
+    assert_eq!(point - Vector::new(1.0, -2.0), Point::new(9.0, 22.0));
+    assert_eq!(Point::new(11.0, 18.0) - point, Vector::new(1.0, -2.0));
+    point += Vector::new(5.0, 5.0);
+    assert_eq!(point, Point::new(15.0, 25.0));
+    point -= Vector::new(15.0, 25.0);
+    assert_eq!(point, Point::ORIGIN);
+    assert_eq!(Point::new(3.0, 0.0).distance(Point::new(0.0, 4.0)), 5.0);
+}
+
+#[test]
+fn vector_arithmetic() {
+    let a = Vector::new(3.0, 4.0);
+    let b = Vector::new(1.0, -1.0);
+    assert_eq!(a + b, Vector::new(4.0, 3.0));
+    assert_eq!(a - b, Vector::new(2.0, 5.0));
+    assert_eq!(-a, Vector::new(-3.0, -4.0));
+    assert_eq!(a * 2.0, Vector::new(6.0, 8.0));
+    assert_eq!(2.0 * a, a * 2.0);
+    assert_eq!(a / 2.0, Vector::new(1.5, 2.0));
+    assert_eq!(a.length(), 5.0);
+    assert_eq!(a.dot(b), -1.0);
+
+    let mut c = a;
+    c += b;
+    c -= a;
+    c *= 4.0;
+    c /= 2.0;
+    assert_eq!(c, Vector::new(2.0, -2.0));
+}
+
+#[test]
+fn sizes() {
+    assert_eq!(Size::new(2.0, 3.0) * 2.0, Size::new(4.0, 6.0));
+    assert_eq!(Size::new(2.0, 3.0) / 2.0, Size::new(1.0, 1.5));
+    assert!(Size::ZERO.is_empty());
+    assert!(Size::new(10.0, 0.0).is_empty());
+    assert!(Size::new(-1.0, 10.0).is_empty());
+    assert!(!Size::new(1.0, 1.0).is_empty());
+}
+
+#[test]
+fn rect_edges() {
+    let rect = Rect::from_origin_size((10.0, 20.0), (30.0, 40.0));
+    assert_eq!(rect, Rect::new(10.0, 20.0, 30.0, 40.0));
+    assert_eq!(rect.origin(), Point::new(10.0, 20.0));
+    assert_eq!(rect.size(), Size::new(30.0, 40.0));
+    assert_eq!(rect.right(), 40.0);
+    assert_eq!(rect.bottom(), 60.0);
+    assert_eq!(rect.center(), Point::new(25.0, 40.0));
+    assert_eq!(
+        Rect::from_points((40.0, 20.0), (10.0, 60.0)),
+        Rect::new(10.0, 20.0, 30.0, 40.0)
+    );
+    assert_eq!(
+        rect + Vector::new(1.0, 2.0),
+        Rect::new(11.0, 22.0, 30.0, 40.0)
+    );
+    assert_eq!(
+        rect - Vector::new(10.0, 20.0),
+        Rect::new(0.0, 0.0, 30.0, 40.0)
+    );
+}
+
+#[test]
+fn rect_contains_its_top_left_but_not_its_bottom_right() {
+    let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+    assert!(rect.contains((0.0, 0.0)));
+    assert!(rect.contains(Point::new(9.99, 5.0)));
+    assert!(!rect.contains((10.0, 5.0)));
+    assert!(!rect.contains((5.0, 10.0)));
+    assert!(!rect.contains((-0.1, 5.0)));
+    assert!(!Rect::new(0.0, 0.0, 0.0, 10.0).contains((0.0, 0.0)));
+}
+
+#[test]
+fn rect_intersection() {
+    let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+    assert_eq!(
+        a.intersect(&Rect::new(5.0, -5.0, 10.0, 10.0)),
+        Some(Rect::new(5.0, 0.0, 5.0, 5.0))
+    );
+    assert_eq!(
+        a.intersect(&Rect::new(2.0, 2.0, 2.0, 2.0)),
+        Some(Rect::new(2.0, 2.0, 2.0, 2.0))
+    );
+    // Rectangles that only touch do not overlap
+    assert_eq!(a.intersect(&Rect::new(10.0, 0.0, 10.0, 10.0)), None);
+    assert_eq!(a.intersect(&Rect::new(20.0, 20.0, 1.0, 1.0)), None);
+}
+
+#[test]
+fn rect_union_ignores_empty_rects() {
+    let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+    assert_eq!(
+        a.union(&Rect::new(20.0, 5.0, 5.0, 10.0)),
+        Rect::new(0.0, 0.0, 25.0, 15.0)
+    );
+    assert_eq!(a.union(&Rect::new(100.0, 100.0, 0.0, 0.0)), a);
+    assert_eq!(Rect::default().union(&a), a);
+}
+
+#[test]
+fn rect_inset() {
+    let rect = Rect::new(0.0, 0.0, 100.0, 50.0);
+    assert_eq!(rect.inset(10.0, 5.0), Rect::new(10.0, 5.0, 80.0, 40.0));
+    assert_eq!(rect.inset(-1.0, -2.0), Rect::new(-1.0, -2.0, 102.0, 54.0));
+    assert!(rect.inset(60.0, 0.0).is_empty());
+}
+
+#[test]
+fn tuple_conversions() {
+    assert_eq!(Point::from((1.0, 2.0)), Point::new(1.0, 2.0));
+    assert_eq!(Vector::from((1.0, 2.0)), Vector::new(1.0, 2.0));
+    assert_eq!(Size::from((1.0, 2.0)), Size::new(1.0, 2.0));
+    assert_eq!(<(f32, f32)>::from(Point::new(1.0, 2.0)), (1.0, 2.0));
+    assert_eq!(<(f32, f32)>::from(Vector::new(1.0, 2.0)), (1.0, 2.0));
+    assert_eq!(<(f32, f32)>::from(Size::new(1.0, 2.0)), (1.0, 2.0));
+}
+
+#[test]
+fn transforms_map_points_and_vectors() {
+    let transform = Transform2D::scale(2.0, 3.0).then(&Transform2D::translation(10.0, 0.0));
+    assert_eq!(
+        transform.transform_point(Point::new(1.0, 1.0)),
+        Point::new(12.0, 3.0)
+    );
+    // Vectors are not translated
+    assert_eq!(
+        transform.transform_vector(Vector::new(1.0, 1.0)),
+        Vector::new(2.0, 3.0)
+    );
+}
+
+#[cfg(feature = "mint")]
+#[test]
+fn mint_conversions() {
+    let point: mint::Point2<f32> = Point::new(1.0, 2.0).into();
+    assert_eq!(Point::from(point), Point::new(1.0, 2.0));
+    let vector: mint::Vector2<f32> = Vector::new(3.0, 4.0).into();
+    assert_eq!(Vector::from(vector), Vector::new(3.0, 4.0));
+    let size: mint::Vector2<f32> = Size::new(5.0, 6.0).into();
+    assert_eq!(Size::from(size), Size::new(5.0, 6.0));
+}
@@ -0,0 +1,675 @@
+//! Golden-image regression tests
+//! Every scene is drawn through Painter into an offscreen canvas and compared with
+//! the reference image in tests/golden/<scene>.png.  
+//! Run with BLY_BLESS=1 to (re)generate the reference images after an intended change.
+//! When a scene does not match, a diff image is written next to the test binary's
+//! temporary directory and its path is printed in the failure message.
+
+use bly::{
+    BlendMode, Color, ExtendMode, Font, Image, ImageFilter, LineCap, LineJoin, LinearGradient,
+    Painter, Path, Point, RadialGradient, Rect, Size, Snapshot, StrokeStyle, TextAlign, TextLayout,
+    Transform2D, Vector,
+};
+use std::fs::File;
+use std::path::PathBuf;
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 128;
+
+/// Maximum difference allowed per channel before a pixel counts as different
+const TOLERANCE: u8 = 2;
+
+fn test_font() -> Font {
+    Font::from_bytes(&include_bytes!("fonts/DejaVuSans.ttf")[..]).unwrap()
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.png", name))
+}
+
+fn diff_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("golden")
+        .join(format!("{}.diff.png", name))
+}
+
+fn load_png(path: &PathBuf) -> Snapshot {
+    let decoder = png::Decoder::new(File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+    pixels.truncate(info.buffer_size());
+    Snapshot::new(info.width, info.height, pixels).unwrap()
+}
+
+/// Builds an image showing mismatching pixels in red over a faded copy of the expected image
+fn diff_image(expected: &Snapshot, actual: &Snapshot) -> (Snapshot, usize) {
+    let mut pixels = Vec::with_capacity(expected.pixels().len());
+    let mut mismatches = 0;
+    for (e, a) in expected
+        .pixels()
+        .chunks_exact(4)
+        .zip(actual.pixels().chunks_exact(4))
+    {
+        let differs = e.iter().zip(a).any(|(e, a)| e.abs_diff(*a) > TOLERANCE);
+        if differs {
+            mismatches += 1;
+            pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (e[0] as u32 * 3 + e[1] as u32 * 6 + e[2] as u32) / 10;
+            let faded = (luma / 4 + 191) as u8;
+            pixels.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+    (
+        Snapshot::new(expected.width(), expected.height(), pixels).unwrap(),
+        mismatches,
+    )
+}
+
+fn check_scene<F>(name: &str, scene: F)
+where
+    F: FnMut(&mut Painter),
+{
+    let mut canvas = bly::create_offscreen_canvas(WIDTH, HEIGHT);
+    canvas.draw(scene).unwrap();
+    let actual = canvas
+        .snapshot()
+        .expect("offscreen canvas can always be read back");
+
+    let reference = reference_path(name);
+    if std::env::var_os("BLY_BLESS").is_some() {
+        actual.save_png(&reference).unwrap();
+        return;
+    }
+    assert!(
+        reference.exists(),
+        "missing reference image {}, run with BLY_BLESS=1 to create it",
+        reference.display()
+    );
+
+    let expected = load_png(&reference);
+    assert_eq!(
+        (expected.width(), expected.height()),
+        (actual.width(), actual.height()),
+        "scene {} has a different size than its reference image",
+        name
+    );
+
+    let (diff, mismatches) = diff_image(&expected, &actual);
+    if mismatches > 0 {
+        let path = diff_path(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        diff.save_png(&path).unwrap();
+        actual
+            .save_png(path.with_file_name(format!("{}.actual.png", name)))
+            .unwrap();
+        panic!(
+            "scene {}: {} pixels differ from the reference by more than {}, diff written to {}",
+            name,
+            mismatches,
+            TOLERANCE,
+            path.display()
+        );
+    }
+}
+
+macro_rules! scenes {
+    ($($name:ident => $scene:expr;)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check_scene(stringify!($name), $scene);
+            }
+        )*
+    };
+}
+
+scenes! {
+    clear => |painter: &mut Painter| {
+        painter.clear(Color::WHITE_GRAY);
+    };
+
+    named_colors => |painter: &mut Painter| {
+        painter.clear(Color::BLACK);
+        let colors = [
+            Color::WHITE,
+            Color::WHITE_GRAY,
+            Color::GRAY,
+            Color::RED,
+            Color::GREEN,
+            Color::BLUE,
+        ];
+        for (i, color) in colors.into_iter().enumerate() {
+            let x = (i % 3) as f32 * 40.0 + 4.0;
+            let y = (i / 3) as f32 * 60.0 + 4.0;
+            painter.rectangle(Point::new(x, y), Size::new(36.0, 56.0), color);
+        }
+    };
+
+    tiles => |painter: &mut Painter| {
+        painter.clear(Color::WHITE_GRAY);
+        for i in 0..9 {
+            let x = (i % 3) as f32 * 42.0 + 3.0;
+            let y = (i / 3) as f32 * 42.0 + 3.0;
+            let r = (i % 3) as f32 / 2.0;
+            let g = (i / 3) as f32 / 2.0;
+            painter.rectangle(
+                Point::new(x, y),
+                Size::new(38.0, 38.0),
+                Color::rgba(r, g, 1.0 - r, 1.0),
+            );
+        }
+    };
+
+    ellipses => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+        painter.ellipse(Point::new(4.0, 4.0), 40.0, Color::RED);
+        painter.ellipse(Point::new(60.5, 10.25), 25.5, Color::BLUE);
+        painter.ellipse(Point::new(20.0, 90.0), 2.5, Color::BLACK);
+        painter.ellipse(Point::new(40.0, 70.0), 25.0, Color::rgba(0.0, 0.5, 0.0, 0.5));
+    };
+
+    rounded_rectangles => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+        painter.rounded_rectangle(
+            Point::new(8.0, 8.0),
+            Size::new(112.0, 48.0),
+            12.0,
+            Color::BLUE,
+        );
+        painter.rounded_rectangle(
+            Point::new(8.0, 72.0),
+            Size::new(48.0, 48.0),
+            100.0,
+            Color::RED,
+        );
+        painter.rounded_rectangle(
+            Point::new(64.0, 72.0),
+            Size::new(56.0, 48.0),
+            0.0,
+            Color::GREEN,
+        );
+    };
+
+    lines => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+        for i in 0..8 {
+            let offset = i as f32 * 16.0 + 4.0;
+            painter.line(
+                Point::new(4.0, offset),
+                Point::new(124.0, 128.0 - offset),
+                (i + 1) as f32 * 0.75,
+                Color::BLACK,
+            );
+        }
+        painter.line(
+            Point::new(64.0, 4.0),
+            Point::new(64.0, 124.0),
+            4.0,
+            Color::RED,
+        );
+    };
+
+    alpha_blending => |painter: &mut Painter| {
+        painter.clear(Color::rgba(1.0, 1.0, 1.0, 1.0));
+        painter.rectangle(
+            Point::new(10.0, 10.0),
+            Size::new(70.0, 70.0),
+            Color::rgba(1.0, 0.0, 0.0, 0.5),
+        );
+        painter.rectangle(
+            Point::new(48.0, 48.0),
+            Size::new(70.0, 70.0),
+            Color::rgba(0.0, 0.0, 1.0, 0.5),
+        );
+    };
+
+    transparent_clear => |painter: &mut Painter| {
+        painter.clear(Color::rgba(0.0, 0.0, 0.0, 0.0));
+        painter.ellipse(Point::new(24.0, 24.0), 40.0, Color::rgba(0.2, 0.4, 0.6, 0.75));
+    };
+
+    fill_path => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        let mut star = Path::new();
+        for i in 0..10 {
+            let angle = i as f32 * std::f32::consts::PI / 5.0 - std::f32::consts::FRAC_PI_2;
+            let radius = if i % 2 == 0 { 30.0 } else { 12.0 };
+            star.line_to(34.0 + radius * angle.cos(), 34.0 + radius * angle.sin());
+        }
+        star.close();
+        painter.fill_path(&star, Color::RED);
+
+        let mut curves = Path::new();
+        curves
+            .move_to(70.0, 60.0)
+            .quad_to(96.0, 4.0, 122.0, 60.0)
+            .cubic_to(110.0, 40.0, 82.0, 80.0, 70.0, 60.0)
+            .close();
+        painter.fill_path(&curves, Color::BLUE);
+
+        let mut rounded = Path::new();
+        rounded
+            .move_to(8.0, 120.0)
+            .arc_to(8.0, 72.0, 56.0, 72.0, 20.0)
+            .arc_to(56.0, 120.0, 8.0, 120.0, 8.0)
+            .close();
+        painter.fill_path(&rounded, Color::rgba(0.0, 0.5, 0.0, 1.0));
+
+        // Two overlapping sub-paths with the same direction stay filled (non-zero rule)
+        let mut squares = Path::new();
+        squares
+            .move_to(70.0, 76.0)
+            .line_to(106.0, 76.0)
+            .line_to(106.0, 112.0)
+            .line_to(70.0, 112.0)
+            .close()
+            .move_to(88.0, 90.0)
+            .line_to(122.0, 90.0)
+            .line_to(122.0, 124.0)
+            .line_to(88.0, 124.0)
+            .close();
+        painter.fill_path(&squares, Color::rgba(0.5, 0.0, 0.5, 0.6));
+    };
+
+    stroke_path => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        let mut zigzag = Path::new();
+        zigzag.move_to(10.0, 40.0);
+        for i in 1..6 {
+            let y = if i % 2 == 0 { 40.0 } else { 10.0 };
+            zigzag.line_to(10.0 + i as f32 * 20.0, y);
+        }
+        painter.stroke_path(&zigzag, 5.0, Color::BLACK);
+
+        let mut triangle = Path::new();
+        triangle
+            .move_to(20.0, 60.0)
+            .line_to(60.0, 120.0)
+            .line_to(4.0, 110.0)
+            .close();
+        painter.stroke_path(&triangle, 3.0, Color::RED);
+
+        let mut wave = Path::new();
+        wave.move_to(70.0, 90.0)
+            .cubic_to(80.0, 50.0, 100.0, 130.0, 120.0, 70.0)
+            .quad_to(100.0, 60.0, 80.0, 120.0);
+        painter.stroke_path(&wave, 2.5, Color::rgba(0.0, 0.0, 1.0, 0.7));
+    };
+
+    stroked_primitives => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+        painter.stroke_ellipse(Point::new(8.0, 8.0), 24.0, 3.0, Color::RED);
+        painter.stroke_rectangle(
+            Point::new(70.0, 10.0),
+            Size::new(50.0, 40.0),
+            StrokeStyle::new(6.0).with_line_join(LineJoin::Round),
+            Color::BLUE,
+        );
+        painter.stroke_rectangle(
+            Point::new(70.5, 70.5),
+            Size::new(50.0, 20.0),
+            1.0,
+            Color::BLACK,
+        );
+        painter.stroke_rounded_rectangle(
+            Point::new(10.0, 70.0),
+            Size::new(50.0, 48.0),
+            12.0,
+            4.0,
+            Color::rgba(0.0, 0.5, 0.0, 0.8),
+        );
+        painter.stroke_ellipse(
+            Point::new(80.0, 96.0),
+            12.0,
+            StrokeStyle::new(8.0),
+            Color::rgba(0.5, 0.0, 0.5, 0.5),
+        );
+    };
+
+    line_joins_and_caps => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        let joins = [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel];
+        for (i, join) in joins.into_iter().enumerate() {
+            let x = 12.0 + i as f32 * 40.0;
+            let mut chevron = Path::new();
+            chevron
+                .move_to(x, 50.0)
+                .line_to(x + 14.0, 14.0)
+                .line_to(x + 28.0, 50.0);
+            painter.stroke_path(
+                &chevron,
+                StrokeStyle::new(10.0).with_line_join(join),
+                Color::BLACK,
+            );
+        }
+
+        let caps = [LineCap::Butt, LineCap::Round, LineCap::Square];
+        for (i, cap) in caps.into_iter().enumerate() {
+            let y = 72.0 + i as f32 * 20.0;
+            painter.line(
+                Point::new(24.0, y),
+                Point::new(104.0, y),
+                StrokeStyle::new(12.0).with_line_cap(cap),
+                Color::BLUE,
+            );
+        }
+        // Thin guides show where the lines end
+        painter.line(Point::new(24.0, 60.0), Point::new(24.0, 124.0), 1.0, Color::RED);
+        painter.line(Point::new(104.0, 60.0), Point::new(104.0, 124.0), 1.0, Color::RED);
+
+        // A sharp corner beyond the miter limit is beveled
+        let mut spike = Path::new();
+        spike.move_to(4.0, 126.0).line_to(64.0, 118.0).line_to(124.0, 126.0);
+        painter.stroke_path(
+            &spike,
+            StrokeStyle::new(4.0).with_miter_limit(2.0),
+            Color::rgba(0.0, 0.5, 0.0, 1.0),
+        );
+    };
+
+    dashes => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        // Grid lines
+        for i in 0..4 {
+            let y = 10.5 + i as f32 * 12.0;
+            painter.line(
+                Point::new(8.0, y),
+                Point::new(120.0, y),
+                StrokeStyle::new(1.0).with_dash(&[4.0, 2.0], i as f32 * 2.0),
+                Color::BLACK,
+            );
+        }
+        painter.line(
+            Point::new(8.0, 60.0),
+            Point::new(120.0, 60.0),
+            StrokeStyle::new(6.0)
+                .with_line_cap(LineCap::Round)
+                .with_dash(&[0.0, 12.0], 0.0),
+            Color::BLUE,
+        );
+
+        // Selection marquee
+        painter.stroke_rectangle(
+            Point::new(10.0, 76.0),
+            Size::new(50.0, 40.0),
+            StrokeStyle::new(2.0).with_dash(&[6.0], 3.0),
+            Color::RED,
+        );
+        painter.stroke_ellipse(
+            Point::new(72.0, 74.0),
+            22.0,
+            StrokeStyle::new(4.0).with_dash(&[10.0, 4.0, 2.0, 4.0], 0.0),
+            Color::rgba(0.0, 0.5, 0.0, 1.0),
+        );
+    };
+
+    gradients => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        let extends = [ExtendMode::Pad, ExtendMode::Repeat, ExtendMode::Reflect];
+        for (i, extend) in extends.into_iter().enumerate() {
+            let y = 4.0 + i as f32 * 20.0;
+            let gradient = LinearGradient::new(Point::new(40.0, 0.0), Point::new(72.0, 0.0))
+                .with_stop(0.0, Color::RED)
+                .with_stop(0.5, Color::rgba(1.0, 1.0, 0.0, 1.0))
+                .with_stop(1.0, Color::BLUE)
+                .with_extend(extend);
+            painter.rectangle(Point::new(4.0, y), Size::new(120.0, 16.0), gradient);
+        }
+
+        let spot = RadialGradient::new(Point::new(32.0, 96.0), 28.0)
+            .with_stop(0.0, Color::WHITE)
+            .with_stop(1.0, Color::rgba(0.0, 0.5, 0.0, 1.0));
+        painter.ellipse(Point::new(4.0, 68.0), 28.0, spot);
+
+        let rings = RadialGradient::new(Point::new(96.0, 96.0), 8.0)
+            .with_stop(0.0, Color::rgba(0.0, 0.0, 0.0, 0.0))
+            .with_stop(1.0, Color::BLACK)
+            .with_extend(ExtendMode::Repeat);
+        painter.rounded_rectangle(Point::new(68.0, 68.0), Size::new(56.0, 56.0), 8.0, rings);
+
+        let outline = LinearGradient::new(Point::new(0.0, 64.0), Point::new(0.0, 128.0))
+            .with_stop(0.0, Color::RED)
+            .with_stop(1.0, Color::BLUE);
+        painter.stroke_rectangle(Point::new(2.0, 66.0), Size::new(124.0, 60.0), 2.0, outline);
+    };
+
+    images => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        // 4x4 checkerboard of red, green, blue and half transparent black
+        let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [0, 0, 0, 128]];
+        let pixels = (0..16)
+            .flat_map(|i| colors[(i % 4 + i / 4) % 4])
+            .collect();
+        let image = Image::from_rgba(4, 4, pixels).unwrap();
+        let nearest = image.clone().with_filter(ImageFilter::Nearest);
+
+        painter.draw_image(&nearest, Rect::new(4.0, 4.0, 56.0, 56.0), None, 1.0);
+        painter.draw_image(&image, Rect::new(68.0, 4.0, 56.0, 56.0), None, 1.0);
+        // The top left 2x2 pixels, stretched
+        painter.draw_image(
+            &nearest,
+            Rect::new(4.0, 68.0, 56.0, 24.0),
+            Some(Rect::new(0.0, 0.0, 2.0, 2.0)),
+            1.0,
+        );
+        painter.draw_image(
+            &image,
+            Rect::new(4.0, 100.0, 56.0, 24.0),
+            Some(Rect::new(1.0, 1.0, 2.0, 2.0)),
+            1.0,
+        );
+        painter.draw_image(&nearest, Rect::new(68.0, 68.0, 56.0, 56.0), None, 0.5);
+    };
+
+    text => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+        let font = test_font();
+
+        painter.draw_text("Hello, Bly!", Point::new(4.0, 20.0), &font, 16.0, Color::BLACK);
+        // Kerning and the fi ligature
+        painter.draw_text("AVA office", Point::new(4.0, 44.0), &font, 18.0, Color::BLUE);
+        // Right-to-left text between left-to-right text
+        painter.draw_text("abc שלום 123", Point::new(4.0, 66.0), &font, 14.0, Color::BLACK);
+        // Arabic letters change their shape depending on their neighbours
+        painter.draw_text("سلام", Point::new(4.0, 90.0), &font, 18.0, Color::rgba(0.0, 0.5, 0.0, 1.0));
+
+        let gradient = LinearGradient::new(Point::new(0.0, 96.0), Point::new(0.0, 124.0))
+            .with_stop(0.0, Color::RED)
+            .with_stop(1.0, Color::BLUE);
+        painter.draw_text("Big", Point::new(56.0, 122.0), &font, 32.0, gradient);
+    };
+
+    text_layout => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+        let font = test_font();
+        let text = "Bly lays out text in lines that wrap at words.";
+
+        let aligns = [TextAlign::Left, TextAlign::Center, TextAlign::Justify];
+        for (i, align) in aligns.into_iter().enumerate() {
+            let layout = TextLayout::new(text, &font, 9.0)
+                .with_max_width(56.0)
+                .with_align(align);
+            let position = Point::new(4.0 + (i % 2) as f32 * 64.0, 4.0 + (i / 2) as f32 * 64.0);
+            painter.stroke_rectangle(
+                position - Vector::new(0.5, 0.5),
+                Size::new(57.0, layout.height() + 1.0),
+                1.0,
+                Color::rgba(0.0, 0.0, 1.0, 0.5),
+            );
+            painter.draw_text_layout(&layout, position, Color::BLACK);
+        }
+
+        // Highlights the box of the third glyph and places a caret after the first line
+        let layout = TextLayout::new("Hit\ntest", &font, 14.0).with_align(TextAlign::Right);
+        let position = Point::new(68.0, 68.0);
+        let glyph = layout.glyph_boxes()[2].rect;
+        painter.rectangle(
+            position + Vector::new(glyph.x, glyph.y),
+            glyph.size(),
+            Color::rgba(1.0, 1.0, 0.0, 1.0),
+        );
+        painter.draw_text_layout(&layout, position, Color::BLACK);
+        let caret = layout.caret_rect(3);
+        let top = position + Vector::new(caret.x, caret.y);
+        painter.line(
+            top,
+            top + Vector::new(0.0, caret.height),
+            1.0,
+            Color::RED,
+        );
+    };
+
+    transforms => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        // A square and its gradient rotated around their center
+        painter.save();
+        painter.translate(32.0, 32.0);
+        painter.rotate(std::f32::consts::FRAC_PI_4);
+        let gradient = LinearGradient::new(Point::new(-16.0, 0.0), Point::new(16.0, 0.0))
+            .with_stop(0.0, Color::RED)
+            .with_stop(1.0, Color::BLUE);
+        painter.rectangle(Point::new(-16.0, -16.0), Size::new(32.0, 32.0), gradient);
+        painter.restore();
+
+        // Scaling also widens the stroke
+        painter.save();
+        painter.translate(68.0, 8.0);
+        painter.scale(3.0, 1.5);
+        painter.stroke_ellipse(Point::new(0.0, 0.0), 8.0, 1.0, Color::BLACK);
+        painter.restore();
+
+        // Skewed text drawn from outlines
+        painter.save();
+        painter.translate(4.0, 90.0);
+        painter.skew(-0.3, 0.0);
+        painter.draw_text("Skew", Point::new(0.0, 0.0), &test_font(), 20.0, Color::BLACK);
+        painter.restore();
+
+        // An image turned upside down with set_transform()
+        let image = Image::from_rgba(2, 2, vec![
+            255, 0, 0, 255, 0, 255, 0, 255,
+            0, 0, 255, 255, 255, 255, 0, 255,
+        ])
+        .unwrap()
+        .with_filter(ImageFilter::Nearest);
+        painter.set_transform(
+            Transform2D::scale(1.0, -1.0).then(&Transform2D::translation(72.0, 124.0)),
+        );
+        painter.draw_image(&image, Rect::new(0.0, 0.0, 48.0, 48.0), None, 1.0);
+        painter.set_transform(Transform2D::identity());
+
+        // restore() without save() keeps the transform
+        painter.translate(100.0, 40.0);
+        painter.restore();
+        painter.rounded_rectangle(Point::new(0.0, 0.0), Size::new(20.0, 20.0), 6.0, Color::GREEN);
+    };
+
+    clips => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        // A panel whose content is larger than the panel
+        painter.push_clip_rect(Rect::new(8.0, 8.0, 48.0, 48.0));
+        painter.clear(Color::rgba(0.9, 0.9, 1.0, 1.0));
+        for i in 0..6 {
+            painter.rectangle(
+                Point::new(0.0, i as f32 * 12.0),
+                Size::new(64.0, 8.0),
+                Color::rgba(0.0, 0.0, i as f32 / 5.0, 1.0),
+            );
+        }
+        // Nested clips only keep their intersection
+        painter.push_clip_rounded_rect(Rect::new(32.0, 32.0, 40.0, 40.0), 10.0);
+        painter.rectangle(Point::new(0.0, 0.0), Size::new(128.0, 128.0), Color::RED);
+        painter.pop_clip();
+        painter.pop_clip();
+
+        // The clip follows the transform at the time it was pushed, not later ones
+        painter.save();
+        painter.translate(96.0, 32.0);
+        painter.rotate(std::f32::consts::FRAC_PI_4);
+        painter.push_clip_rect(Rect::new(-16.0, -16.0, 32.0, 32.0));
+        painter.restore();
+        painter.rectangle(Point::new(64.0, 0.0), Size::new(64.0, 64.0), Color::GREEN);
+        painter.pop_clip();
+
+        // A triangle clip around text
+        let mut triangle = Path::new();
+        triangle.move_to(8.0, 124.0).line_to(64.0, 68.0).line_to(120.0, 124.0).close();
+        painter.push_clip_path(&triangle);
+        painter.draw_text("CLIP", Point::new(20.0, 120.0), &test_font(), 36.0, Color::BLACK);
+        painter.pop_clip();
+        // Popping without a clip does nothing
+        painter.pop_clip();
+        painter.stroke_path(&triangle, 1.0, Color::BLUE);
+    };
+
+    layers => |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+
+        // Per-shape alpha shows the overlap, a layer with the same opacity does not
+        painter.ellipse(Point::new(4.0, 4.0), 16.0, Color::rgba(1.0, 0.0, 0.0, 0.5));
+        painter.ellipse(Point::new(24.0, 4.0), 16.0, Color::rgba(0.0, 0.0, 1.0, 0.5));
+        painter.push_layer(0.5, BlendMode::Normal);
+        painter.ellipse(Point::new(68.0, 4.0), 16.0, Color::RED);
+        painter.ellipse(Point::new(88.0, 4.0), 16.0, Color::BLUE);
+        // Clips pushed into a layer are popped with it
+        painter.push_clip_rect(Rect::new(68.0, 0.0, 8.0, 8.0));
+        painter.pop_layer();
+        // Popping without a layer does nothing
+        painter.pop_layer();
+
+        // Blend modes of single draws over a gradient backdrop
+        let gradient = LinearGradient::new(Point::new(0.0, 0.0), Point::new(128.0, 0.0))
+            .with_stop(0.0, Color::rgba(0.0, 0.5, 1.0, 1.0))
+            .with_stop(1.0, Color::rgba(1.0, 0.8, 0.0, 1.0));
+        painter.rectangle(Point::new(0.0, 44.0), Size::new(128.0, 40.0), gradient);
+        let modes = [
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Overlay,
+            BlendMode::Darken,
+            BlendMode::Lighten,
+            BlendMode::ColorDodge,
+            BlendMode::ColorBurn,
+            BlendMode::HardLight,
+            BlendMode::SoftLight,
+            BlendMode::Difference,
+            BlendMode::Exclusion,
+            BlendMode::Add,
+        ];
+        for (i, mode) in modes.into_iter().enumerate() {
+            painter.save();
+            painter.set_blend_mode(mode);
+            painter.rectangle(
+                Point::new(2.0 + (i % 6) as f32 * 21.0, 46.0 + (i / 6) as f32 * 19.0),
+                Size::new(18.0, 16.0),
+                Color::rgba(0.8, 0.2, 0.4, 1.0),
+            );
+            painter.restore();
+        }
+
+        // A whole group multiplied over the backdrop
+        painter.rectangle(Point::new(0.0, 88.0), Size::new(128.0, 40.0), Color::rgba(1.0, 0.9, 0.2, 1.0));
+        painter.push_layer(1.0, BlendMode::Multiply);
+        painter.ellipse(Point::new(24.0, 90.0), 18.0, Color::rgba(0.2, 0.6, 1.0, 1.0));
+        painter.ellipse(Point::new(64.0, 90.0), 18.0, Color::rgba(1.0, 0.3, 0.3, 1.0));
+        painter.pop_layer();
+        // Layers left pushed are popped at the end of the frame
+        painter.push_layer(0.5, BlendMode::Normal);
+        painter.rectangle(Point::new(104.0, 92.0), Size::new(20.0, 32.0), Color::BLACK);
+    };
+}
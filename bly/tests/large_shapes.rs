@@ -0,0 +1,80 @@
+//! Shapes far larger than the canvas, which must be drawn in bounded time and memory
+
+use bly::{Color, LineCap, LineJoin, Path, Point, StrokeStyle, Transform2D};
+
+/// Pixel (x, y) of a canvas of 64x64 pixels drawn by f
+fn draw(x: usize, y: usize, mut f: impl FnMut(&mut bly::Painter)) -> [u8; 4] {
+    let mut canvas = bly::create_offscreen_canvas(64, 64);
+    canvas
+        .draw(|painter| {
+            painter.clear(Color::WHITE);
+            f(painter);
+        })
+        .unwrap();
+    let pixels = canvas.read_pixels().unwrap();
+    let i = (y * 64 + x) * 4;
+    pixels[i..i + 4].try_into().unwrap()
+}
+
+#[test]
+fn huge_ellipses_are_filled() {
+    let radius = 2e7;
+    let pixel = draw(32, 32, |painter| {
+        painter.ellipse(Point::new(-radius, -radius), radius, Color::BLACK)
+    });
+    assert_eq!(pixel, [0, 0, 0, 255]);
+
+    // The tolerance is divided by the scale, so a small circle is enough when zoomed in
+    let pixel = draw(32, 32, |painter| {
+        painter.set_transform(Transform2D::scale(1e4, 1e4));
+        painter.ellipse(Point::new(-5e3, -5e3), 5e3, Color::BLACK)
+    });
+    assert_eq!(pixel, [0, 0, 0, 255]);
+}
+
+#[test]
+fn wide_round_caps_and_joins_are_drawn() {
+    // Only the round cap at the start of the line covers the pixel
+    let style = StrokeStyle::new(4e7).with_line_cap(LineCap::Round);
+    let pixel = draw(32, 32, |painter| {
+        painter.line(
+            Point::new(-1e7, 0.0),
+            Point::new(-1e7, 1.0),
+            style.clone(),
+            Color::BLACK,
+        )
+    });
+    assert_eq!(pixel, [0, 0, 0, 255]);
+
+    // A round join, of a width that is only large once zoomed in
+    let style = StrokeStyle::new(4e3).with_line_join(LineJoin::Round);
+    let pixel = draw(32, 32, |painter| {
+        painter.set_transform(Transform2D::scale(1e4, 1e4));
+        let mut path = Path::new();
+        path.move_to(-1e4, 1e3)
+            .line_to(-1e3, 1e3)
+            .line_to(-1e3, 1e4);
+        painter.stroke_path(&path, style.clone(), Color::BLACK)
+    });
+    assert_eq!(pixel, [0, 0, 0, 255]);
+}
+
+#[test]
+fn long_dashed_lines_are_drawn() {
+    let style = StrokeStyle::new(2.0).with_dash(&[2.0, 2.0], 0.0);
+    // Only the dashes inside of the canvas are built, with the phase they have there
+    for (from, to) in [(0.0, 1e8), (-1e8, 64.0)] {
+        let line = |painter: &mut bly::Painter| {
+            painter.line(
+                Point::new(from, 32.0),
+                Point::new(to, 32.0),
+                style.clone(),
+                Color::BLACK,
+            )
+        };
+        assert_eq!(draw(1, 32, line), [0, 0, 0, 255]);
+        assert_eq!(draw(3, 32, line), [255, 255, 255, 255]);
+        assert_eq!(draw(61, 31, line), [0, 0, 0, 255]);
+        assert_eq!(draw(63, 31, line), [255, 255, 255, 255]);
+    }
+}
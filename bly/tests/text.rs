@@ -0,0 +1,175 @@
+//! Text shaping tests
+
+use bly::{Font, GlyphRun, Point, TextAlign, TextLayout};
+
+fn test_font() -> Font {
+    Font::from_bytes(&include_bytes!("fonts/DejaVuSans.ttf")[..]).unwrap()
+}
+
+#[test]
+fn invalid_font_is_rejected() {
+    assert!(Font::from_bytes(vec![0u8; 16]).is_err());
+}
+
+#[test]
+fn kerning_moves_glyphs_closer() {
+    let font = test_font();
+    let pair = GlyphRun::new("AV", &font, 32.0).advance();
+    let single =
+        GlyphRun::new("A", &font, 32.0).advance() + GlyphRun::new("V", &font, 32.0).advance();
+    assert!(pair < single, "{} should be smaller than {}", pair, single);
+}
+
+#[test]
+fn ligatures_merge_glyphs() {
+    let font = test_font();
+    let run = GlyphRun::new("fi", &font, 16.0);
+    assert_eq!(run.glyphs().len(), 1);
+    assert_eq!(run.glyphs()[0].cluster, 0);
+}
+
+#[test]
+fn right_to_left_text_is_reversed() {
+    let font = test_font();
+    let text = "ab שלום";
+    let run = GlyphRun::new(text, &font, 16.0);
+    let clusters: Vec<usize> = run.glyphs().iter().map(|glyph| glyph.cluster).collect();
+
+    // "ab " stays in order, the Hebrew word is laid out from its last letter to its first
+    let mut hebrew: Vec<usize> = text.char_indices().skip(3).map(|(i, _)| i).collect();
+    hebrew.reverse();
+    assert_eq!(&clusters[..3], &[0, 1, 2]);
+    assert_eq!(&clusters[3..], &hebrew[..]);
+
+    let x: Vec<f32> = run.glyphs().iter().map(|glyph| glyph.x).collect();
+    assert!(x.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+#[test]
+fn size_scales_the_advance() {
+    let font = test_font();
+    let small = GlyphRun::new("Hello", &font, 10.0).advance();
+    let large = GlyphRun::new("Hello", &font, 20.0).advance();
+    assert!((large - small * 2.0).abs() < 0.01);
+    assert!(font.ascent(20.0) > 0.0 && font.descent(20.0) > 0.0);
+}
+
+#[test]
+fn line_breaks_are_left_out() {
+    let font = test_font();
+    assert_eq!(
+        GlyphRun::new("ab\n", &font, 16.0),
+        GlyphRun::new("ab", &font, 16.0)
+    );
+}
+
+fn line_texts(layout: &TextLayout) -> Vec<&str> {
+    layout
+        .lines()
+        .iter()
+        .map(|line| &layout.text()[line.range()])
+        .collect()
+}
+
+#[test]
+fn layout_wraps_at_word_boundaries() {
+    let font = test_font();
+    let text = "one two three four";
+    let layout = TextLayout::new(text, &font, 16.0);
+    assert_eq!(line_texts(&layout), ["one two three four"]);
+
+    let max_width = GlyphRun::new("one two", &font, 16.0)
+        .advance()
+        .max(GlyphRun::new("three four", &font, 16.0).advance())
+        + 1.0;
+    let layout = layout.with_max_width(max_width);
+    assert_eq!(line_texts(&layout), ["one two ", "three four"]);
+    assert!(layout.width() <= max_width);
+    assert_eq!(layout.height(), 2.0 * layout.line_height());
+    assert!(layout.lines()[1].baseline() > layout.lines()[0].baseline());
+}
+
+#[test]
+fn layout_breaks_long_words() {
+    let font = test_font();
+    let max_width = GlyphRun::new("abc", &font, 16.0).advance() + 0.5;
+    let layout = TextLayout::new("abcdefgh", &font, 16.0).with_max_width(max_width);
+    assert_eq!(line_texts(&layout), ["abc", "def", "gh"]);
+
+    // At least one character is placed on each line
+    let layout = TextLayout::new("ab", &font, 16.0).with_max_width(0.0);
+    assert_eq!(line_texts(&layout), ["a", "b"]);
+}
+
+#[test]
+fn layout_keeps_hard_line_breaks() {
+    let font = test_font();
+    let layout = TextLayout::new("a\n\nb\n", &font, 16.0);
+    assert_eq!(line_texts(&layout), ["a\n", "\n", "b\n", ""]);
+
+    let layout = TextLayout::new("", &font, 16.0);
+    assert_eq!(line_texts(&layout), [""]);
+    assert_eq!(layout.width(), 0.0);
+}
+
+#[test]
+fn layout_aligns_lines() {
+    let font = test_font();
+    let text = "wide line\nx";
+    let left = TextLayout::new(text, &font, 16.0);
+    let [wide, narrow] = [left.lines()[0].rect(), left.lines()[1].rect()];
+    assert_eq!((wide.x, narrow.x), (0.0, 0.0));
+
+    let right = left.clone().with_align(TextAlign::Right);
+    let line = right.lines()[1].rect();
+    assert!((line.x + line.width - wide.width).abs() < 0.01);
+
+    let center = left.with_align(TextAlign::Center);
+    let line = center.lines()[1].rect();
+    assert!((line.x - (wide.width - narrow.width) / 2.0).abs() < 0.01);
+}
+
+#[test]
+fn layout_justifies_all_but_the_last_line() {
+    let font = test_font();
+    let layout = TextLayout::new("aa b cc dd e ff", &font, 16.0)
+        .with_max_width(60.0)
+        .with_align(TextAlign::Justify);
+    let lines = layout.lines();
+    assert!(lines.len() > 1);
+    for line in &lines[..lines.len() - 1] {
+        assert_eq!(line.rect().width, 60.0);
+        let last = line.glyph_run().glyphs().last().unwrap();
+        assert!((last.x + last.advance - 60.0).abs() < 0.01);
+    }
+    assert!(lines[lines.len() - 1].rect().width < 60.0);
+}
+
+#[test]
+fn layout_hit_testing_and_carets() {
+    let font = test_font();
+    let layout = TextLayout::new("ab\ncd", &font, 16.0);
+    let boxes = layout.glyph_boxes();
+    assert_eq!(boxes.len(), 4);
+    assert_eq!(
+        boxes
+            .iter()
+            .map(|b| (b.cluster, b.line))
+            .collect::<Vec<_>>(),
+        [(0, 0), (1, 0), (3, 1), (4, 1)]
+    );
+
+    let d = boxes[3].rect;
+    let center = Point::new(d.x + d.width / 2.0, d.y + d.height / 2.0);
+    assert_eq!(layout.hit_test(center), 4);
+    // Beside and below the text
+    assert_eq!(layout.hit_test(Point::new(-10.0, 1.0)), 0);
+    assert_eq!(layout.hit_test(Point::new(1000.0, 1000.0)), 4);
+
+    assert_eq!(layout.caret_rect(4).x, d.x);
+    let end = layout.caret_rect(5);
+    assert!((end.x - (d.x + d.width)).abs() < 0.01);
+    assert_eq!(end.y, d.y);
+    // The line break belongs to the end of the first line
+    assert_eq!(layout.caret_rect(2).y, 0.0);
+}
@@ -0,0 +1,159 @@
+//! Tests of the Wayland backend against a running compositor
+//! Run them on a headless weston, with Mesa's llvmpipe for EGL:
+//! ```text
+//! weston --backend=headless-backend.so --socket=wayland-bly &
+//! WAYLAND_DISPLAY=wayland-bly LIBGL_ALWAYS_SOFTWARE=1 cargo test --test wayland
+//! ```
+//! The test is skipped when WAYLAND_DISPLAY is not set.
+#![cfg(all(target_os = "linux", feature = "wayland"))]
+
+use bly::{BlendMode, BlendSpace, Color, LinearGradient, Painter, Path, Point, Rect, Size};
+use winit::dpi::PhysicalSize;
+use winit::event_loop::{EventLoop, EventLoopBuilder};
+use winit::platform::wayland::EventLoopBuilderExtWayland;
+use winit::window::{Window, WindowBuilder};
+
+/// Draws shapes through every part of the renderer: blending, gradients, clips,
+/// layers and paths filled with the non-zero rule
+fn draw_scene(painter: &mut Painter) {
+    painter.clear(Color::WHITE);
+    painter.rectangle(
+        Point::new(0.0, 0.0),
+        Size::new(16.0, 16.0),
+        Color::RED.with_alpha(0.5),
+    );
+    let gradient = LinearGradient::new((16.0, 0.0), (48.0, 0.0))
+        .with_stop(0.0, Color::RED)
+        .with_stop(1.0, Color::BLUE.with_alpha(0.0));
+    painter.rectangle(Point::new(16.0, 0.0), Size::new(32.0, 16.0), gradient);
+
+    painter.push_clip_rounded_rect(Rect::new(0.0, 16.0, 32.0, 32.0), 10.0);
+    painter.rectangle(Point::ORIGIN, Size::new(64.0, 64.0), Color::BLUE);
+    painter.pop_clip();
+
+    painter.push_layer(0.5, BlendMode::Normal);
+    painter.rectangle(Point::new(32.0, 16.0), Size::new(32.0, 16.0), Color::GREEN);
+    painter.rectangle(Point::new(48.0, 16.0), Size::new(16.0, 16.0), Color::GREEN);
+    painter.pop_layer();
+
+    // A square with a hole wound the other way, then two overlapping squares wound the same way
+    let mut path = Path::new();
+    path.move_to(32.0, 32.0)
+        .line_to(48.0, 32.0)
+        .line_to(48.0, 48.0)
+        .line_to(32.0, 48.0)
+        .close();
+    path.move_to(36.0, 36.0)
+        .line_to(36.0, 44.0)
+        .line_to(44.0, 44.0)
+        .line_to(44.0, 36.0)
+        .close();
+    path.move_to(48.0, 32.0)
+        .line_to(64.0, 32.0)
+        .line_to(64.0, 48.0)
+        .line_to(48.0, 48.0)
+        .close();
+    path.move_to(52.0, 36.0)
+        .line_to(60.0, 36.0)
+        .line_to(60.0, 44.0)
+        .line_to(52.0, 44.0)
+        .close();
+    painter.fill_path(&path, Color::BLACK);
+}
+
+/// Points inside of the shapes of draw_scene(), away from their edges,
+/// where every renderer must give the same colors
+const SAMPLES: [(u32, u32); 12] = [
+    (8, 8),
+    (20, 8),
+    (32, 8),
+    (44, 8),
+    (16, 32),
+    (1, 17),
+    (40, 24),
+    (56, 24),
+    (34, 40),
+    (40, 40),
+    (50, 40),
+    (56, 40),
+];
+
+/// Draws the scene on a canvas of width x height pixels and compares the pixels read back
+/// with the offscreen canvas
+fn check_scene(canvas: &mut bly::Canvas, width: u32, height: u32) {
+    canvas.draw(draw_scene).unwrap();
+    let mut reference = bly::create_offscreen_canvas(width, height);
+    reference.draw(draw_scene).unwrap();
+
+    let pixels = canvas.read_pixels().unwrap();
+    let expected = reference.read_pixels().unwrap();
+    assert_eq!(pixels.len(), (width * height * 4) as usize);
+    for (x, y) in SAMPLES {
+        let i = ((y * width + x) * 4) as usize;
+        let (pixel, expected) = (&pixels[i..i + 4], &expected[i..i + 4]);
+        assert!(
+            pixel.iter().zip(expected).all(|(p, e)| p.abs_diff(*e) <= 3),
+            "pixel ({}, {}) is {:?} instead of {:?}",
+            x,
+            y,
+            pixel,
+            expected
+        );
+    }
+    // The scene covers 64x48 pixels, the rest of a larger canvas is only cleared
+    if width > 64 && height > 48 {
+        let i = (((height - 1) * width + width - 1) * 4) as usize;
+        assert_eq!(&pixels[i..i + 4], &[255, 255, 255, 255]);
+    }
+}
+
+/// Draws the scene, then resizes the canvas larger and smaller and draws it again
+fn check_canvas(window: &Window, canvas: &mut bly::Canvas) {
+    for (width, height) in [(64, 48), (96, 80), (64, 48)] {
+        window.set_inner_size(PhysicalSize::new(width, height));
+        canvas.resize(width, height);
+        check_scene(canvas, width, height);
+    }
+}
+
+fn create_window(event_loop: &EventLoop<()>) -> Window {
+    WindowBuilder::new()
+        .with_title("bly wayland test")
+        .with_inner_size(PhysicalSize::new(64, 48))
+        .build(event_loop)
+        .unwrap()
+}
+
+// Only one event loop can be created by a process, so every check is in one test
+#[test]
+fn canvases_are_drawn_like_offscreen() {
+    if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        eprintln!("WAYLAND_DISPLAY is not set, skipping");
+        return;
+    }
+    let event_loop = EventLoopBuilder::new()
+        .with_wayland()
+        .with_any_thread(true)
+        .build();
+
+    // OpenGL ES through EGL, which cannot blend in linear light
+    let window = create_window(&event_loop);
+    let mut canvas = bly::create_canvas(&window).unwrap();
+    assert!(
+        canvas.set_blend_space(BlendSpace::Linear).is_err(),
+        "EGL is not usable, the canvas fell back to wl_shm"
+    );
+    check_canvas(&window, &mut canvas);
+    drop(canvas);
+    drop(window);
+
+    // The CPU and wl_shm buffers
+    std::env::set_var("BLY_WAYLAND_SHM", "1");
+    let window = create_window(&event_loop);
+    let canvas = bly::create_canvas(&window);
+    std::env::remove_var("BLY_WAYLAND_SHM");
+    let mut canvas = canvas.unwrap();
+    assert!(canvas.set_blend_space(BlendSpace::Linear).is_ok());
+    canvas.set_blend_space(BlendSpace::Srgb).unwrap();
+    check_canvas(&window, &mut canvas);
+}
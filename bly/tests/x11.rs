@@ -0,0 +1,589 @@
+//! Tests of the X11 backends against a running X server
+//! Run them under Xvfb with `xvfb-run cargo test --test x11`.
+//! Every test is skipped when DISPLAY is not set.
+#![cfg(all(target_os = "linux", any(feature = "xlib", feature = "xcb")))]
+
+use bly::{BlendSpace, Color, Error, LinearGradient, Painter, Point, Size};
+use std::sync::atomic::{AtomicI16, Ordering};
+
+/// Tests run in parallel, so every window gets its own place on the screen
+/// to keep the others from covering it
+static NEXT_X: AtomicI16 = AtomicI16::new(0);
+
+fn next_x() -> i16 {
+    NEXT_X.fetch_add(128, Ordering::Relaxed)
+}
+
+/// Returns false, which skips the test, when there is no X server to connect to
+fn has_display() -> bool {
+    if std::env::var_os("DISPLAY").is_none() {
+        eprintln!("DISPLAY is not set, skipping");
+        return false;
+    }
+    true
+}
+
+/// Draws a blue square on the background and checks the pixels read back from the window
+fn check_square(canvas: &mut bly::Canvas, width: u32, height: u32, background: Color) {
+    canvas
+        .draw(|painter| {
+            painter.clear(background);
+            painter.rectangle(
+                Point::new(16.0, 16.0),
+                Size::new(16.0, 16.0),
+                Color::rgba(0.0, 0.0, 1.0, 1.0),
+            );
+        })
+        .unwrap();
+
+    let pixels = canvas.read_pixels().unwrap();
+    assert_eq!(pixels.len(), (width * height * 4) as usize);
+    let pixel = |x: u32, y: u32| {
+        let i = ((y * width + x) * 4) as usize;
+        &pixels[i..i + 4]
+    };
+    let expected = match background.a > 0.0 {
+        true => background.to_rgba8(),
+        false => [0, 0, 0, 0],
+    };
+    assert_eq!(pixel(0, 0), expected);
+    assert_eq!(pixel(width - 1, height - 1), expected);
+    assert_eq!(pixel(20, 20), [0, 0, 255, 255]);
+}
+
+/// Draws translucent colors and a gradient on a canvas of 64x48 pixels
+/// and checks that they are blended like on the offscreen canvas
+fn check_colors(canvas: &mut bly::Canvas) {
+    let draw = |painter: &mut Painter| {
+        painter.clear(Color::WHITE);
+        painter.rectangle(
+            Point::new(0.0, 0.0),
+            Size::new(16.0, 16.0),
+            Color::RED.with_alpha(0.5),
+        );
+        // Interpolated with premultiplied alpha, the red fades out without turning purple
+        let gradient = LinearGradient::new(Point::new(16.0, 0.0), Point::new(48.0, 0.0))
+            .with_stop(0.0, Color::RED)
+            .with_stop(1.0, Color::BLUE.with_alpha(0.0));
+        painter.rectangle(Point::new(16.0, 0.0), Size::new(32.0, 16.0), gradient);
+    };
+    canvas.draw(draw).unwrap();
+    let mut reference = bly::create_offscreen_canvas(64, 48);
+    reference.draw(draw).unwrap();
+
+    let pixels = canvas.read_pixels().unwrap();
+    let expected = reference.read_pixels().unwrap();
+    for x in (0..64).step_by(4) {
+        let i = (8 * 64 + x) * 4;
+        let (pixel, expected) = (&pixels[i..i + 4], &expected[i..i + 4]);
+        assert!(
+            pixel.iter().zip(expected).all(|(p, e)| p.abs_diff(*e) <= 2),
+            "pixel ({}, 8) is {:?} instead of {:?}",
+            x,
+            pixel,
+            expected
+        );
+    }
+
+    assert!(matches!(
+        canvas.set_blend_space(BlendSpace::Linear),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[cfg(feature = "xcb")]
+mod xcb {
+    use super::*;
+    use raw_window_handle::{
+        HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+        XcbDisplayHandle, XcbWindowHandle,
+    };
+    use std::ffi::{c_char, c_int, c_void};
+
+    #[repr(C)]
+    struct XcbIterator<T> {
+        data: *mut T,
+        rem: c_int,
+        index: c_int,
+    }
+
+    /// The leading fields of xcb_screen_t
+    #[repr(C)]
+    struct XcbScreen {
+        root: u32,
+        default_colormap: u32,
+        white_pixel: u32,
+        black_pixel: u32,
+        current_input_masks: u32,
+        width_in_pixels: u16,
+        height_in_pixels: u16,
+        width_in_millimeters: u16,
+        height_in_millimeters: u16,
+        min_installed_maps: u16,
+        max_installed_maps: u16,
+        root_visual: u32,
+    }
+
+    #[link(name = "xcb")]
+    extern "C" {
+        fn xcb_connect(display: *const c_char, screen: *mut c_int) -> *mut c_void;
+        fn xcb_disconnect(c: *mut c_void);
+        fn xcb_connection_has_error(c: *mut c_void) -> c_int;
+        fn xcb_get_setup(c: *mut c_void) -> *const c_void;
+        fn xcb_setup_roots_iterator(setup: *const c_void) -> XcbIterator<XcbScreen>;
+        fn xcb_screen_next(i: *mut XcbIterator<XcbScreen>);
+        fn xcb_generate_id(c: *mut c_void) -> u32;
+        fn xcb_create_window(
+            c: *mut c_void,
+            depth: u8,
+            wid: u32,
+            parent: u32,
+            x: i16,
+            y: i16,
+            width: u16,
+            height: u16,
+            border_width: u16,
+            class: u16,
+            visual: u32,
+            value_mask: u32,
+            value_list: *const c_void,
+        ) -> u32;
+        fn xcb_configure_window(
+            c: *mut c_void,
+            window: u32,
+            value_mask: u16,
+            value_list: *const c_void,
+        ) -> u32;
+        fn xcb_map_window(c: *mut c_void, window: u32) -> u32;
+        fn xcb_destroy_window(c: *mut c_void, window: u32) -> u32;
+        fn xcb_flush(c: *mut c_void) -> c_int;
+    }
+
+    const XCB_WINDOW_CLASS_INPUT_OUTPUT: u16 = 1;
+    const XCB_CONFIG_WINDOW_WIDTH: u16 = 4;
+    const XCB_CONFIG_WINDOW_HEIGHT: u16 = 8;
+
+    /// A connection to the X server of DISPLAY
+    struct Connection {
+        connection: *mut c_void,
+        screen: c_int,
+    }
+
+    impl Connection {
+        /// Returns None, which skips the test, when there is no X server to connect to
+        fn open() -> Option<Self> {
+            if !has_display() {
+                return None;
+            }
+            let mut screen = 0;
+            let connection = unsafe { xcb_connect(std::ptr::null(), &mut screen) };
+            if unsafe { xcb_connection_has_error(connection) } != 0 {
+                unsafe { xcb_disconnect(connection) };
+                eprintln!("Cannot connect to the X server, skipping");
+                return None;
+            }
+            Some(Self { connection, screen })
+        }
+
+        fn screen(&self) -> &XcbScreen {
+            unsafe {
+                let mut screens = xcb_setup_roots_iterator(xcb_get_setup(self.connection));
+                for _ in 0..self.screen {
+                    xcb_screen_next(&mut screens);
+                }
+                &*screens.data
+            }
+        }
+
+        /// Creates and maps a window of the default visual.
+        /// With pass_visual false, the window handle leaves the visual for Bly to look up
+        fn create_window(&self, width: u16, height: u16, pass_visual: bool) -> XcbWindow<'_> {
+            let screen = self.screen();
+            let x = next_x();
+            unsafe {
+                let window = xcb_generate_id(self.connection);
+                xcb_create_window(
+                    self.connection,
+                    0,
+                    window,
+                    screen.root,
+                    x,
+                    0,
+                    width,
+                    height,
+                    0,
+                    XCB_WINDOW_CLASS_INPUT_OUTPUT,
+                    screen.root_visual,
+                    0,
+                    std::ptr::null(),
+                );
+                xcb_map_window(self.connection, window);
+                xcb_flush(self.connection);
+                XcbWindow {
+                    connection: self,
+                    window,
+                    visual: if pass_visual { screen.root_visual } else { 0 },
+                }
+            }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            unsafe { xcb_disconnect(self.connection) }
+        }
+    }
+
+    struct XcbWindow<'a> {
+        connection: &'a Connection,
+        window: u32,
+        visual: u32,
+    }
+
+    impl XcbWindow<'_> {
+        fn resize(&self, width: u32, height: u32) {
+            let values = [width, height];
+            unsafe {
+                xcb_configure_window(
+                    self.connection.connection,
+                    self.window,
+                    XCB_CONFIG_WINDOW_WIDTH | XCB_CONFIG_WINDOW_HEIGHT,
+                    values.as_ptr() as *const c_void,
+                );
+                xcb_flush(self.connection.connection);
+            }
+        }
+    }
+
+    impl Drop for XcbWindow<'_> {
+        fn drop(&mut self) {
+            unsafe {
+                xcb_destroy_window(self.connection.connection, self.window);
+                xcb_flush(self.connection.connection);
+            }
+        }
+    }
+
+    unsafe impl HasRawWindowHandle for XcbWindow<'_> {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            let mut handle = XcbWindowHandle::empty();
+            handle.window = self.window;
+            handle.visual_id = self.visual;
+            RawWindowHandle::Xcb(handle)
+        }
+    }
+
+    unsafe impl HasRawDisplayHandle for XcbWindow<'_> {
+        fn raw_display_handle(&self) -> RawDisplayHandle {
+            let mut handle = XcbDisplayHandle::empty();
+            handle.connection = self.connection.connection;
+            handle.screen = self.connection.screen;
+            RawDisplayHandle::Xcb(handle)
+        }
+    }
+
+    #[test]
+    fn window_is_drawn_on() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48, true);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::RED);
+    }
+
+    #[test]
+    fn visual_is_looked_up_when_missing() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48, false);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::RED);
+    }
+
+    #[test]
+    fn canvas_follows_window_resizes() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48, true);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::RED);
+
+        window.resize(96, 80);
+        canvas.resize(96, 80);
+        check_square(&mut canvas, 96, 80, Color::RED);
+    }
+
+    #[test]
+    fn colors_are_drawn_like_offscreen() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48, true);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_colors(&mut canvas);
+    }
+}
+
+#[cfg(feature = "xlib")]
+mod xlib {
+    use super::*;
+    use raw_window_handle::{
+        HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+        XlibDisplayHandle, XlibWindowHandle,
+    };
+    use std::ffi::{c_int, c_ulong, c_void};
+    use x11::xlib::{
+        AllocNone, CWBackPixel, CWBorderPixel, CWColormap, Display, InputOutput, TrueColor,
+        XAllPlanes, XCloseDisplay, XCreateColormap, XCreateSimpleWindow, XCreateWindow,
+        XDefaultScreen, XDestroyImage, XDestroyWindow, XFreeColormap, XGetImage, XGetPixel,
+        XMapWindow, XMatchVisualInfo, XOpenDisplay, XResizeWindow, XRootWindow,
+        XSetWindowAttributes, XSync, XVisualInfo, ZPixmap,
+    };
+
+    /// A connection to the X server of DISPLAY, opened by the test like a toolkit would
+    struct Connection {
+        display: *mut Display,
+        screen: c_int,
+    }
+
+    impl Connection {
+        /// Returns None, which skips the test, when there is no X server to connect to
+        fn open() -> Option<Self> {
+            if !has_display() {
+                return None;
+            }
+            let display = unsafe { XOpenDisplay(std::ptr::null()) };
+            if display.is_null() {
+                eprintln!("Cannot connect to the X server, skipping");
+                return None;
+            }
+            let screen = unsafe { XDefaultScreen(display) };
+            Some(Self { display, screen })
+        }
+
+        /// Creates and maps a window of the default visual
+        fn create_window(&self, width: u32, height: u32) -> XlibWindow<'_> {
+            unsafe {
+                let window = XCreateSimpleWindow(
+                    self.display,
+                    XRootWindow(self.display, self.screen),
+                    next_x() as c_int,
+                    0,
+                    width,
+                    height,
+                    0,
+                    0,
+                    0,
+                );
+                self.map(window, 0)
+            }
+        }
+
+        /// Creates and maps a window with a 32 bits ARGB visual,
+        /// or returns None if the server has no such visual
+        fn create_argb_window(&self, width: u32, height: u32) -> Option<XlibWindow<'_>> {
+            unsafe {
+                let mut info: XVisualInfo = std::mem::zeroed();
+                if XMatchVisualInfo(self.display, self.screen, 32, TrueColor, &mut info) == 0 {
+                    eprintln!("The X server has no ARGB visual, skipping");
+                    return None;
+                }
+                let root = XRootWindow(self.display, self.screen);
+                let colormap = XCreateColormap(self.display, root, info.visual, AllocNone);
+                let mut attributes: XSetWindowAttributes = std::mem::zeroed();
+                attributes.colormap = colormap;
+                let window = XCreateWindow(
+                    self.display,
+                    root,
+                    next_x() as c_int,
+                    0,
+                    width,
+                    height,
+                    0,
+                    info.depth,
+                    InputOutput as u32,
+                    info.visual,
+                    CWColormap | CWBorderPixel | CWBackPixel,
+                    &mut attributes,
+                );
+                Some(self.map(window, colormap))
+            }
+        }
+
+        unsafe fn map(&self, window: c_ulong, colormap: c_ulong) -> XlibWindow<'_> {
+            XMapWindow(self.display, window);
+            XSync(self.display, 0);
+            XlibWindow {
+                connection: self,
+                window,
+                colormap,
+            }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            unsafe {
+                XCloseDisplay(self.display);
+            }
+        }
+    }
+
+    struct XlibWindow<'a> {
+        connection: &'a Connection,
+        window: c_ulong,
+        colormap: c_ulong,
+    }
+
+    impl XlibWindow<'_> {
+        fn resize(&self, width: u32, height: u32) {
+            unsafe {
+                XResizeWindow(self.connection.display, self.window, width, height);
+                XSync(self.connection.display, 0);
+            }
+        }
+
+        /// Pixel of the window as 0xRRGGBB, read from the server
+        fn pixel(&self, x: i32, y: i32) -> c_ulong {
+            unsafe {
+                let image = XGetImage(
+                    self.connection.display,
+                    self.window,
+                    x,
+                    y,
+                    1,
+                    1,
+                    XAllPlanes(),
+                    ZPixmap,
+                );
+                assert!(!image.is_null());
+                let pixel = XGetPixel(image, 0, 0) & 0xffffff;
+                XDestroyImage(image);
+                pixel
+            }
+        }
+    }
+
+    impl Drop for XlibWindow<'_> {
+        fn drop(&mut self) {
+            unsafe {
+                XDestroyWindow(self.connection.display, self.window);
+                if self.colormap != 0 {
+                    XFreeColormap(self.connection.display, self.colormap);
+                }
+                XSync(self.connection.display, 0);
+            }
+        }
+    }
+
+    unsafe impl HasRawWindowHandle for XlibWindow<'_> {
+        fn raw_window_handle(&self) -> RawWindowHandle {
+            let mut handle = XlibWindowHandle::empty();
+            handle.window = self.window;
+            RawWindowHandle::Xlib(handle)
+        }
+    }
+
+    unsafe impl HasRawDisplayHandle for XlibWindow<'_> {
+        fn raw_display_handle(&self) -> RawDisplayHandle {
+            let mut handle = XlibDisplayHandle::empty();
+            handle.display = self.connection.display as *mut c_void;
+            handle.screen = self.connection.screen;
+            RawDisplayHandle::Xlib(handle)
+        }
+    }
+
+    #[test]
+    fn window_is_drawn_on() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::RED);
+    }
+
+    #[test]
+    fn argb_window_is_cleared_to_transparent() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = match connection.create_argb_window(64, 48) {
+            Some(window) => window,
+            None => return,
+        };
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::rgba(0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn finished_frame_is_copied_to_the_window() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::RED);
+        assert_eq!(window.pixel(0, 0), 0xff0000);
+        assert_eq!(window.pixel(20, 20), 0x0000ff);
+    }
+
+    #[test]
+    fn last_frame_is_kept_on_resize() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::RED);
+
+        window.resize(96, 80);
+        canvas.draw(|_| {}).unwrap();
+        let pixels = canvas.read_pixels().unwrap();
+        assert_eq!(pixels.len(), 96 * 80 * 4);
+        let i = (20 * 96 + 20) * 4;
+        assert_eq!(&pixels[i..i + 4], [0, 0, 255, 255]);
+        assert_eq!(window.pixel(20, 20), 0x0000ff);
+    }
+
+    #[test]
+    fn snapshot_keeps_the_size_of_the_last_frame() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_square(&mut canvas, 64, 48, Color::RED);
+
+        // Nothing is drawn after the resize, so the snapshot is still the frame of 64x48 pixels
+        window.resize(96, 80);
+        canvas.resize(96, 80);
+        let snapshot = canvas.snapshot().unwrap();
+        assert_eq!((snapshot.width(), snapshot.height()), (64, 48));
+        let i = (20 * 64 + 20) * 4;
+        assert_eq!(&snapshot.pixels()[i..i + 4], [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn colors_are_drawn_like_offscreen() {
+        let connection = match Connection::open() {
+            Some(connection) => connection,
+            None => return,
+        };
+        let window = connection.create_window(64, 48);
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        check_colors(&mut canvas);
+    }
+}
@@ -0,0 +1,241 @@
+//! Checks that the Xlib backend releases its native resources
+//! Run under Xvfb with `xvfb-run cargo test --test x11_leaks`.
+//! The memory of the whole process is measured, so the test has a binary of its own.
+//! The resources held by the client in the X server, such as the pixmaps of back buffers,
+//! are counted with libXRes, which is loaded at run time. They are not checked if it is missing.
+//! The test is skipped when DISPLAY is not set.
+#![cfg(all(target_os = "linux", feature = "xlib"))]
+
+use bly::Color;
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, XlibDisplayHandle,
+    XlibWindowHandle,
+};
+use std::ffi::{c_char, c_int, c_long, c_uint, c_ulong, c_void, CStr};
+use x11::xlib::{
+    Display, XCloseDisplay, XCreateSimpleWindow, XDefaultScreen, XDestroyWindow, XFree, XMapWindow,
+    XOpenDisplay, XResizeWindow, XRootWindow, XSync,
+};
+
+/// Memory that may be allocated by the process (or by the X server for its pixmaps)
+/// during the measured loops without counting as a leak
+const TOLERANCE: usize = 1024 * 1024;
+
+/// Resources of the client in the X server that may be created during the measured loops,
+/// e.g. by caches of Xlib and Cairo
+const RESOURCE_TOLERANCE: usize = 16;
+
+// libc, declared by hand as it is not among our dependencies
+extern "C" {
+    fn sysconf(name: c_int) -> c_long;
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+const SC_PAGESIZE: c_int = 30;
+const RTLD_NOW: c_int = 2;
+
+/// XResType
+#[repr(C)]
+struct ResourceType {
+    resource_type: c_ulong,
+    count: c_uint,
+}
+
+type QueryExtension = unsafe extern "C" fn(*mut Display, *mut c_int, *mut c_int) -> c_int;
+type QueryClientResources =
+    unsafe extern "C" fn(*mut Display, c_ulong, *mut c_int, *mut *mut ResourceType) -> c_int;
+type QueryClientPixmapBytes = unsafe extern "C" fn(*mut Display, c_ulong, *mut c_ulong) -> c_int;
+
+/// The functions of libXRes used to count the resources of a client
+struct XRes {
+    query_client_resources: QueryClientResources,
+    query_client_pixmap_bytes: QueryClientPixmapBytes,
+}
+
+impl XRes {
+    /// Loads libXRes, or returns None if it or the X-Resource extension is missing
+    fn load(display: *mut Display) -> Option<Self> {
+        unsafe {
+            let library = dlopen(c"libXRes.so.1".as_ptr(), RTLD_NOW);
+            if library.is_null() {
+                return None;
+            }
+            let symbol = |name: &CStr| {
+                let symbol = dlsym(library, name.as_ptr());
+                (!symbol.is_null()).then_some(symbol)
+            };
+            let query_extension =
+                std::mem::transmute::<*mut c_void, QueryExtension>(symbol(c"XResQueryExtension")?);
+            let (mut event_base, mut error_base) = (0, 0);
+            if query_extension(display, &mut event_base, &mut error_base) == 0 {
+                return None;
+            }
+            Some(Self {
+                query_client_resources: std::mem::transmute::<*mut c_void, QueryClientResources>(
+                    symbol(c"XResQueryClientResources")?,
+                ),
+                query_client_pixmap_bytes: std::mem::transmute::<*mut c_void, QueryClientPixmapBytes>(
+                    symbol(c"XResQueryClientPixmapBytes")?,
+                ),
+            })
+        }
+    }
+
+    /// Number of resources and bytes of pixmaps held in the server by the client owning xid
+    fn usage(&self, display: *mut Display, xid: c_ulong) -> (usize, usize) {
+        unsafe {
+            XSync(display, 0);
+            let (mut count, mut types) = (0, std::ptr::null_mut());
+            (self.query_client_resources)(display, xid, &mut count, &mut types);
+            let resources = match types.is_null() {
+                true => 0,
+                false => {
+                    let types = std::slice::from_raw_parts(types, count as usize);
+                    types.iter().map(|t| t.count as usize).sum()
+                }
+            };
+            if !types.is_null() {
+                XFree(types as *mut c_void);
+            }
+            let mut bytes = 0;
+            (self.query_client_pixmap_bytes)(display, xid, &mut bytes);
+            (resources, bytes as usize)
+        }
+    }
+}
+
+struct Window {
+    display: *mut Display,
+    window: c_ulong,
+}
+
+impl Window {
+    /// Opens a connection and maps a window on it,
+    /// or returns None to skip the test when there is no X server
+    fn open(width: u32, height: u32) -> Option<Self> {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("DISPLAY is not set, skipping");
+            return None;
+        }
+        unsafe {
+            let display = XOpenDisplay(std::ptr::null());
+            if display.is_null() {
+                eprintln!("Cannot connect to the X server, skipping");
+                return None;
+            }
+            let root = XRootWindow(display, XDefaultScreen(display));
+            let window = XCreateSimpleWindow(display, root, 0, 0, width, height, 0, 0, 0);
+            XMapWindow(display, window);
+            XSync(display, 0);
+            Some(Self { display, window })
+        }
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        unsafe {
+            XResizeWindow(self.display, self.window, width, height);
+            XSync(self.display, 0);
+        }
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        unsafe {
+            XDestroyWindow(self.display, self.window);
+            XCloseDisplay(self.display);
+        }
+    }
+}
+
+unsafe impl HasRawWindowHandle for Window {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let mut handle = XlibWindowHandle::empty();
+        handle.window = self.window;
+        RawWindowHandle::Xlib(handle)
+    }
+}
+
+unsafe impl HasRawDisplayHandle for Window {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        let mut handle = XlibDisplayHandle::empty();
+        handle.display = self.display as *mut c_void;
+        RawDisplayHandle::Xlib(handle)
+    }
+}
+
+/// Resident memory of the process in bytes
+fn resident_memory() -> usize {
+    let statm = std::fs::read_to_string("/proc/self/statm").unwrap();
+    let pages: usize = statm.split_whitespace().nth(1).unwrap().parse().unwrap();
+    pages * unsafe { sysconf(SC_PAGESIZE) } as usize
+}
+
+/// Runs f a few times so that caches fill up, then fails if running it count more times
+/// makes the process grow by more than TOLERANCE,
+/// or the resources of the window's client in the X server grow
+fn assert_no_growth(window: &Window, xres: Option<&XRes>, count: usize, mut f: impl FnMut(usize)) {
+    for i in 0..count / 4 {
+        f(i);
+    }
+    let before = resident_memory();
+    let server_before = xres.map(|xres| xres.usage(window.display, window.window));
+    for i in 0..count {
+        f(i);
+    }
+    let after = resident_memory();
+    assert!(
+        after <= before + TOLERANCE,
+        "the process grew by {} bytes in {} iterations",
+        after.saturating_sub(before),
+        count
+    );
+
+    if let (Some(xres), Some((resources_before, bytes_before))) = (xres, server_before) {
+        let (resources, bytes) = xres.usage(window.display, window.window);
+        assert!(
+            resources <= resources_before + RESOURCE_TOLERANCE,
+            "the client holds {} more resources in the X server after {} iterations",
+            resources.saturating_sub(resources_before),
+            count
+        );
+        assert!(
+            bytes <= bytes_before + TOLERANCE,
+            "the pixmaps of the client grew by {} bytes in {} iterations",
+            bytes.saturating_sub(bytes_before),
+            count
+        );
+    }
+}
+
+// Both checks are in one test, as tests running in parallel would measure each other
+#[test]
+fn native_resources_are_released() {
+    let window = match Window::open(64, 48) {
+        Some(window) => window,
+        None => return,
+    };
+    let xres = XRes::load(window.display);
+    if xres.is_none() {
+        eprintln!("libXRes or the X-Resource extension is missing, only the process is measured");
+    }
+
+    // The window surface and its back buffer are resized
+    let mut canvas = bly::create_canvas(&window).unwrap();
+    assert_no_growth(&window, xres.as_ref(), 4000, |i| {
+        window.resize(64 + (i % 2) as u32, 48 + (i % 2) as u32);
+        canvas
+            .draw(|painter| painter.clear(Color::rgba(1.0, 0.0, 0.0, 1.0)))
+            .unwrap();
+    });
+    drop(canvas);
+
+    // Everything is released with the canvas
+    assert_no_growth(&window, xres.as_ref(), 2000, |_| {
+        let mut canvas = bly::create_canvas(&window).unwrap();
+        canvas
+            .draw(|painter| painter.clear(Color::rgba(1.0, 0.0, 0.0, 1.0)))
+            .unwrap();
+    });
+}